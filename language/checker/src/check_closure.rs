@@ -0,0 +1,235 @@
+use data::tokens::Span;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::code::{
+    EffectType, Effects, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression, FinalizedField,
+};
+use syntax::program::function::FinalizedCodeBody;
+use syntax::program::syntax::Syntax;
+use syntax::program::types::FinalizedTypes;
+use syntax::SimpleVariableManager;
+
+use crate::check_code::verify_code;
+use crate::check_function::has_valued_return;
+use crate::drops::insert_drops;
+use crate::{get_return, CodeVerifier};
+
+/// Checks a closure, verifying its code and finding the variables it captures from the outer scope
+pub async fn check_closure(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    effect: Effects,
+) -> Result<FinalizedEffects, ParsingError> {
+    let (arguments, returning, body) = if let EffectType::Closure(arguments, returning, body) = effect.types {
+        (arguments, returning, body)
+    } else {
+        unreachable!()
+    };
+
+    // The closure can see every variable in the outer scope, plus its arguments.
+    // Captured variables are copies, so changing them would never change the outer variable.
+    let mut closure_variables = variables.clone();
+    for name in variables.variables.keys() {
+        closure_variables.immutable.entry(name.clone()).or_insert_with(|| effect.span.clone());
+    }
+    let mut fields = Vec::default();
+    for (name, types) in arguments {
+        let types = Syntax::parse_type(
+            code_verifier.syntax.clone(),
+            effect.span.clone(),
+            code_verifier.resolver.boxed_clone(),
+            types,
+            vec![],
+        )
+        .await?
+        .finalize(code_verifier.syntax.clone())
+        .await;
+        // Arguments are references, just like function arguments.
+        closure_variables.variables.insert(name.clone(), FinalizedTypes::Reference(Box::new(types.clone())));
+        closure_variables.immutable.insert(name.clone(), effect.span.clone());
+        fields.push(FinalizedField { field_type: types, name });
+    }
+
+    let returning = match returning {
+        Some(returning) => Some(
+            Syntax::parse_type(
+                code_verifier.syntax.clone(),
+                effect.span.clone(),
+                code_verifier.resolver.boxed_clone(),
+                returning,
+                vec![],
+            )
+            .await?
+            .finalize(code_verifier.syntax.clone())
+            .await,
+        ),
+        None => None,
+    };
+
+    // Closures without a return type take it from their first return with a value, like functions
+    let mut closure_verifier = CodeVerifier {
+        process_manager: code_verifier.process_manager,
+        resolver: code_verifier.resolver.boxed_clone(),
+        return_type: returning.clone(),
+        infer_return: returning.is_none() && has_valued_return(&body),
+        inferring: None,
+        syntax: code_verifier.syntax.clone(),
+    };
+    let mut code = verify_code(&mut closure_verifier, &mut closure_variables.clone(), body).await?;
+    let returning = closure_verifier.return_type;
+
+    // Checks the return type exists
+    if !code.returns {
+        if returning.is_none() {
+            code.expressions.push(FinalizedExpression::new(
+                ExpressionType::Return(Span::default()),
+                FinalizedEffects::new(Span::default(), FinalizedEffectType::NOP),
+            ));
+        } else {
+            return Err(effect.span.make_error(ParsingMessage::MissingReturn("the closure".to_string())));
+        }
+    }
+
+    // Any outer variable the closure loads is captured, unless an argument or a variable declared before the load
+    // shadows it.
+    let mut captures = Vec::default();
+    find_loads(&code, &mut fields.iter().map(|field| field.name.clone()).collect(), &mut captures);
+    let captures = captures
+        .into_iter()
+        .filter_map(|name| variables.variables.get(&name).cloned().map(|types| (name, types)))
+        .collect::<Vec<_>>();
+
+    // The closure doesn't own its arguments or captures, so they're never freed by it.
+    insert_drops(&mut code, closure_variables, &code_verifier.syntax).await;
+
+    return Ok(FinalizedEffects::new(effect.span, FinalizedEffectType::CreateClosure(fields, returning, captures, code)));
+}
+
+/// Checks a call to a function value, like a closure stored in a variable.
+pub async fn check_closure_call(
+    code_verifier: &CodeVerifier<'_>,
+    variables: &SimpleVariableManager,
+    calling: FinalizedEffects,
+    arguments: Vec<FinalizedEffects>,
+    span: &Span,
+) -> Result<FinalizedEffects, ParsingError> {
+    let calling_type = match get_return(&calling.types, variables, &code_verifier.syntax).await {
+        Some(found) => found,
+        None => return Err(span.make_error(ParsingMessage::UnexpectedVoid())),
+    };
+    let argument_types = match calling_type.inner_function() {
+        Some((argument_types, _)) => argument_types,
+        None => return Err(span.make_error(ParsingMessage::NotAFunction(calling_type.clone()))),
+    };
+    if argument_types.len() != arguments.len() {
+        return Err(span.make_error(ParsingMessage::MissingArgument()));
+    }
+
+    for (argument, argument_type) in arguments.iter().zip(argument_types) {
+        let found = match get_return(&argument.types, variables, &code_verifier.syntax).await {
+            Some(found) => found,
+            None => return Err(span.make_error(ParsingMessage::UnexpectedVoid())),
+        };
+
+        if !found.of_type(argument_type, code_verifier.syntax.clone()).await {
+            return Err(span.make_error(ParsingMessage::MismatchedTypes(found, argument_type.clone())));
+        }
+    }
+
+    return Ok(FinalizedEffects::new(span.clone(), FinalizedEffectType::CallClosure(Box::new(calling), arguments)));
+}
+
+/// Finds the names of every variable loaded in the code that isn't declared in it first, in the order they're first
+/// loaded. Variables declared in a block are only seen by the rest of that block and the blocks inside of it.
+fn find_loads(code: &FinalizedCodeBody, declared: &mut Vec<String>, found: &mut Vec<String>) {
+    for line in &code.expressions {
+        find_effect_loads(&line.effect, declared, found);
+    }
+}
+
+/// Finds the names of every variable loaded in the effect that isn't declared.
+// skipcq: RS-R1000 Match statements have complexity calculated incorrectly
+fn find_effect_loads(effect: &FinalizedEffects, declared: &mut Vec<String>, found: &mut Vec<String>) {
+    let mut load = |name: &String| {
+        if !declared.contains(name) && !found.contains(name) {
+            found.push(name.clone());
+        }
+    };
+    match &effect.types {
+        FinalizedEffectType::LoadVariable(name) => load(name),
+        FinalizedEffectType::CodeBody(body) => find_loads(body, &mut declared.clone(), found),
+        // Nested closures load the variables they capture when they're created.
+        FinalizedEffectType::CreateClosure(_, _, captures, _) => {
+            for (name, _) in captures {
+                load(name);
+            }
+        }
+        // The value is loaded before the variable exists, so it can load an outer variable with the same name
+        FinalizedEffectType::CreateVariable(name, inner, _) => {
+            find_effect_loads(inner, declared, found);
+            declared.push(name.clone());
+        }
+        FinalizedEffectType::CompareJump(inner, _, _)
+        | FinalizedEffectType::Load(inner, _, _)
+        | FinalizedEffectType::IsVariant(inner, _)
+        | FinalizedEffectType::Downcast(inner, _, _)
+        | FinalizedEffectType::HeapStore(inner)
+        | FinalizedEffectType::ReferenceLoad(inner)
+        | FinalizedEffectType::StackStore(inner)
+        | FinalizedEffectType::Free(inner) => find_effect_loads(inner, declared, found),
+        FinalizedEffectType::Set(first, second) | FinalizedEffectType::ShortCircuit(first, second, _) => {
+            find_effect_loads(first, declared, found);
+            find_effect_loads(second, declared, found);
+        }
+        FinalizedEffectType::MethodCall(pointer, _, arguments, _) => {
+            if let Some(pointer) = pointer {
+                find_effect_loads(pointer, declared, found);
+            }
+            for argument in arguments {
+                find_effect_loads(argument, declared, found);
+            }
+        }
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            find_effect_loads(calling, declared, found);
+            for argument in arguments {
+                find_effect_loads(argument, declared, found);
+            }
+        }
+        FinalizedEffectType::CreateStruct(target, _, arguments) => {
+            if let Some(target) = target {
+                find_effect_loads(target, declared, found);
+            }
+            for (_, argument) in arguments {
+                find_effect_loads(argument, declared, found);
+            }
+        }
+        FinalizedEffectType::CreateVariant(target, _, _, arguments) => {
+            find_effect_loads(target, declared, found);
+            for (_, argument) in arguments {
+                find_effect_loads(argument, declared, found);
+            }
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            for effect in [value, check, residual, unwrapped] {
+                find_effect_loads(effect, declared, found);
+            }
+        }
+        FinalizedEffectType::CreateArray(_, values)
+        | FinalizedEffectType::CreateTuple(_, values)
+        | FinalizedEffectType::GenericMethodCall(_, _, values)
+        | FinalizedEffectType::VirtualCall(_, _, values, _)
+        | FinalizedEffectType::GenericVirtualCall(_, _, _, values, _) => {
+            for value in values {
+                find_effect_loads(value, declared, found);
+            }
+        }
+        FinalizedEffectType::NOP
+        | FinalizedEffectType::Jump(_)
+        | FinalizedEffectType::FunctionReference(_)
+        | FinalizedEffectType::Float(_, _)
+        | FinalizedEffectType::UInt(_, _)
+        | FinalizedEffectType::Bool(_)
+        | FinalizedEffectType::String(_)
+        | FinalizedEffectType::Char(_)
+        | FinalizedEffectType::HeapAllocate(_) => {}
+    }
+}
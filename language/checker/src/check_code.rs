@@ -1,17 +1,22 @@
+use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 
 use async_recursion::async_recursion;
 use data::tokens::Span;
-use syntax::async_util::UnparsedType;
+use syntax::async_util::{AsyncDataGetter, UnparsedType};
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{
-    EffectType, Effects, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression,
+    EffectType, Effects, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression, FinalizedMemberField,
+    BREAK_LABEL, CONTINUE_LABEL,
 };
-use syntax::program::function::{CodeBody, FinalizedCodeBody};
+use syntax::program::function::{CodeBody, CodelessFinalizedFunction, FinalizedCodeBody};
+use syntax::program::r#struct::{FinalizedStruct, F64, U64};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
-use syntax::SimpleVariableManager;
+use syntax::{is_modifier, Modifier, SimpleVariableManager};
 
+use crate::check_closure::check_closure;
+use crate::check_function::add_function;
 use crate::check_impl_call::check_impl_call;
 use crate::check_method_call::check_method_call;
 use crate::check_operator::check_operator;
@@ -23,11 +28,18 @@ pub async fn verify_code(
     code_verifier: &mut CodeVerifier<'_>,
     variables: &mut SimpleVariableManager,
     code: CodeBody,
-    top: bool,
 ) -> Result<FinalizedCodeBody, ParsingError> {
     let mut body = Vec::default();
     let mut found_end = false;
     for line in code.expressions {
+        // Destructuring declares a variable for each value, so its lines are added to this block
+        if let EffectType::Destructure(_, _) = &line.effect.types {
+            for effect in verify_destructure(code_verifier, variables, line.effect).await? {
+                body.push(FinalizedExpression::new(ExpressionType::Line, effect));
+            }
+            continue;
+        }
+
         match &line.effect.types {
             EffectType::CompareJump(_, _, _) => found_end = true,
             EffectType::Jump(_) => found_end = true,
@@ -39,52 +51,87 @@ pub async fn verify_code(
             verify_effect(code_verifier, variables, line.effect).await?,
         ));
 
-        if check_return_type(line.expression_type, code_verifier, &mut body, variables, &code_verifier.syntax).await? {
+        if check_return_type(line.expression_type, code_verifier, &mut body, variables).await? {
             return Ok(FinalizedCodeBody::new(body.clone(), code.label.clone(), true));
         }
-    }
 
-    if !found_end && !top {
-        panic!("Code body with label {} doesn't return or jump!", code.label)
+        // A block run before any jump that returns on every branch, like an if/else, makes the rest unreachable
+        if let FinalizedEffectType::CodeBody(inner) = &body.last().unwrap().effect.types {
+            if inner.returns && !found_end {
+                return Ok(FinalizedCodeBody::new(body, code.label.clone(), true));
+            }
+        }
     }
 
-    return Ok(FinalizedCodeBody::new(body, code.label.clone(), false));
+    let returns = branches_return(&body);
+    return Ok(FinalizedCodeBody::new(body, code.label.clone(), returns));
+}
+
+/// Checks if the first jump of the block only goes to the block's own branches, and every one of them returns.
+/// This is how if/else blocks return, loops always jump past the loop so they never do.
+fn branches_return(body: &[FinalizedExpression]) -> bool {
+    let returns = |label: &String| {
+        body.iter().any(|line| {
+            matches!(&line.effect.types, FinalizedEffectType::CodeBody(inner) if inner.label == *label && inner.returns)
+        })
+    };
+
+    for line in body {
+        match &line.effect.types {
+            FinalizedEffectType::CompareJump(_, first, second) => return returns(first) && returns(second),
+            FinalizedEffectType::Jump(label) => return returns(label),
+            _ => {}
+        }
+    }
+    return false;
 }
 
 /// Checks to make sure the return type matches in the code block.
+/// Functions without a return type take the type of their first return with a value.
 async fn check_return_type(
     line: ExpressionType,
-    code_verifier: &CodeVerifier<'_>,
+    code_verifier: &mut CodeVerifier<'_>,
     body: &mut Vec<FinalizedExpression>,
     variables: &SimpleVariableManager,
-    syntax: &Arc<Mutex<Syntax>>,
 ) -> Result<bool, ParsingError> {
     let span = match &line {
         ExpressionType::Return(span) => span.clone(),
         _ => return Ok(false),
     };
+    let syntax = code_verifier.syntax.clone();
 
-    let return_type = match code_verifier.return_type.as_ref() {
+    let return_type = match code_verifier.return_type.clone() {
         Some(value) => value,
-        None => return Ok(false),
+        None => return infer_return_type(code_verifier, body, variables, &span).await,
     };
 
-    let last_effect = body.pop().unwrap();
+    let mut last_effect = body.pop().unwrap();
+    // A bare return can't give a value
+    if matches!(last_effect.effect.types, FinalizedEffectType::NOP) {
+        return Err(span.make_error(ParsingMessage::UnexpectedVoid()));
+    }
+    coerce_literal(&mut last_effect.effect, &return_type, &syntax).await?;
     let last_effect_type;
-    if let Some(found) = get_return(&last_effect.effect.types, variables, syntax).await {
+    if let Some(found) = get_return(&last_effect.effect.types, variables, &syntax).await {
         last_effect_type = found;
     } else {
         // This is an if/for/while block, skip it
+        body.push(last_effect);
         return Ok(true);
     }
 
     // Only downcast types that don't match and aren't generic
-    if last_effect_type == *return_type || !last_effect_type.name_safe().is_some() {
+    if last_effect_type == return_type || !last_effect_type.name_safe().is_some() {
         body.push(last_effect);
         return Ok(true);
     }
 
-    return if last_effect_type.of_type(return_type, code_verifier.syntax.clone()).await {
+    return if last_effect_type.of_type(&return_type, syntax.clone()).await {
+        // Only traits are downcast, other types like "Option<T>" match once they're degenericed
+        if !return_type.inner_struct_safe().map_or(false, |inner| is_modifier(inner.data.modifiers, Modifier::Trait)) {
+            body.push(last_effect);
+            return Ok(true);
+        }
         body.push(FinalizedExpression::new(
             line,
             FinalizedEffects::new(
@@ -98,6 +145,42 @@ async fn check_return_type(
     };
 }
 
+/// Infers the return type of the function or closure being checked from the return at the end of the body.
+/// The first return with a value gives the type, and every later return is checked against it like a declared type.
+/// Functions are added to the program at their first return, so any calls after it can use the return type.
+async fn infer_return_type(
+    code_verifier: &mut CodeVerifier<'_>,
+    body: &[FinalizedExpression],
+    variables: &SimpleVariableManager,
+    span: &Span,
+) -> Result<bool, ParsingError> {
+    // Code that returns void doesn't check its returns
+    if !code_verifier.infer_return {
+        return Ok(false);
+    }
+
+    let last_effect = &body.last().unwrap().effect;
+    let found = match get_return(&last_effect.types, variables, &code_verifier.syntax).await {
+        Some(FinalizedTypes::Reference(inner)) => *inner,
+        Some(found) => found,
+        None => {
+            // Code is only inferred if it returns a value somewhere, so it can't also return void
+            if matches!(last_effect.types, FinalizedEffectType::NOP) {
+                return Err(span.make_error(ParsingMessage::UnexpectedVoid()));
+            }
+            // This is an if/for/while block, the returns inside of it were already checked
+            return Ok(true);
+        }
+    };
+
+    if let Some(mut inferring) = code_verifier.inferring.take() {
+        inferring.return_type = Some(found.clone());
+        add_function(&code_verifier.syntax, &inferring);
+    }
+    code_verifier.return_type = Some(found);
+    return Ok(true);
+}
+
 /// Verifies a single effect
 #[async_recursion]
 // skipcq: RS-R1000 Match statements have complexity calculated incorrectly
@@ -106,6 +189,41 @@ pub async fn verify_effect(
     variables: &mut SimpleVariableManager,
     effect: Effects,
 ) -> Result<FinalizedEffects, ParsingError> {
+    // The parser links breaks and continues to their loop, so any left over are outside of one
+    if let EffectType::Jump(label) = &effect.types {
+        if label == BREAK_LABEL {
+            return Err(effect.span.make_error(ParsingMessage::UnexpectedBreak()));
+        } else if label == CONTINUE_LABEL {
+            return Err(effect.span.make_error(ParsingMessage::UnexpectedContinue()));
+        }
+    }
+
+    // Variants without fields, like "Option::None", are parsed as variables
+    if let EffectType::LoadVariable(name) = &effect.types {
+        if name.contains("::") && !variables.variables.contains_key(name) {
+            if let Some(found) = find_enum(code_verifier, name, &effect.span).await? {
+                let variant = name.split("::").last().unwrap();
+                return verify_create_variant(code_verifier, variables, found, variant, Vec::default(), &effect.span).await;
+            }
+        }
+    }
+
+    // Functions used as values, like "map(iter, double)", are turned into function references
+    if let EffectType::LoadVariable(name) = &effect.types {
+        if !variables.variables.contains_key(name) {
+            if let Some(found) = find_function(code_verifier, name, &effect.span).await? {
+                return Ok(FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::FunctionReference(found)));
+            }
+        }
+    }
+
+    // Variables only exist in the block they're declared in and the blocks inside of it
+    if let EffectType::LoadVariable(name) = &effect.types {
+        if !variables.variables.contains_key(name) {
+            return Err(effect.span.make_error(ParsingMessage::UnknownVariable(name.clone())));
+        }
+    }
+
     // Some basic effects are handled in finalize_basic
     if let Some(found) = finalize_basic(&effect).await {
         return Ok(found);
@@ -113,20 +231,37 @@ pub async fn verify_effect(
 
     let output = match effect.types {
         EffectType::Paren(inner) => verify_effect(code_verifier, variables, *inner).await?,
-        EffectType::CodeBody(body) => FinalizedEffects::new(
-            effect.span.clone(),
-            FinalizedEffectType::CodeBody(verify_code(code_verifier, &mut variables.clone(), body, false).await?),
-        ),
-        EffectType::Set(first, second) => FinalizedEffects::new(
-            effect.span.clone(),
-            FinalizedEffectType::Set(
-                Box::new(verify_effect(code_verifier, variables, *first).await?),
-                Box::new(verify_effect(code_verifier, variables, *second).await?),
-            ),
-        ),
+        EffectType::CodeBody(body) => {
+            let body = verify_code(code_verifier, &mut variables.clone(), body).await?;
+            // Control statements end their blocks with a jump, blocks without one can't be run
+            let jumps = body.expressions.iter().any(|line| {
+                matches!(line.effect.types, FinalizedEffectType::Jump(_) | FinalizedEffectType::CompareJump(_, _, _))
+            });
+            if !jumps && !body.returns {
+                return Err(effect.span.make_error(ParsingMessage::UnexpectedBlock()));
+            }
+            FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::CodeBody(body))
+        }
+        EffectType::Set(first, second) => {
+            let first = verify_effect(code_verifier, variables, *first).await?;
+            check_mutable(&first, variables)?;
+            FinalizedEffects::new(
+                effect.span.clone(),
+                FinalizedEffectType::Set(Box::new(first), Box::new(verify_effect(code_verifier, variables, *second).await?)),
+            )
+        }
         EffectType::Operation(_, _) => check_operator(code_verifier, variables, effect).await?,
-        EffectType::ImplementationCall(_, _, _, _, _) => check_impl_call(code_verifier, variables, effect).await?,
-        EffectType::MethodCall(_, _, _, _) => check_method_call(code_verifier, variables, effect).await?,
+        EffectType::ImplementationCall(_, _, _, _, _) => {
+            let output = check_impl_call(code_verifier, variables, effect).await?;
+            check_mutable_args(&output, variables)?;
+            output
+        }
+        EffectType::MethodCall(_, _, _, _) => {
+            let output = check_method_call(code_verifier, variables, effect).await?;
+            check_mutable_args(&output, variables)?;
+            output
+        }
+        EffectType::Closure(_, _, _) => check_closure(code_verifier, variables, effect).await?,
         EffectType::CompareJump(effect, first, second) => FinalizedEffects::new(
             effect.span.clone(),
             FinalizedEffectType::CompareJump(
@@ -135,15 +270,45 @@ pub async fn verify_effect(
                 second,
             ),
         ),
-        EffectType::CreateStruct(target, effects) => verify_create_struct(code_verifier, target, effects, variables).await?,
+        EffectType::CreateStruct(target, effects) => {
+            verify_create_struct(code_verifier, target, effects, variables, &effect.span).await?
+        }
         EffectType::Load(inner_effect, target) => {
             let output = verify_effect(code_verifier, variables, *inner_effect).await?;
             let types = get_return(&output.types, variables, &code_verifier.syntax).await.unwrap();
+            if let Some(structure) = types.inner_struct_safe() {
+                match structure.fields.iter().find(|field| field.field.name == target) {
+                    Some(field) => check_field_visibility(code_verifier, structure, field, &effect.span)?,
+                    None => return Err(effect.span.make_error(ParsingMessage::UnknownField(target))),
+                }
+            } else if types.inner_tuple().is_some() && types.get_field_type(&target).is_none() {
+                return Err(effect.span.make_error(ParsingMessage::UnknownField(target)));
+            }
 
             FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::Load(Box::new(output), target.clone(), types))
         }
-        EffectType::CreateVariable(name, inner_effect) => {
-            let effect = verify_effect(code_verifier, variables, *inner_effect).await?;
+        EffectType::CreateVariable(name, mutable, types, inner_effect) => {
+            let declaration = effect.span.clone();
+            let mut effect = verify_effect(code_verifier, variables, *inner_effect).await?;
+            let types = match types {
+                Some(types) => Some(
+                    Syntax::parse_type(
+                        code_verifier.syntax.clone(),
+                        effect.span.clone(),
+                        code_verifier.resolver.boxed_clone(),
+                        types,
+                        vec![],
+                    )
+                    .await?
+                    .finalize(code_verifier.syntax.clone())
+                    .await,
+                ),
+                None => None,
+            };
+            if let Some(types) = &types {
+                coerce_literal(&mut effect, types, &code_verifier.syntax).await?;
+            }
+
             let found;
             if let Some(temp_found) = get_return(&effect.types, variables, &code_verifier.syntax).await {
                 found = temp_found;
@@ -151,7 +316,18 @@ pub async fn verify_effect(
                 return Err(effect.span.make_error(ParsingMessage::UnexpectedVoid()));
             };
 
+            if let Some(types) = types {
+                if !found.of_type(&types, code_verifier.syntax.clone()).await {
+                    return Err(effect.span.make_error(ParsingMessage::MismatchedTypes(found, types)));
+                }
+            }
+
             variables.variables.insert(name.clone(), found.clone());
+            if mutable {
+                variables.immutable.remove(&name);
+            } else {
+                variables.immutable.insert(name.clone(), declaration);
+            }
             FinalizedEffects::new(
                 effect.span.clone(),
                 FinalizedEffectType::CreateVariable(name.clone(), Box::new(effect), found),
@@ -172,22 +348,116 @@ pub async fn verify_effect(
 
             FinalizedEffects::new(effect.span.clone(), store(FinalizedEffectType::CreateArray(types, output)))
         }
+        EffectType::CreateTuple(effects) => {
+            let mut output = Vec::default();
+            let mut types = Vec::default();
+            for effect in effects {
+                let effect = verify_effect(code_verifier, variables, effect).await?;
+                match get_return(&effect.types, variables, &code_verifier.syntax).await {
+                    Some(FinalizedTypes::Reference(found)) => types.push(*found),
+                    Some(found) => types.push(found),
+                    None => return Err(effect.span.make_error(ParsingMessage::UnexpectedVoid())),
+                }
+                output.push(effect);
+            }
+
+            FinalizedEffects::new(
+                effect.span.clone(),
+                FinalizedEffectType::CreateTuple(FinalizedTypes::Tuple(types), output),
+            )
+        }
+        EffectType::IsVariant(inner_effect, variant) => {
+            let output = verify_effect(code_verifier, variables, *inner_effect).await?;
+            let types = get_return(&output.types, variables, &code_verifier.syntax).await.unwrap();
+            let variant = variant.split("::").last().unwrap();
+            let tag = match types.get_variant(variant) {
+                Some(tag) => tag,
+                None => return Err(effect.span.make_error(ParsingMessage::UnknownVariant(variant.to_string(), types))),
+            };
+
+            FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::IsVariant(Box::new(output), tag))
+        }
         _ => unreachable!(),
     };
     return Ok(output);
 }
 
+/// Verifies a destructuring let, which stores the tuple in a variable then declares a variable for each of its values
+async fn verify_destructure(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    effect: Effects,
+) -> Result<Vec<FinalizedEffects>, ParsingError> {
+    let (names, value) = match effect.types {
+        EffectType::Destructure(names, value) => (names, value),
+        _ => unreachable!(),
+    };
+    let span = effect.span;
+
+    // The variable holding the tuple while it's destructured, named after where it starts so each one is different
+    let holding = format!("$tuple{}", span.start);
+    let tuple = Effects::new(span.clone(), EffectType::CreateVariable(holding.clone(), false, None, value));
+    let tuple = verify_effect(code_verifier, variables, tuple).await?;
+    let types = get_return(&tuple.types, variables, &code_verifier.syntax).await.unwrap();
+    if types.inner_tuple().map_or(true, |found| found.len() != names.len()) {
+        return Err(span.make_error(ParsingMessage::MismatchedDestructure(names.len(), types)));
+    }
+
+    let mut output = vec![tuple];
+    for (index, (name, mutable)) in names.into_iter().enumerate() {
+        let tuple = Effects::new(span.clone(), EffectType::LoadVariable(holding.clone()));
+        let value = Effects::new(span.clone(), EffectType::Load(Box::new(tuple), index.to_string()));
+        let variable = Effects::new(span.clone(), EffectType::CreateVariable(name, mutable, None, Box::new(value)));
+        output.push(verify_effect(code_verifier, variables, variable).await?);
+    }
+    return Ok(output);
+}
+
+/// Makes sure the variable changed by an effect is mutable, erroring at the variable's declaration if it isn't
+fn check_mutable(effect: &FinalizedEffects, variables: &SimpleVariableManager) -> Result<(), ParsingError> {
+    return match &effect.types {
+        FinalizedEffectType::LoadVariable(name) => match variables.immutable.get(name) {
+            Some(declaration) => Err(declaration.make_error(ParsingMessage::Immutable(name.clone()))),
+            None => Ok(()),
+        },
+        // Changing a field changes the structure it's in
+        FinalizedEffectType::Load(inner, _, _)
+        | FinalizedEffectType::Downcast(inner, _, _)
+        | FinalizedEffectType::ReferenceLoad(inner)
+        | FinalizedEffectType::StackStore(inner)
+        | FinalizedEffectType::HeapStore(inner) => check_mutable(inner, variables),
+        _ => Ok(()),
+    };
+}
+
+/// Makes sure every argument passed to a mut argument of the called function is mutable
+fn check_mutable_args(effect: &FinalizedEffects, variables: &SimpleVariableManager) -> Result<(), ParsingError> {
+    let (function, arguments) = match &effect.types {
+        FinalizedEffectType::MethodCall(_, function, arguments, _)
+        | FinalizedEffectType::VirtualCall(_, function, arguments, _)
+        | FinalizedEffectType::GenericVirtualCall(_, _, function, arguments, _) => (function, arguments),
+        _ => return Ok(()),
+    };
+
+    for (field, argument) in function.arguments.iter().zip(arguments) {
+        if is_modifier(field.modifiers, Modifier::Mut) {
+            check_mutable(argument, variables)?;
+        }
+    }
+    return Ok(());
+}
+
 /// Separately handles a few basic effects to declutter the main function
 async fn finalize_basic(effects: &Effects) -> Option<FinalizedEffects> {
     return Some(FinalizedEffects::new(
         effects.span.clone(),
         match &effects.types {
-            EffectType::NOP => panic!("Tried to compile a NOP!"),
+            EffectType::NOP => FinalizedEffectType::NOP,
             EffectType::Jump(jumping) => FinalizedEffectType::Jump(jumping.clone()),
             EffectType::LoadVariable(variable) => FinalizedEffectType::LoadVariable(variable.clone()),
-            EffectType::Float(float) => store(FinalizedEffectType::Float(*float)),
-            EffectType::Int(int) => store(FinalizedEffectType::UInt(*int as u64)),
-            EffectType::UInt(uint) => store(FinalizedEffectType::UInt(*uint)),
+            EffectType::Float(float) => store(FinalizedEffectType::Float(*float, FinalizedTypes::Struct(F64.clone()))),
+            EffectType::Int(int) => store(FinalizedEffectType::UInt(*int as u64, FinalizedTypes::Struct(U64.clone()))),
+            EffectType::UInt(uint) => store(FinalizedEffectType::UInt(*uint, FinalizedTypes::Struct(U64.clone()))),
             EffectType::Bool(bool) => store(FinalizedEffectType::Bool(*bool)),
             EffectType::String(string) => store(FinalizedEffectType::String(string.clone())),
             EffectType::Char(char) => store(FinalizedEffectType::Char(*char)),
@@ -202,17 +472,13 @@ async fn verify_create_struct(
     target: UnparsedType,
     effects: Vec<(String, Effects)>,
     variables: &mut SimpleVariableManager,
+    span: &Span,
 ) -> Result<FinalizedEffects, ParsingError> {
-    let mut target = Syntax::parse_type(
-        code_verifier.syntax.clone(),
-        Span::default(),
-        code_verifier.resolver.boxed_clone(),
-        target,
-        vec![],
-    )
-    .await?
-    .finalize(code_verifier.syntax.clone())
-    .await;
+    let mut target =
+        Syntax::parse_type(code_verifier.syntax.clone(), span.clone(), code_verifier.resolver.boxed_clone(), target, vec![])
+            .await?
+            .finalize(code_verifier.syntax.clone())
+            .await;
 
     let mut generics = code_verifier.process_manager.generics.clone();
     let mut final_effects = vec![];
@@ -229,14 +495,16 @@ async fn verify_create_struct(
         if i == fields.len() {
             return Err(effect.span.make_error(ParsingMessage::UnknownField(field_name)));
         }
+        check_field_visibility(code_verifier, target.inner_struct(), &fields[i], &effect.span)?;
 
         let error = effect.span.clone();
-        let final_effect = verify_effect(code_verifier, variables, effect).await?;
-        get_return(&final_effect.types, variables, &code_verifier.syntax)
-            .await
-            .unwrap()
-            .resolve_generic(&fields[i].field.field_type, &code_verifier.syntax, &mut generics, error)
-            .await?;
+        let mut final_effect = verify_effect(code_verifier, variables, effect).await?;
+        coerce_literal(&mut final_effect, &fields[i].field.field_type, &code_verifier.syntax).await?;
+        // Explicit generics, like "new HashMap<K, V>", aren't replaced by generic values, like "allocate(capacity)"
+        if !matches!(target, FinalizedTypes::GenericType(_, _)) {
+            let found = get_return(&final_effect.types, variables, &code_verifier.syntax).await.unwrap();
+            fields[i].field.field_type.resolve_generic(&found, &code_verifier.syntax, &mut generics, error).await?;
+        }
         final_effects.push((i, final_effect));
     }
 
@@ -251,6 +519,188 @@ async fn verify_create_struct(
     ));
 }
 
+/// Checks the field of the structure can be accessed from the span
+fn check_field_visibility(
+    code_verifier: &CodeVerifier<'_>,
+    structure: &FinalizedStruct,
+    field: &FinalizedMemberField,
+    span: &Span,
+) -> Result<(), ParsingError> {
+    // Degenericed structs, like "ArrayList$u64", are named after their base struct
+    let name = format!("{}::{}", structure.base_name(), field.field.name);
+    return code_verifier.syntax.lock().unwrap().check_visible(&name, field.modifiers, &structure.data.span, span);
+}
+
+/// Finds the enum with the variant, from the variant's path (like "Option::Some")
+async fn find_enum(
+    code_verifier: &CodeVerifier<'_>,
+    path: &str,
+    span: &Span,
+) -> Result<Option<FinalizedTypes>, ParsingError> {
+    let possible = path.split("::").collect::<Vec<_>>();
+    let structure = Syntax::get_struct(
+        code_verifier.syntax.clone(),
+        span.clone(),
+        possible[possible.len() - 2].to_string(),
+        code_verifier.resolver.boxed_clone(),
+        vec![],
+    )
+    .await;
+    let structure = match found_visible(structure)? {
+        Some(structure) => structure.finalize(code_verifier.syntax.clone()).await,
+        None => return Ok(None),
+    };
+    return Ok(structure.get_variant(possible[possible.len() - 1]).map(|_| structure));
+}
+
+/// Finds the function with the given name so it can be used as a value. Generic functions can't be referenced.
+async fn find_function(
+    code_verifier: &CodeVerifier<'_>,
+    name: &str,
+    span: &Span,
+) -> Result<Option<Arc<CodelessFinalizedFunction>>, ParsingError> {
+    let function = Syntax::get_function(
+        code_verifier.syntax.clone(),
+        span.clone(),
+        name.to_string(),
+        code_verifier.resolver.boxed_clone(),
+        true,
+    )
+    .await;
+    let function = match found_visible(function)? {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+    if code_verifier.inferring.as_ref().is_some_and(|inferring| inferring.data == function) {
+        return Ok(None);
+    }
+    let function = AsyncDataGetter::new(code_verifier.syntax.clone(), function).await;
+    return Ok(if function.generics.is_empty() { Some(function) } else { None });
+}
+
+/// Gets the element if it was found, or None if it doesn't exist. Elements that exist but aren't visible are an error.
+pub fn found_visible<T>(found: Result<T, ParsingError>) -> Result<Option<T>, ParsingError> {
+    return match found {
+        Ok(found) => Ok(Some(found)),
+        Err(error) if matches!(error.message, ParsingMessage::NotVisible(_, _)) => Err(error),
+        Err(_) => Ok(None),
+    };
+}
+
+/// Verifies the creation of an enum variant with the given field values
+pub async fn verify_create_variant(
+    code_verifier: &CodeVerifier<'_>,
+    variables: &SimpleVariableManager,
+    mut target: FinalizedTypes,
+    variant: &str,
+    effects: Vec<FinalizedEffects>,
+    span: &Span,
+) -> Result<FinalizedEffects, ParsingError> {
+    let tag = target.get_variant(variant).unwrap();
+    let fields = target.inner_struct().variant_fields(variant);
+    if fields.len() != effects.len() {
+        return Err(span.make_error(ParsingMessage::MissingArgument()));
+    }
+
+    let mut generics = code_verifier.process_manager.generics.clone();
+    let mut final_effects = Vec::default();
+    for (index, mut effect) in fields.into_iter().zip(effects) {
+        let field_type = &target.get_fields()[index].field.field_type;
+        coerce_literal(&mut effect, field_type, &code_verifier.syntax).await?;
+        let returning = get_return(&effect.types, variables, &code_verifier.syntax).await.unwrap();
+        if !returning.of_type(field_type, code_verifier.syntax.clone()).await {
+            return Err(span.make_error(ParsingMessage::MismatchedTypes(returning, field_type.clone())));
+        }
+        returning.resolve_generic(field_type, &code_verifier.syntax, &mut generics, span.clone()).await?;
+        final_effects.push((index, effect));
+    }
+
+    degeneric_type_fields(&mut target, &generics, &code_verifier.syntax).await;
+    return Ok(FinalizedEffects::new(
+        span.clone(),
+        FinalizedEffectType::CreateVariant(
+            Box::new(FinalizedEffects::new(Span::default(), FinalizedEffectType::HeapAllocate(target.clone()))),
+            target,
+            tag,
+            final_effects,
+        ),
+    ));
+}
+
+/// Gives a number literal the expected type, like the u8 in "let x: u8 = 5", instead of the default u64 or f64.
+/// Anything that isn't a literal is left for the normal type checks.
+#[async_recursion]
+pub async fn coerce_literal(
+    effect: &mut FinalizedEffects,
+    expected: &FinalizedTypes,
+    syntax: &Arc<Mutex<Syntax>>,
+) -> Result<(), ParsingError> {
+    // Results of math on closure arguments can be nested references, and literals are never references
+    let mut expected = expected;
+    while let FinalizedTypes::Reference(inner) = expected {
+        expected = inner.deref();
+    }
+    // Each value of a tuple literal is coerced to the type at its place in the tuple
+    if let (FinalizedEffectType::CreateTuple(FinalizedTypes::Tuple(types), values), Some(expected)) =
+        (&mut effect.types, expected.inner_tuple())
+    {
+        if values.len() == expected.len() {
+            for (index, value) in values.iter_mut().enumerate() {
+                coerce_literal(value, &expected[index], syntax).await?;
+                if let Some(found) = literal_type(value) {
+                    types[index] = found;
+                }
+            }
+        }
+        return Ok(());
+    }
+    // Generics are resolved from the literal's type instead
+    let name = match expected.name_safe() {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let literal = match &mut effect.types {
+        FinalizedEffectType::HeapStore(inner) | FinalizedEffectType::StackStore(inner) => &mut inner.types,
+        literal => literal,
+    };
+    let integer = matches!(name.as_str(), "i64" | "i32" | "i16" | "i8" | "u64" | "u32" | "u16" | "u8");
+    let float = matches!(name.as_str(), "f64" | "f32");
+    let coerced = match literal {
+        FinalizedEffectType::UInt(value, _) if integer && fits(*value, &name) => {
+            FinalizedEffectType::UInt(*value, expected.clone())
+        }
+        FinalizedEffectType::UInt(value, _) if float => FinalizedEffectType::Float(*value as f64, expected.clone()),
+        FinalizedEffectType::Float(value, _) if float => FinalizedEffectType::Float(*value, expected.clone()),
+        FinalizedEffectType::UInt(_, found) | FinalizedEffectType::Float(_, found) => {
+            if (integer || float) || !found.of_type(expected, syntax.clone()).await {
+                return Err(effect.span.make_error(ParsingMessage::MismatchedTypes(found.clone(), expected.clone())));
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    *literal = coerced;
+    return Ok(());
+}
+
+/// Gets the type of a number literal
+fn literal_type(effect: &FinalizedEffects) -> Option<FinalizedTypes> {
+    return match &effect.types {
+        FinalizedEffectType::HeapStore(inner) | FinalizedEffectType::StackStore(inner) => literal_type(inner),
+        FinalizedEffectType::UInt(_, types) | FinalizedEffectType::Float(_, types) => Some(types.clone()),
+        _ => None,
+    };
+}
+
+/// Checks if the integer fits in the integer type with the given name.
+/// Literals are never negative, so signed types only allow up to their maximum.
+fn fits(value: u64, name: &str) -> bool {
+    let bits = name[1..].parse::<u32>().unwrap();
+    let maximum = if name.starts_with('i') { u64::MAX >> (65 - bits) } else { u64::MAX >> (64 - bits) };
+    return value <= maximum;
+}
+
 /// Checks if two types are the same
 async fn check_type(
     types: &Option<FinalizedTypes>,
@@ -1,4 +1,5 @@
 use crate::check_code::verify_code;
+use crate::drops::insert_drops;
 use crate::output::TypesChecker;
 use crate::{finalize_generics, CodeVerifier};
 use data::tokens::Span;
@@ -7,7 +8,8 @@ use std::sync::Mutex;
 use syntax::async_util::NameResolver;
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{
-    ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression, FinalizedField, FinalizedMemberField,
+    EffectType, ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression, FinalizedField,
+    FinalizedMemberField,
 };
 use syntax::program::function::{
     CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction, UnfinalizedFunction,
@@ -33,6 +35,7 @@ pub async fn verify_function(
                 field_type: field.field.field_type.finalize(syntax.clone()).await,
                 name: field.field.name,
             },
+            span: field.span,
         };
         if include_refs {
             field.field.field_type = FinalizedTypes::Reference(Box::new(field.field.field_type));
@@ -71,9 +74,14 @@ pub async fn verify_function_code(
     codeless: CodelessFinalizedFunction,
     syntax: &Arc<Mutex<Syntax>>,
 ) -> Result<FinalizedFunction, ParsingError> {
-    {
-        let mut locked = syntax.lock().unwrap();
-        locked.functions.add_data(codeless.data.clone(), Arc::new(codeless.clone()));
+    // Functions without a return type that return a value get it from their code, so they're added once their first
+    // return is checked. Ones that never return a value are void, and are added right away so callers aren't blocked.
+    let inferring = codeless.return_type.is_none()
+        && !is_modifier(codeless.data.modifiers, Modifier::Internal)
+        && !is_modifier(codeless.data.modifiers, Modifier::Extern)
+        && has_valued_return(&code);
+    if !inferring {
+        add_function(syntax, &codeless);
     }
 
     //Internal/external/trait functions verify everything but the code.
@@ -83,26 +91,25 @@ pub async fn verify_function_code(
 
     let mut variable_manager = SimpleVariableManager::for_function(&codeless);
     let mut process_manager = process_manager.clone();
-    for (name, bounds) in resolver.generics() {
-        let mut output = vec![];
-        for bound in bounds {
-            output.push(
-                Syntax::parse_type(syntax.clone(), Span::default(), resolver.boxed_clone(), bound.clone(), vec![])
-                    .await?
-                    .finalize(syntax.clone())
-                    .await,
-            )
-        }
-        process_manager.mut_generics().insert(name.clone(), FinalizedTypes::Generic(name.clone(), output));
-    }
+    // If this fails, anything waiting on the function is stuck because of the error instead of deadlocked
+    let generics = finalize_bounds(&*resolver, syntax, &codeless.data.span).await?;
+    process_manager.mut_generics().extend(generics);
 
     let mut code_verifier = CodeVerifier {
         process_manager: &process_manager,
         resolver,
         return_type: codeless.return_type.clone(),
+        infer_return: inferring,
+        inferring: if inferring { Some(codeless.clone()) } else { None },
         syntax: syntax.clone(),
     };
-    let mut code = verify_code(&mut code_verifier, &mut variable_manager, code, true).await?;
+    let mut code = verify_code(&mut code_verifier, &mut variable_manager, code).await?;
+
+    // No return with a value was found, so the function returns void
+    if let Some(inferred) = code_verifier.inferring.take() {
+        add_function(syntax, &inferred);
+    }
+    let codeless = CodelessFinalizedFunction { return_type: code_verifier.return_type, ..codeless };
 
     // Checks the return type exists
     if !code.returns {
@@ -112,9 +119,82 @@ pub async fn verify_function_code(
                 FinalizedEffects::new(Span::default(), FinalizedEffectType::NOP),
             ));
         } else if !is_modifier(codeless.data.modifiers, Modifier::Trait) {
-            return Err(codeless.data.span.make_error(ParsingMessage::UnexpectedVoid()));
+            return Err(codeless.data.span.make_error(ParsingMessage::MissingReturn(codeless.data.name.clone())));
         }
     }
 
+    // Mut primitive arguments are copied on entry, so changing them doesn't change the caller's value
+    for argument in codeless.arguments.iter().rev() {
+        if is_modifier(argument.modifiers, Modifier::Mut) && argument.field.field_type.is_primitive() {
+            let loading =
+                FinalizedEffects::new(argument.span.clone(), FinalizedEffectType::LoadVariable(argument.field.name.clone()));
+            code.expressions.insert(
+                0,
+                FinalizedExpression::new(
+                    ExpressionType::Line,
+                    FinalizedEffects::new(
+                        argument.span.clone(),
+                        FinalizedEffectType::CreateVariable(
+                            argument.field.name.clone(),
+                            Box::new(loading),
+                            argument.field.field_type.clone(),
+                        ),
+                    ),
+                ),
+            );
+        }
+    }
+
+    // Frees any heap allocations the function's variables own
+    insert_drops(&mut code, SimpleVariableManager::for_function(&codeless), syntax).await;
+
     return Ok(codeless.clone().add_code(code));
 }
+
+/// Checks if the code body, or any block inside of it, returns a value.
+/// Closures are separate effects, so their returns aren't counted.
+pub fn has_valued_return(body: &CodeBody) -> bool {
+    for line in &body.expressions {
+        match &line.effect.types {
+            EffectType::CodeBody(inner) => {
+                if has_valued_return(inner) {
+                    return true;
+                }
+            }
+            EffectType::NOP => {}
+            _ => {
+                if matches!(line.expression_type, ExpressionType::Return(_)) {
+                    return true;
+                }
+            }
+        }
+    }
+    return false;
+}
+
+/// Adds the function to the program, waking up any code waiting to call it
+pub fn add_function(syntax: &Arc<Mutex<Syntax>>, codeless: &CodelessFinalizedFunction) {
+    syntax.lock().unwrap().functions.add_data(codeless.data.clone(), Arc::new(codeless.clone()));
+}
+
+/// Finalizes the bounds of each of the function's generics
+async fn finalize_bounds(
+    resolver: &dyn NameResolver,
+    syntax: &Arc<Mutex<Syntax>>,
+    span: &Span,
+) -> Result<Vec<(String, FinalizedTypes)>, ParsingError> {
+    let mut generics = Vec::default();
+    for (name, bounds) in resolver.generics() {
+        let mut output = vec![];
+        for bound in bounds {
+            output.push(
+                Syntax::parse_type(syntax.clone(), span.clone(), resolver.boxed_clone(), bound.clone(), vec![])
+                    .await?
+                    .finalize(syntax.clone())
+                    .await,
+            )
+        }
+        generics.push((name.clone(), FinalizedTypes::Generic(name.clone(), output)));
+    }
+    return Ok(generics);
+}
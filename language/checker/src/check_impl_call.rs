@@ -10,9 +10,8 @@ use syntax::program::types::FinalizedTypes;
 use syntax::top_element_manager::ImplWaiter;
 use syntax::SimpleVariableManager;
 
-use crate::check_code::verify_effect;
+use crate::check_code::{coerce_literal, verify_effect};
 use crate::check_method_call::check_method;
-use crate::degeneric::degeneric_header;
 use crate::{get_return, CodeVerifier};
 
 /// Checks an implementation call generated by control_parser or an operator to get the correct method
@@ -44,15 +43,37 @@ pub async fn check_impl_call(
     if matches!(calling.types, EffectType::NOP) {
         calling_type = FinalizedTypes::Struct(VOID.clone());
     } else {
-        let calling_effect = verify_effect(code_verifier, variables, *calling.clone()).await?;
-        calling_type = get_return(&calling_effect.types, variables, &code_verifier.syntax).await.unwrap();
+        let mut calling_effect = verify_effect(code_verifier, variables, *calling.clone()).await?;
+        // Number literals take the type of the other side, so "x == 5" and "5 == x" work for any number type.
+        // Literals that can't be that type, like "x == 300" for a u8, are errors.
+        if let Some(other) = finalized_effects.first() {
+            if let Some(other) = get_return(&other.types, variables, &code_verifier.syntax).await {
+                if other.is_primitive() {
+                    coerce_literal(&mut calling_effect, &other, &code_verifier.syntax).await?;
+                }
+            }
+        }
+        calling_type = match get_return(&calling_effect.types, variables, &code_verifier.syntax).await {
+            Some(found) => found,
+            None => return Err(calling.span.make_error(ParsingMessage::UnexpectedVoid())),
+        };
+        if calling_type.is_primitive() {
+            for effect in &mut finalized_effects {
+                coerce_literal(effect, &calling_type, &code_verifier.syntax).await?;
+            }
+        }
         finalized_effects.insert(0, calling_effect);
     }
 
+    // Boolean ands and ors skip the right side if the left side already decides the result
+    if let Some(found) = check_short_circuit(code_verifier, variables, &traits, &mut finalized_effects, &effect.span).await {
+        return Ok(found);
+    }
+
     // Get the trait
     if let Ok(trait_type) = Syntax::get_struct(
         code_verifier.syntax.clone(),
-        Span::default(),
+        effect.span.clone(),
         traits.clone(),
         code_verifier.resolver.boxed_clone(),
         vec![],
@@ -99,6 +120,38 @@ pub async fn check_impl_call(
     }
 }
 
+/// Turns "&&" and "||" on two bools into a short circuit, other types keep using their trait's impl
+async fn check_short_circuit(
+    code_verifier: &CodeVerifier<'_>,
+    variables: &SimpleVariableManager,
+    traits: &str,
+    finalized_effects: &mut Vec<FinalizedEffects>,
+    span: &Span,
+) -> Option<FinalizedEffects> {
+    let and = match traits {
+        "math::And" => true,
+        "math::Or" => false,
+        _ => return None,
+    };
+
+    if finalized_effects.len() != 2 {
+        return None;
+    }
+    for effect in finalized_effects.iter() {
+        let types = get_return(&effect.types, variables, &code_verifier.syntax).await?;
+        if types.name_safe()? != "bool" {
+            return None;
+        }
+    }
+
+    let second = finalized_effects.pop().unwrap();
+    let first = finalized_effects.pop().unwrap();
+    return Some(FinalizedEffects::new(
+        span.clone(),
+        FinalizedEffectType::ShortCircuit(Box::new(first), Box::new(second), and),
+    ));
+}
+
 /// All the data used by implementation checkers
 pub struct ImplCheckerData<'a> {
     /// The code verified fields
@@ -117,10 +170,19 @@ pub struct ImplCheckerData<'a> {
     variables: &'a SimpleVariableManager,
 }
 
+/// Checks if the calling type is a copy of the trait with its generics filled in, like "iter::Iter<u64>" for "iter::Iter"
+fn is_degenericed_trait(calling_type: &FinalizedTypes, trait_type: &FinalizedTypes) -> bool {
+    let (Some(calling), Some(trait_type)) = (calling_type.inner_struct_safe(), trait_type.inner_struct_safe()) else {
+        return false;
+    };
+    return calling.data.name.split('<').next().unwrap() == trait_type.data.name;
+}
+
 /// Checks an implementation call to see if it should be a virtual call (a method call on a trait instead of a struct)
 async fn check_virtual_type(data: &mut ImplCheckerData<'_>, token: &Span) -> Result<Option<FinalizedEffects>, ParsingError> {
     // If calling_type doesn't extend trait_type, then it's not a virtual call
-    if !data.calling_type.of_type_sync(data.trait_type, None).0 {
+    if !data.calling_type.of_type_sync(data.trait_type, None).0 && !is_degenericed_trait(data.calling_type, data.trait_type)
+    {
         return Ok(None);
     }
 
@@ -183,19 +245,7 @@ async fn check_virtual_type(data: &mut ImplCheckerData<'_>, token: &Span) -> Res
             )));
         }
 
-        data.code_verifier.syntax.lock().unwrap().process_manager.handle().lock().unwrap().spawn(
-            target.name.clone(),
-            degeneric_header(
-                target.clone(),
-                found.clone(),
-                data.code_verifier.syntax.clone(),
-                Box::new(data.code_verifier.process_manager.clone()),
-                data.finalized_effects.clone(),
-                data.variables.clone(),
-                token.clone(),
-            ),
-        );
-
+        // Flattened traits, like "iter::Iter<u64>", had their functions degenericed when they were flattened
         let output = AsyncDataGetter::new(data.code_verifier.syntax.clone(), target.clone()).await;
         let mut temp = vec![];
         mem::swap(&mut temp, data.finalized_effects);
@@ -8,9 +8,10 @@ use syntax::program::function::{CodelessFinalizedFunction, FunctionData};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
 use syntax::top_element_manager::TraitImplWaiter;
-use syntax::{is_modifier, FinishedTraitImplementor, Modifier, SimpleVariableManager};
+use syntax::{is_modifier, Attribute, FinishedTraitImplementor, Modifier, SimpleVariableManager};
 
-use crate::check_code::verify_effect;
+use crate::check_closure::check_closure_call;
+use crate::check_code::{coerce_literal, found_visible, verify_create_variant, verify_effect};
 use crate::{get_return, CodeVerifier};
 
 /// Checks a method call to make sure it's valid
@@ -89,7 +90,7 @@ pub async fn check_method_call(
         }
 
         // If it's a trait, handle virtual method calls.
-        if is_modifier(return_type.inner_struct().data.modifiers, Modifier::Trait) {
+        if return_type.inner_struct_safe().map_or(false, |inner| is_modifier(inner.data.modifiers, Modifier::Trait)) {
             finalized_effects.insert(0, calling);
 
             let method = Syntax::get_function(
@@ -115,7 +116,7 @@ pub async fn check_method_call(
 
         if let Ok(value) = Syntax::get_function(
             code_verifier.syntax.clone(),
-            Span::default(),
+            effect.span.clone(),
             method.clone(),
             code_verifier.resolver.boxed_clone(),
             true,
@@ -124,9 +125,43 @@ pub async fn check_method_call(
         {
             value
         } else {
+            // Methods from impls without a trait, like "impl Test { fn method(self) }"
+            if let Some(structure) = return_type.inner_struct_safe() {
+                let structure = FinalizedTypes::Struct(structure.clone());
+                for implementor in Syntax::get_struct_impl(code_verifier.syntax.clone(), structure).await {
+                    for function in &implementor.functions {
+                        if function.name.split("::").last().unwrap() != method {
+                            continue;
+                        }
+                        code_verifier.syntax.lock().unwrap().check_visible(
+                            &function.name,
+                            function.modifiers,
+                            &function.span,
+                            &effect.span,
+                        )?;
+                        let function = AsyncDataGetter::new(code_verifier.syntax.clone(), function.clone()).await;
+                        return check_method(
+                            function,
+                            finalized_effects,
+                            &code_verifier.syntax,
+                            variables,
+                            returning,
+                            &effect.span,
+                        )
+                        .await;
+                    }
+                }
+            }
+
             let checker = async |implementor: Arc<FinishedTraitImplementor>,
                                  method: Arc<FunctionData>|
                    -> Result<FinalizedEffects, ParsingError> {
+                code_verifier.syntax.lock().unwrap().check_visible(
+                    &method.name,
+                    method.modifiers,
+                    &method.span,
+                    &effect.span,
+                )?;
                 let method = AsyncDataGetter::new(code_verifier.syntax.clone(), method).await;
                 let mut process_manager = code_verifier.process_manager.clone();
                 implementor
@@ -155,27 +190,49 @@ pub async fn check_method_call(
             .await;
         }
     } else {
+        // Calling a variable holding a function value, like a closure. Variables shadow functions with the same name.
+        if variables.variables.contains_key(&method) {
+            let calling = FinalizedEffects::new(effect.span.clone(), FinalizedEffectType::LoadVariable(method));
+            return check_closure_call(code_verifier, variables, calling, finalized_effects, &effect.span).await;
+        }
+
         if method.contains("::") {
             let possible = method.split("::").collect::<Vec<_>>();
             let structure = possible[possible.len() - 2];
 
-            if let Ok(structure) = Syntax::get_struct(
+            let structure = Syntax::get_struct(
                 code_verifier.syntax.clone(),
-                Span::default(),
+                effect.span.clone(),
                 structure.to_string(),
                 code_verifier.resolver.boxed_clone(),
                 vec![],
             )
-            .await
-            {
-                for implementor in Syntax::get_struct_impl(
-                    code_verifier.syntax.clone(),
-                    structure.finalize(code_verifier.syntax.clone()).await,
-                )
-                .await
-                {
+            .await;
+            if let Some(structure) = found_visible(structure)? {
+                let structure = structure.finalize(code_verifier.syntax.clone()).await;
+                // Enum variants with fields are created like a function call, like "Option::Some(value)"
+                let variant = possible[possible.len() - 1];
+                if structure.get_variant(variant).is_some() {
+                    return verify_create_variant(
+                        code_verifier,
+                        variables,
+                        structure,
+                        variant,
+                        finalized_effects,
+                        &effect.span,
+                    )
+                    .await;
+                }
+
+                for implementor in Syntax::get_struct_impl(code_verifier.syntax.clone(), structure).await {
                     for function in &implementor.functions {
                         if function.name.split("::").last().unwrap() == possible[possible.len() - 1] {
+                            code_verifier.syntax.lock().unwrap().check_visible(
+                                &function.name,
+                                function.modifiers,
+                                &function.span,
+                                &effect.span,
+                            )?;
                             let method = AsyncDataGetter::new(code_verifier.syntax.clone(), function.clone()).await;
                             match check_method(
                                 method,
@@ -206,6 +263,11 @@ pub async fn check_method_call(
         .await?
     };
 
+    // The function being inferred isn't added until its first return, so calling it before then would never finish
+    if code_verifier.inferring.as_ref().is_some_and(|inferring| inferring.data == method) {
+        return Err(effect.span.make_error(ParsingMessage::UninferredReturn(method.name.clone())));
+    }
+
     let method = AsyncDataGetter::new(code_verifier.syntax.clone(), method).await;
     return check_method(method, finalized_effects, &code_verifier.syntax, variables, returning, &effect.span).await;
 }
@@ -247,11 +309,14 @@ pub async fn check_args(
     variables: &SimpleVariableManager,
     span: &Span,
 ) -> Result<(), ParsingError> {
-    if function.arguments.len() != args.len() {
+    // Vararg functions (like printf) take any amount of extra arguments, which aren't type checked
+    let vararg = Attribute::find_attribute("vararg", &function.data.attributes).is_some();
+    if function.arguments.len() > args.len() || (!vararg && function.arguments.len() != args.len()) {
         return Err(span.make_error(ParsingMessage::MissingArgument()));
     }
 
     for i in 0..function.arguments.len() {
+        coerce_literal(&mut args[i], &function.arguments[i].field.field_type, syntax).await?;
         let mut arg_return_type = get_return(&args[i].types, variables, syntax).await;
         if !arg_return_type.is_some() {
             return Err(span.make_error(ParsingMessage::UnexpectedVoid()));
@@ -1,15 +1,16 @@
+use async_recursion::async_recursion;
 use data::tokens::Span;
 use std::mem;
 use std::sync::Arc;
 
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::operation_util::OperationGetter;
-use syntax::program::code::{EffectType, Effects, FinalizedEffects};
+use syntax::program::code::{EffectType, Effects, FinalizedEffectType, FinalizedEffects};
 use syntax::program::r#struct::StructData;
 use syntax::{Attribute, SimpleVariableManager};
 
 use crate::check_code::verify_effect;
-use crate::CodeVerifier;
+use crate::{get_return, CodeVerifier};
 
 /// Checks if an operator call is valid
 pub async fn check_operator(
@@ -27,6 +28,7 @@ pub async fn check_operator(
     }
 
     let error = effect.span.make_error(ParsingMessage::UnknownOperation());
+    let operation = split_prefix_operation(operation, &mut values, code_verifier, &effect.span).await;
     // Check if it's two operations that should be combined, like a list ([])
     let outer_operation = combine_operation(&operation, &mut values, code_verifier, &effect.span).await?;
 
@@ -46,6 +48,10 @@ pub async fn check_operator(
         }
     }
 
+    if Attribute::find_attribute("propagate", &operation.attributes).is_some() {
+        return check_propagate(code_verifier, variables, &operation.name, values.remove(0), &effect.span).await;
+    }
+
     let calling;
     if values.len() > 0 {
         calling = Box::new(values.remove(0));
@@ -64,7 +70,93 @@ pub async fn check_operator(
     .await;
 }
 
+/// Checks a propagating operation, like "{}?", which calls the trait's should_return on the value,
+/// returns the residual out of the function if it's true, and otherwise evaluates to the unwrapped value.
+async fn check_propagate(
+    code_verifier: &mut CodeVerifier<'_>,
+    variables: &mut SimpleVariableManager,
+    trait_name: &str,
+    value: Effects,
+    span: &Span,
+) -> Result<FinalizedEffects, ParsingError> {
+    let return_type = match &code_verifier.return_type {
+        Some(found) => found.clone(),
+        None => return Err(span.make_error(ParsingMessage::UnexpectedPropagate())),
+    };
+
+    // The value is stored in a variable so it's only computed once
+    let variable = format!("$try{}", span.start);
+    let value = verify_effect(
+        code_verifier,
+        variables,
+        Effects::new(span.clone(), EffectType::CreateVariable(variable.clone(), false, None, Box::new(value))),
+    )
+    .await?;
+
+    let mut calls = Vec::default();
+    for method in ["should_return", "residual", "unwrap"] {
+        let calling = Box::new(Effects::new(span.clone(), EffectType::LoadVariable(variable.clone())));
+        let call = EffectType::ImplementationCall(calling, trait_name.to_string(), method.to_string(), vec![], None);
+        calls.push(verify_effect(code_verifier, variables, Effects::new(span.clone(), call)).await?);
+    }
+
+    let residual_type = get_return(&calls[1].types, variables, &code_verifier.syntax).await.unwrap();
+    // Generic types are checked when they're degenericed
+    if residual_type.name_safe().is_some() && residual_type != return_type {
+        return Err(span.make_error(ParsingMessage::UnexpectedReturnType(return_type, residual_type)));
+    }
+
+    let unwrapped = calls.pop().unwrap();
+    let residual = calls.pop().unwrap();
+    let check = calls.pop().unwrap();
+    return Ok(FinalizedEffects::new(
+        span.clone(),
+        FinalizedEffectType::Propagate(Box::new(value), Box::new(check), Box::new(residual), Box::new(unwrapped)),
+    ));
+}
+
+/// Splits a binary operation followed by a prefix operation, like "{}||!{}" in "a || !b",
+/// since the parser reads the operators as one. Other operations are returned unchanged.
+async fn split_prefix_operation(
+    operation: String,
+    values: &mut Vec<Effects>,
+    code_verifier: &CodeVerifier<'_>,
+    span: &Span,
+) -> String {
+    let error = span.make_error(ParsingMessage::UnknownOperation());
+    if values.len() != 2 || operation.len() <= 4 || !operation.starts_with("{}") || !operation.ends_with("{}") {
+        return operation;
+    }
+    let getter =
+        OperationGetter { syntax: code_verifier.syntax.clone(), operation: vec![operation.clone()], error: error.clone() };
+    if getter.await.is_ok() {
+        return operation;
+    }
+
+    let symbols = &operation[2..operation.len() - 2];
+    for (i, _) in symbols.char_indices().skip(1) {
+        let binary = format!("{{}}{}{{}}", &symbols[..i]);
+        let prefix = format!("{}{{}}", &symbols[i..]);
+        let getter =
+            OperationGetter { syntax: code_verifier.syntax.clone(), operation: vec![binary.clone()], error: error.clone() };
+        if getter.await.is_err() {
+            continue;
+        }
+        let getter =
+            OperationGetter { syntax: code_verifier.syntax.clone(), operation: vec![prefix.clone()], error: error.clone() };
+        if getter.await.is_err() {
+            continue;
+        }
+
+        let right = values.pop().unwrap();
+        values.push(Effects::new(right.span.clone(), EffectType::Operation(prefix, vec![right])));
+        return binary;
+    }
+    return operation;
+}
+
 /// Checks if two operations can be combined
+#[async_recursion]
 async fn combine_operation(
     operation: &String,
     values: &mut Vec<Effects>,
@@ -85,7 +177,18 @@ async fn combine_operation(
             }
         }
 
-        if let EffectType::Operation(inner_operation, effects) = last.types {
+        if let EffectType::Operation(inner_operation, mut effects) = last.types {
+            // The inner operation could be two operations read as one too, like the "{}||!{}" in "!a || !b"
+            let mut inner_operation = split_prefix_operation(inner_operation, &mut effects, code_verifier, span).await;
+            // The inner operation is combined with its own inner operation first, so it's finished, like the "{}[{}"
+            // in "a + b[c]", and ordered, like the "2 * 3 == 4" in "1 - 2 * 3 == 4", before it's ordered with this one
+            let mut combining = effects.clone();
+            if let Ok(Some(found)) = combine_operation(&inner_operation, &mut combining, code_verifier, span).await {
+                inner_operation.clone_from(
+                    Attribute::find_attribute("operation", &found.attributes).unwrap().as_string_attribute().unwrap(),
+                );
+                effects = combining;
+            }
             if operation.ends_with("{}") && inner_operation.starts_with("{}") {
                 let combined = operation[0..operation.len() - 2].to_string() + &inner_operation;
                 let new_operation = if operation.starts_with("{}") && inner_operation.ends_with("{}") {
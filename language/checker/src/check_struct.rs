@@ -26,6 +26,7 @@ pub async fn verify_struct(
             modifiers: field.modifiers,
             attributes: field.attributes,
             field: FinalizedField { field_type, name: field.field.name },
+            span: field.span,
         })
     }
 
@@ -8,15 +8,15 @@ use std::task::{Context, Poll};
 
 use async_recursion::async_recursion;
 use data::tokens::Span;
-use syntax::async_util::AsyncDataGetter;
+use syntax::async_util::{wait_on, AsyncDataGetter};
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
-use syntax::program::code::{FinalizedEffectType, FinalizedEffects, FinalizedMemberField};
+use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedMemberField};
 use syntax::program::function::{display_parenless, CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
 use syntax::program::r#struct::{FinalizedStruct, StructData};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::FinalizedTypes;
 use syntax::top_element_manager::ImplWaiter;
-use syntax::{ProcessManager, SimpleVariableManager};
+use syntax::{is_modifier, Modifier, ProcessManager, SimpleVariableManager};
 
 use crate::get_return;
 
@@ -32,22 +32,34 @@ pub async fn degeneric_effect(
 ) -> Result<(), ParsingError> {
     match effect {
         FinalizedEffectType::CreateVariable(name, value, types) => {
+            // The value is degenericed first so variables it declares, like the one holding a "?" value, exist
+            degeneric_effect(&mut value.types, syntax, process_manager, variables, span).await?;
             *types = get_return(&value.types, variables, syntax).await.unwrap();
             variables.variables.insert(name.clone(), types.clone());
-            degeneric_effect(&mut value.types, syntax, process_manager, variables, span).await?;
             degeneric_type(types, process_manager.generics(), syntax).await;
         }
         FinalizedEffectType::CompareJump(effect, _, _) => {
             degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?
         }
-        FinalizedEffectType::CodeBody(body) => degeneric_code_body(body, process_manager, variables, syntax).await?,
-        FinalizedEffectType::MethodCall(calling, function, arguments, _return_type) => {
+        FinalizedEffectType::CodeBody(body) => {
+            degeneric_code_body(body, process_manager, &mut variables.clone(), syntax).await?
+        }
+        FinalizedEffectType::MethodCall(calling, function, arguments, return_type) => {
             if let Some(found) = calling {
                 degeneric_effect(&mut found.types, syntax, process_manager, variables, span).await?;
             }
 
+            // Explicit return types, like "value.cast<f32>()", fill in generics the arguments can't
+            let returning = match return_type {
+                Some(found) => {
+                    degeneric_type(found, process_manager.generics(), syntax).await;
+                    Some((found.clone(), span.clone()))
+                }
+                None => None,
+            };
             *function =
-                degeneric_function(function.clone(), process_manager.cloned(), arguments, syntax, variables, None).await?;
+                degeneric_function(function.clone(), process_manager.cloned(), arguments, syntax, variables, returning)
+                    .await?;
 
             for argument in &mut *arguments {
                 degeneric_effect(&mut argument.types, syntax, process_manager, variables, span).await?;
@@ -94,19 +106,76 @@ pub async fn degeneric_effect(
                 degeneric_effect(&mut found.types, syntax, process_manager, variables, span).await?;
             }
             let fields = types.get_fields();
+            let mut flattened = types.clone();
+            degeneric_type(&mut flattened, process_manager.generics(), syntax).await;
             let mut type_generics = process_manager.generics().clone();
             for i in 0..fields.len() {
                 let found = &mut effects[i].1;
-                found
-                    .types
-                    .get_nongeneric_return(variables)
-                    .unwrap()
-                    .resolve_generic(&fields[i].field.field_type, syntax, &mut type_generics, span.clone())
-                    .await?;
+                // Calls only generic in their return, like "allocate(capacity)", return the field's type
+                if let FinalizedEffectType::MethodCall(_, function, _, returning @ None) = &mut found.types {
+                    let field_type = &flattened.get_fields()[i].field.field_type;
+                    if function.return_type.as_ref().is_some_and(|inner| inner.name_safe().is_none())
+                        && field_type.name_safe().is_some()
+                    {
+                        *returning = Some(field_type.clone());
+                    }
+                }
+                degeneric_effect(&mut found.types, syntax, process_manager, variables, span).await?;
+                let found = found.types.get_nongeneric_return(variables).unwrap();
+                fields[i].field.field_type.resolve_generic(&found, syntax, &mut type_generics, span.clone()).await?;
+            }
+            degeneric_type(types, &type_generics, syntax).await;
+        }
+        FinalizedEffectType::CreateVariant(storing, types, _, effects) => {
+            degeneric_effect(&mut storing.types, syntax, process_manager, variables, span).await?;
+            let fields = types.get_fields();
+            let mut type_generics = process_manager.generics().clone();
+            for (index, found) in &mut *effects {
                 degeneric_effect(&mut found.types, syntax, process_manager, variables, span).await?;
+                let found = found.types.get_nongeneric_return(variables).unwrap();
+                fields[*index].field.field_type.resolve_generic(&found, syntax, &mut type_generics, span.clone()).await?;
             }
             degeneric_type(types, &type_generics, syntax).await;
         }
+        FinalizedEffectType::IsVariant(base, _) => {
+            degeneric_effect(&mut base.types, syntax, process_manager, variables, span).await?
+        }
+        FinalizedEffectType::ShortCircuit(first, second, _) => {
+            degeneric_effect(&mut first.types, syntax, process_manager, variables, span).await?;
+            degeneric_effect(&mut second.types, syntax, process_manager, variables, span).await?;
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            // The value's variable has to be declared before the calls that load it
+            for effect in [value, check, residual, unwrapped] {
+                degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?;
+            }
+        }
+        FinalizedEffectType::CreateClosure(arguments, returning, captures, body) => {
+            let mut closure_variables = variables.clone();
+            for argument in &mut *arguments {
+                degeneric_type(&mut argument.field_type, process_manager.generics(), syntax).await;
+                closure_variables
+                    .variables
+                    .insert(argument.name.clone(), FinalizedTypes::Reference(Box::new(argument.field_type.clone())));
+            }
+            if let Some(found) = returning {
+                degeneric_type(found, process_manager.generics(), syntax).await;
+            }
+            for (_, types) in &mut *captures {
+                degeneric_type(types, process_manager.generics(), syntax).await;
+            }
+            degeneric_code_body(body, process_manager, &mut closure_variables, syntax).await?;
+        }
+        FinalizedEffectType::FunctionReference(function) => {
+            *function =
+                degeneric_function(function.clone(), process_manager.cloned(), &vec![], syntax, variables, None).await?;
+        }
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            degeneric_effect(&mut calling.types, syntax, process_manager, variables, span).await?;
+            for argument in arguments {
+                degeneric_effect(&mut argument.types, syntax, process_manager, variables, span).await?;
+            }
+        }
         FinalizedEffectType::CreateArray(types, effects) => {
             if let Some(found) = types {
                 degeneric_type(found, process_manager.generics(), syntax).await;
@@ -115,6 +184,12 @@ pub async fn degeneric_effect(
                 degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?;
             }
         }
+        FinalizedEffectType::CreateTuple(types, effects) => {
+            degeneric_type(types, process_manager.generics(), syntax).await;
+            for effect in effects {
+                degeneric_effect(&mut effect.types, syntax, process_manager, variables, span).await?;
+            }
+        }
         FinalizedEffectType::VirtualCall(_, function, arguments, returning) => {
             *function = degeneric_function(
                 function.clone(),
@@ -130,21 +205,13 @@ pub async fn degeneric_effect(
             }
             degeneric_arguments(&function.arguments, arguments, syntax, variables, process_manager).await?;
         }
-        FinalizedEffectType::GenericVirtualCall(index, target, found, effects, returning) => {
-            syntax.lock().unwrap().process_manager.handle().lock().unwrap().spawn(
-                target.name.clone(),
-                degeneric_header(
-                    target.clone(),
-                    found.data.clone(),
-                    syntax.clone(),
-                    process_manager.cloned(),
-                    effects.clone(),
-                    variables.clone(),
-                    span.clone(),
-                ),
-            );
-
-            let output = AsyncDataGetter::new(syntax.clone(), target.clone()).await;
+        FinalizedEffectType::GenericVirtualCall(index, _, _, effects, returning) => {
+            // Flattening the trait degenerics its functions, so the call uses the flattened trait's function
+            let mut calling = get_return(&effects[0].types, variables, syntax).await.unwrap();
+            degeneric_type(&mut calling, process_manager.generics(), syntax).await;
+            let target = calling.inner_struct().data.functions[*index].clone();
+
+            let output = AsyncDataGetter::new(syntax.clone(), target).await;
             let mut temp = vec![];
             mem::swap(&mut temp, effects);
             *effect = FinalizedEffectType::VirtualCall(*index, output, temp, returning.clone());
@@ -201,9 +268,9 @@ pub async fn degeneric_arguments(
     for i in 0..base_arguments.len() {
         let arg_return_type = get_return(&arguments[i].types, variables, syntax).await.unwrap();
         let base_field_type = &base_arguments[i].field.field_type;
-        // Only downcast if an implementation was found and it's not generic. Don't downcast if they're of the same type.
+        // Only downcast to a trait if an implementation was found and it's not generic. Don't downcast if they're of the same type.
         if !arg_return_type.of_type_sync(base_field_type, None).0
-            && base_field_type.inner_struct_safe().is_some()
+            && base_field_type.inner_struct_safe().map_or(false, |inner| is_modifier(inner.data.modifiers, Modifier::Trait))
             && arg_return_type.of_type(base_field_type, syntax.clone()).await
         {
             // Handle downcasting
@@ -246,7 +313,13 @@ pub async fn degeneric_function(
     // Degenerics the return type if there is one and returning is some.
     if let Some(inner) = method.return_type.clone() {
         if let Some((returning, span)) = returning {
-            inner.resolve_generic(&returning, syntax, manager.mut_generics(), span).await?;
+            let mut explicit = HashMap::default();
+            inner.resolve_generic(&returning, syntax, &mut explicit, span).await?;
+            // Like get_return, generics the return type doesn't hold, like K and V in "HashMap::new<u64>()", are the type given
+            for name in method.generics.keys() {
+                let found = explicit.remove(name).unwrap_or_else(|| returning.clone());
+                manager.mut_generics().insert(name.clone(), found);
+            }
         }
     }
 
@@ -274,14 +347,12 @@ pub async fn degeneric_function(
     // Now all the generic types have been resolved, it's time to replace them with
     // their solidified versions.
     // Degenericed function names have a $ separating the name and the generics.
-    let name = if manager.generics().is_empty() {
+    // Only the method's own generics are named, in order, since the manager also has the caller's.
+    let generics = method.generics.keys().filter_map(|key| manager.generics().get(key)).collect::<Vec<_>>();
+    let name = if generics.is_empty() {
         method.data.name.split("$").next().unwrap().to_string()
     } else {
-        format!(
-            "{}${}",
-            method.data.name.split("$").next().unwrap(),
-            display_parenless(&manager.generics().values().collect(), "_")
-        )
+        format!("{}${}", method.data.name.split("$").next().unwrap(), display_parenless(&generics, "_"))
     };
 
     // If this function has already been degenericed, use the previous one.
@@ -347,6 +418,16 @@ async fn degeneric_code(
 
     // Degenerics the code body.
     degeneric_code_body(&mut code, &*manager, &mut variables, &syntax).await?;
+    if let Some(returning) = &degenericed_method.return_type {
+        downcast_returns(
+            &mut code,
+            returning,
+            &*manager,
+            &mut SimpleVariableManager::for_function(&degenericed_method),
+            &syntax,
+        )
+        .await?;
+    }
 
     // Combines the degenericed function with the degenericed code to finalize it.
     let output = CodelessFinalizedFunction::clone(degenericed_method.deref()).add_code(code);
@@ -360,6 +441,52 @@ async fn degeneric_code(
     return Ok(());
 }
 
+/// Downcasts returned structs to the return type if it's a trait, which generic returns like "Iter<T>" skipped
+/// when they were checked because the returned type wasn't known yet.
+#[async_recursion]
+async fn downcast_returns(
+    code: &mut FinalizedCodeBody,
+    returning: &FinalizedTypes,
+    process_manager: &dyn ProcessManager,
+    variables: &mut SimpleVariableManager,
+    syntax: &Arc<Mutex<Syntax>>,
+) -> Result<(), ParsingError> {
+    if !returning.inner_struct_safe().map_or(false, |inner| is_modifier(inner.data.modifiers, Modifier::Trait)) {
+        return Ok(());
+    }
+
+    for expression in &mut code.expressions {
+        match &mut expression.effect.types {
+            FinalizedEffectType::CreateVariable(name, _, types) => {
+                variables.variables.insert(name.clone(), types.clone());
+            }
+            FinalizedEffectType::CodeBody(body) => {
+                downcast_returns(body, returning, process_manager, &mut variables.clone(), syntax).await?
+            }
+            _ => {}
+        }
+
+        if !matches!(expression.expression_type, ExpressionType::Return(_))
+            || matches!(expression.effect.types, FinalizedEffectType::Downcast(_, _, _))
+        {
+            continue;
+        }
+        let Some(found) = get_return(&expression.effect.types, variables, syntax).await else {
+            continue;
+        };
+        if found.inner_struct_safe().map_or(true, |inner| is_modifier(inner.data.modifiers, Modifier::Trait)) {
+            continue;
+        }
+
+        let span = expression.effect.span.clone();
+        let base = mem::replace(&mut expression.effect, FinalizedEffects::new(span.clone(), FinalizedEffectType::NOP));
+        let mut downcast = FinalizedEffectType::Downcast(Box::new(base), returning.clone(), vec![]);
+        degeneric_effect(&mut downcast, syntax, process_manager, variables, &span).await?;
+        expression.effect = FinalizedEffects::new(span, downcast);
+    }
+    return Ok(());
+}
+
 /// A waiter used by generics trying to degeneric a function that returns when the target function's
 /// code is in the compiling list
 struct FunctionWaiter {
@@ -383,6 +510,7 @@ impl Future for FunctionWaiter {
                 .entry(self.data.name.clone())
                 .or_insert(vec![])
                 .push(cx.waker().clone());
+            wait_on(self.data.name.clone(), &self.data.span);
             Poll::Pending
         };
     }
@@ -416,8 +544,7 @@ pub async fn degeneric_type(
             }
         }
         FinalizedTypes::GenericType(base, bounds) => {
-            degeneric_type(base, generics, syntax).await;
-
+            // The base's generics are filled by the bounds, not by the caller's generics that share their names
             for bound in &mut *bounds {
                 degeneric_type(bound, generics, syntax).await;
             }
@@ -458,20 +585,36 @@ pub async fn degeneric_type(
                 let mut data = FinalizedStruct::clone(AsyncDataGetter::new(syntax.clone(), base.data.clone()).await.deref());
                 data.data.clone_from(&arc_other);
 
-                // Update the program's fields
+                // Update the program's fields, which use the base's generics
+                let base_generics = base.generics.keys().cloned().zip(bounds.iter().cloned()).collect::<HashMap<_, _>>();
                 for field in &mut data.fields {
-                    degeneric_type(&mut field.field.field_type, generics, syntax).await;
+                    degeneric_type(&mut field.field.field_type, &base_generics, syntax).await;
                 }
 
                 let data = Arc::new(data);
                 // Add the flattened type to the syntax
-                let mut locked = syntax.lock().unwrap();
-                locked.structures.add_data(arc_other, data.clone());
-                *types = FinalizedTypes::Struct(data.clone());
+                syntax.lock().unwrap().structures.add_data(arc_other.clone(), data.clone());
+                if is_modifier(base.data.modifiers, Modifier::Trait) {
+                    degeneric_trait_functions(base, &arc_other, bounds, syntax).await;
+                }
+                *types = FinalizedTypes::Struct(data);
             }
         }
         FinalizedTypes::Reference(inner) => degeneric_type(inner, generics, syntax).await,
         FinalizedTypes::Array(inner) => degeneric_type(inner, generics, syntax).await,
+        FinalizedTypes::Function(arguments, returning) => {
+            for argument in arguments {
+                degeneric_type(argument, generics, syntax).await;
+            }
+            if let Some(returning) = returning {
+                degeneric_type(returning, generics, syntax).await;
+            }
+        }
+        FinalizedTypes::Tuple(types) => {
+            for found in types {
+                degeneric_type(found, generics, syntax).await;
+            }
+        }
         FinalizedTypes::Struct(inner) => {
             let mut temp = FinalizedStruct::clone(inner);
             for field in &mut temp.fields {
@@ -482,6 +625,48 @@ pub async fn degeneric_type(
     };
 }
 
+/// Degenerics the function headers of a flattened trait, so virtual calls on it have solidified types
+async fn degeneric_trait_functions(
+    base: &Arc<FinalizedStruct>,
+    flattened: &Arc<StructData>,
+    bounds: &[FinalizedTypes],
+    syntax: &Arc<Mutex<Syntax>>,
+) {
+    let generics = base.generics.keys().cloned().zip(bounds.iter().cloned()).collect::<HashMap<_, _>>();
+    for (function, degenericed) in base.data.functions.iter().zip(&flattened.functions) {
+        {
+            // The functions' types can contain the trait again, which would flatten it again
+            let mut locked = syntax.lock().unwrap();
+            if locked.functions.types.contains_key(&degenericed.name) {
+                continue;
+            }
+            locked.functions.add_type(degenericed.clone());
+        }
+
+        let mut new_method =
+            CodelessFinalizedFunction::clone(AsyncDataGetter::new(syntax.clone(), function.clone()).await.deref());
+        new_method.generics.clear();
+        new_method.data = degenericed.clone();
+        for argument in &mut new_method.arguments {
+            degeneric_type(&mut argument.field.field_type, &generics, syntax).await;
+        }
+        if let Some(returning) = &mut new_method.return_type {
+            degeneric_type(returning, &generics, syntax).await;
+        }
+
+        // Trait functions have no code, so the empty body is added like a verified trait function's would be
+        let empty = new_method.clone().add_code(FinalizedCodeBody::new(vec![], "empty".to_string(), true));
+        let mut locked = syntax.lock().unwrap();
+        locked.functions.add_data(degenericed.clone(), Arc::new(new_method));
+        locked.generics.insert(degenericed.name.clone(), Arc::new(empty));
+        if let Some(wakers) = locked.compiling_wakers.get(&degenericed.name) {
+            for waker in wakers {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
 /// Degenerics the type by replacing all generics with their solidified value.
 /// Ignores generic types
 #[async_recursion]
@@ -505,6 +690,19 @@ pub async fn degeneric_type_no_generic_types(
         }
         FinalizedTypes::Reference(inner) => degeneric_type_no_generic_types(inner, generics, syntax).await,
         FinalizedTypes::Array(inner) => degeneric_type_no_generic_types(inner, generics, syntax).await,
+        FinalizedTypes::Function(arguments, returning) => {
+            for argument in arguments {
+                degeneric_type_no_generic_types(argument, generics, syntax).await;
+            }
+            if let Some(returning) = returning {
+                degeneric_type_no_generic_types(returning, generics, syntax).await;
+            }
+        }
+        FinalizedTypes::Tuple(types) => {
+            for found in types {
+                degeneric_type_no_generic_types(found, generics, syntax).await;
+            }
+        }
         FinalizedTypes::Struct(inner) => {
             let mut temp = FinalizedStruct::clone(inner);
             for field in &mut temp.fields {
@@ -545,6 +743,19 @@ pub async fn degeneric_type_fields(
         }
         FinalizedTypes::Reference(inner) => degeneric_type_fields(inner, generics, syntax).await,
         FinalizedTypes::Array(inner) => degeneric_type_fields(inner, generics, syntax).await,
+        FinalizedTypes::Function(arguments, returning) => {
+            for argument in arguments {
+                degeneric_type_fields(argument, generics, syntax).await;
+            }
+            if let Some(returning) = returning {
+                degeneric_type_fields(returning, generics, syntax).await;
+            }
+        }
+        FinalizedTypes::Tuple(types) => {
+            for found in types {
+                degeneric_type_fields(found, generics, syntax).await;
+            }
+        }
         FinalizedTypes::Struct(inner) => {
             let mut temp = FinalizedStruct::clone(inner);
             for field in &mut temp.fields {
@@ -555,63 +766,6 @@ pub async fn degeneric_type_fields(
     };
 }
 
-/// Degenerics a function header, for virtual function calls
-pub async fn degeneric_header(
-    degenericed: Arc<FunctionData>,
-    base: Arc<FunctionData>,
-    syntax: Arc<Mutex<Syntax>>,
-    mut manager: Box<dyn ProcessManager>,
-    arguments: Vec<FinalizedEffects>,
-    variables: SimpleVariableManager,
-    span: Span,
-) -> Result<(), ParsingError> {
-    let function: Arc<CodelessFinalizedFunction> = AsyncDataGetter { getting: base, syntax: syntax.clone() }.await;
-
-    let return_type = arguments[0].types.get_nongeneric_return(&variables).unwrap();
-    let (_, generics) = return_type.inner_generic_type().unwrap();
-    assert_eq!(function.generics.len(), generics.len());
-
-    let mut iterator = function.generics.iter();
-    for generic in generics {
-        let (name, bounds) = iterator.next().unwrap();
-        for bound in bounds {
-            if !generic.of_type(bound, syntax.clone()).await {
-                // TODO see if this is needed
-                return Err(span.make_error(ParsingMessage::ShouldntSee("Bounds sanity check!")));
-            }
-        }
-        manager.mut_generics().insert(name.clone(), generic.clone());
-    }
-
-    // Copy the method and degeneric every type inside of it.
-    let mut new_method = CodelessFinalizedFunction::clone(&function);
-    // Delete the generics because now they are all solidified.
-    new_method.generics.clear();
-    new_method.data = degenericed;
-
-    // Degeneric the arguments.
-    for arguments in &mut new_method.arguments {
-        degeneric_type(&mut arguments.field.field_type, &manager.generics(), &syntax).await;
-    }
-
-    // Degeneric the return type if there is one.
-    if let Some(returning) = &mut new_method.return_type {
-        degeneric_type(returning, &manager.generics(), &syntax).await;
-    }
-
-    let new_method = Arc::new(new_method);
-
-    //let mut code =
-    //    CodelessFinalizedFunction::clone(&new_method).add_code(FinalizedCodeBody::new(vec![], "empty".to_string(), true));
-
-    let mut locked = syntax.lock().unwrap();
-    locked.functions.add_type(new_method.data.clone());
-    locked.functions.add_data(new_method.data.clone(), new_method.clone());
-
-    // Give the compiler the empty body
-    return Ok(());
-}
-
 /// Degenerics a finalized struct
 pub async fn degeneric_struct(
     mut structure: FinalizedStruct,
@@ -620,7 +774,8 @@ pub async fn degeneric_struct(
 ) -> Arc<FinalizedStruct> {
     let targets: Vec<_> =
         generics.iter().filter(|(key, _)| structure.generics.contains_key(*key)).map(|(_, value)| value).collect();
-    if targets.is_empty() {
+    // Structs made in generic code, like "SlotIter<K>", stay generic until the code is degenericed
+    if targets.is_empty() || targets.iter().any(|target| target.name_safe().is_none()) {
         return Arc::new(structure);
     }
     let mut data = StructData::clone(&structure.data);
@@ -0,0 +1,377 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use async_recursion::async_recursion;
+use data::tokens::Span;
+use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedExpression};
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody};
+use syntax::program::syntax::Syntax;
+use syntax::program::types::FinalizedTypes;
+use syntax::{is_modifier, Modifier, SimpleVariableManager};
+
+use crate::get_return;
+
+/// The variable returned values are stored in while the function's owned variables are freed.
+const RETURNING: &str = "$returning";
+
+/// A field holding the heap allocation its structure was created with, which is freed along with the structure.
+struct OwnedField {
+    /// The name of the field
+    name: String,
+    /// The type of the structure the field is in
+    structure: FinalizedTypes,
+    /// The fields of this field's value that own their allocation
+    fields: Vec<OwnedField>,
+}
+
+/// Frees every variable that owns a heap allocation once it goes out of scope.
+/// A variable owns its value if it's created from a struct, array or string literal, it's only declared once,
+/// and it never escapes the function. Variables escape by being returned, stored, aliased or passed to any
+/// function that could keep a reference to them. Fields created with a literal are owned the same way.
+pub async fn insert_drops(code: &mut FinalizedCodeBody, mut variables: SimpleVariableManager, syntax: &Arc<Mutex<Syntax>>) {
+    let mut declared = HashMap::default();
+    find_owned(code, &mut declared);
+
+    let mut escaped = HashSet::default();
+    for line in &code.expressions {
+        find_escapes(&line.effect, &mut escaped);
+    }
+
+    let owned = declared
+        .into_iter()
+        .filter(|(name, _)| !escaped.contains(name) && !variables.variables.contains_key(name))
+        .filter_map(|(name, fields)| fields.map(|fields| (name.clone(), remove_escaped(fields, &name, &escaped))))
+        .collect::<HashMap<_, _>>();
+    if owned.is_empty() {
+        return;
+    }
+
+    let mut parents = HashMap::default();
+    find_parents(code, &mut Vec::default(), &mut parents);
+    drop_body(code, Vec::default(), &parents, &owned, &mut variables, syntax).await;
+}
+
+/// Finds every variable declared in the code, and the fields it owns if it owns a fresh heap allocation.
+fn find_owned(code: &FinalizedCodeBody, declared: &mut HashMap<String, Option<Vec<OwnedField>>>) {
+    for line in &code.expressions {
+        match &line.effect.types {
+            FinalizedEffectType::CreateVariable(name, value, _) => {
+                // Variables declared more than once can't be tracked
+                declared
+                    .entry(name.clone())
+                    .and_modify(|owns| *owns = None)
+                    .or_insert_with(|| is_allocation(&value.types).then(|| find_owned_fields(&value.types)));
+            }
+            FinalizedEffectType::CodeBody(body) => find_owned(body, declared),
+            _ => {}
+        }
+    }
+}
+
+/// Finds the fields of a new struct that are set to a fresh heap allocation.
+fn find_owned_fields(effect: &FinalizedEffectType) -> Vec<OwnedField> {
+    let (structure, arguments) = match effect {
+        FinalizedEffectType::CreateStruct(Some(_), structure, arguments) => (structure, arguments),
+        _ => return Vec::default(),
+    };
+
+    let fields = structure.get_fields();
+    return arguments
+        .iter()
+        .filter(|(_, value)| is_allocation(&value.types))
+        .map(|(index, value)| OwnedField {
+            name: fields[*index].field.name.clone(),
+            structure: structure.clone(),
+            fields: find_owned_fields(&value.types),
+        })
+        .collect();
+}
+
+/// Removes the fields that escape, which are named by their path from the variable like "value.inner".
+fn remove_escaped(fields: Vec<OwnedField>, path: &str, escaped: &HashSet<String>) -> Vec<OwnedField> {
+    return fields
+        .into_iter()
+        .filter_map(|mut field| {
+            let path = format!("{}.{}", path, field.name);
+            if escaped.contains(&path) {
+                return None;
+            }
+            field.fields = remove_escaped(field.fields, &path, escaped);
+            return Some(field);
+        })
+        .collect();
+}
+
+/// Finds the labels of the code bodies each code body is inside of.
+/// The end of a body (its label plus "end") continues in the body's parent, so it's inside the same bodies.
+fn find_parents(code: &FinalizedCodeBody, parents: &mut Vec<String>, found: &mut HashMap<String, Vec<String>>) {
+    found.insert(code.label.clone(), parents.clone());
+    found.insert(code.label.clone() + "end", parents.clone());
+    parents.push(code.label.clone());
+    for line in &code.expressions {
+        if let FinalizedEffectType::CodeBody(body) = &line.effect.types {
+            find_parents(body, parents, found);
+        }
+    }
+    parents.pop();
+}
+
+/// Checks if the effect allocates a new value on the heap that nothing else points to.
+fn is_allocation(effect: &FinalizedEffectType) -> bool {
+    return match effect {
+        FinalizedEffectType::CreateStruct(Some(target), _, _) | FinalizedEffectType::CreateVariant(target, _, _, _) => {
+            matches!(target.types, FinalizedEffectType::HeapAllocate(_))
+        }
+        FinalizedEffectType::HeapStore(inner) => {
+            matches!(inner.types, FinalizedEffectType::CreateArray(_, _) | FinalizedEffectType::String(_))
+        }
+        // Array literals go through the CreateArray operation, which returns the new array
+        FinalizedEffectType::MethodCall(_, function, arguments, _) => {
+            function.data.name.starts_with("array::CreateArray")
+                && arguments.first().map_or(false, |argument| is_allocation(&argument.types))
+        }
+        _ => false,
+    };
+}
+
+/// Finds every variable that escapes in the effect.
+// skipcq: RS-R1000 Match statements have complexity calculated incorrectly
+fn find_escapes(effect: &FinalizedEffects, escaped: &mut HashSet<String>) {
+    match &effect.types {
+        FinalizedEffectType::LoadVariable(name) => {
+            escaped.insert(name.clone());
+        }
+        // Accessing a field leaks the field's value, but not the struct itself.
+        FinalizedEffectType::Load(base, _, _) => {
+            if let Some(path) = field_path(effect) {
+                escaped.insert(path);
+            }
+            borrow(base, escaped);
+        }
+        FinalizedEffectType::IsVariant(base, _) => borrow(base, escaped),
+        // Whatever is set no longer holds its own allocation
+        FinalizedEffectType::Set(setting, value) => {
+            if let Some(path) = field_path(setting) {
+                escaped.insert(path);
+            }
+            borrow(setting, escaped);
+            find_escapes(value, escaped);
+        }
+        FinalizedEffectType::MethodCall(pointer, function, arguments, _) => {
+            if let Some(pointer) = pointer {
+                find_escapes(pointer, escaped);
+            }
+            let borrowing = borrows_arguments(function);
+            for argument in arguments {
+                if borrowing {
+                    borrow(argument, escaped);
+                } else {
+                    find_escapes(argument, escaped);
+                }
+            }
+        }
+        FinalizedEffectType::CodeBody(body) => {
+            for line in &body.expressions {
+                find_escapes(&line.effect, escaped);
+            }
+        }
+        FinalizedEffectType::CreateVariable(_, inner, _)
+        | FinalizedEffectType::CompareJump(inner, _, _)
+        | FinalizedEffectType::Downcast(inner, _, _)
+        | FinalizedEffectType::HeapStore(inner)
+        | FinalizedEffectType::ReferenceLoad(inner)
+        | FinalizedEffectType::StackStore(inner)
+        | FinalizedEffectType::Free(inner) => find_escapes(inner, escaped),
+        FinalizedEffectType::CreateStruct(target, _, arguments) => {
+            if let Some(target) = target {
+                find_escapes(target, escaped);
+            }
+            for (_, argument) in arguments {
+                find_escapes(argument, escaped);
+            }
+        }
+        FinalizedEffectType::CreateVariant(target, _, _, arguments) => {
+            find_escapes(target, escaped);
+            for (_, argument) in arguments {
+                find_escapes(argument, escaped);
+            }
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            for effect in [value, check, residual, unwrapped] {
+                find_escapes(effect, escaped);
+            }
+        }
+        FinalizedEffectType::ShortCircuit(first, second, _) => {
+            find_escapes(first, escaped);
+            find_escapes(second, escaped);
+        }
+        // Closures keep their captured variables, so they escape.
+        FinalizedEffectType::CreateClosure(_, _, captures, _) => {
+            for (name, _) in captures {
+                escaped.insert(name.clone());
+            }
+        }
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            find_escapes(calling, escaped);
+            for argument in arguments {
+                find_escapes(argument, escaped);
+            }
+        }
+        FinalizedEffectType::CreateArray(_, values)
+        | FinalizedEffectType::CreateTuple(_, values)
+        | FinalizedEffectType::GenericMethodCall(_, _, values)
+        | FinalizedEffectType::VirtualCall(_, _, values, _)
+        | FinalizedEffectType::GenericVirtualCall(_, _, _, values, _) => {
+            for value in values {
+                find_escapes(value, escaped);
+            }
+        }
+        FinalizedEffectType::NOP
+        | FinalizedEffectType::Jump(_)
+        | FinalizedEffectType::FunctionReference(_)
+        | FinalizedEffectType::Float(_, _)
+        | FinalizedEffectType::UInt(_, _)
+        | FinalizedEffectType::Bool(_)
+        | FinalizedEffectType::String(_)
+        | FinalizedEffectType::Char(_)
+        | FinalizedEffectType::HeapAllocate(_) => {}
+    }
+}
+
+/// Finds escapes in an effect that's only borrowed, so a variable or field it directly loads doesn't escape.
+fn borrow(effect: &FinalizedEffects, escaped: &mut HashSet<String>) {
+    match &effect.types {
+        FinalizedEffectType::LoadVariable(_) => {}
+        FinalizedEffectType::Load(base, _, _) => borrow(base, escaped),
+        _ => find_escapes(effect, escaped),
+    }
+}
+
+/// Gets the path of the variable or field the effect loads, like "value.inner"
+fn field_path(effect: &FinalizedEffects) -> Option<String> {
+    return match &effect.types {
+        FinalizedEffectType::LoadVariable(name) => Some(name.clone()),
+        FinalizedEffectType::Load(base, field, _) => field_path(base).map(|path| format!("{}.{}", path, field)),
+        _ => None,
+    };
+}
+
+/// Internal functions (like printf or math operations) never keep their arguments,
+/// so they only borrow them as long as they can't return one of them.
+fn borrows_arguments(function: &CodelessFinalizedFunction) -> bool {
+    return is_modifier(function.data.modifiers, Modifier::Internal)
+        && function.return_type.as_ref().map_or(true, |returning| returning.is_primitive());
+}
+
+/// Inserts the frees into a code body, freeing the variables declared in the code bodies a jump leaves
+/// and every owned variable in scope before a return.
+/// The scopes are the labels of the code bodies this one is in, along with the owned variables declared in them.
+#[async_recursion]
+async fn drop_body(
+    code: &mut FinalizedCodeBody,
+    mut scopes: Vec<(String, Vec<String>)>,
+    parents: &HashMap<String, Vec<String>>,
+    owned: &HashMap<String, Vec<OwnedField>>,
+    variables: &mut SimpleVariableManager,
+    syntax: &Arc<Mutex<Syntax>>,
+) {
+    scopes.push((code.label.clone(), Vec::default()));
+    let mut output = Vec::default();
+    for mut line in mem::take(&mut code.expressions) {
+        if let FinalizedEffectType::CodeBody(body) = &mut line.effect.types {
+            drop_body(body, scopes.clone(), parents, owned, &mut variables.clone(), syntax).await;
+            output.push(line);
+            continue;
+        }
+
+        match &line.effect.types {
+            FinalizedEffectType::CreateVariable(name, _, types) => {
+                variables.variables.insert(name.clone(), types.clone());
+                if owned.contains_key(name) {
+                    scopes.last_mut().unwrap().1.push(name.clone());
+                }
+            }
+            // Loops and if statements end their bodies with a jump, and breaks and continues jump out of the loop's
+            // body. Every body that isn't around the jump's target is left. Comparison jumps are skipped because
+            // their condition (like a do-while's) could still read the variables.
+            FinalizedEffectType::Jump(label) => {
+                // Jumps to an unknown label only leave the current body
+                let kept = match parents.get(label) {
+                    Some(kept) => kept.clone(),
+                    None => scopes[..scopes.len() - 1].iter().map(|(label, _)| label.clone()).collect(),
+                };
+                for (_, declared) in scopes.iter().rev().filter(|(label, _)| !kept.contains(label)) {
+                    free(declared, owned, &mut output);
+                }
+            }
+            _ => {}
+        }
+
+        let in_scope = scopes.iter().flat_map(|(_, declared)| declared.clone()).collect::<Vec<_>>();
+        if !matches!(line.expression_type, ExpressionType::Return(_)) || in_scope.is_empty() {
+            output.push(line);
+        } else if matches!(line.effect.types, FinalizedEffectType::NOP) {
+            free(&in_scope, owned, &mut output);
+            output.push(line);
+        } else {
+            // The returned value is computed before freeing, because it could read from the freed variables.
+            let span = line.effect.span.clone();
+            match get_return(&line.effect.types, variables, syntax).await {
+                Some(types) => {
+                    output.push(FinalizedExpression::new(
+                        ExpressionType::Line,
+                        FinalizedEffects::new(
+                            span.clone(),
+                            FinalizedEffectType::CreateVariable(RETURNING.to_string(), Box::new(line.effect), types),
+                        ),
+                    ));
+                    free(&in_scope, owned, &mut output);
+                    output.push(FinalizedExpression::new(
+                        line.expression_type,
+                        FinalizedEffects::new(span, FinalizedEffectType::LoadVariable(RETURNING.to_string())),
+                    ));
+                }
+                // Returning a call to a void function, so it's run like a normal line before freeing
+                None => {
+                    output.push(FinalizedExpression::new(ExpressionType::Line, line.effect));
+                    free(&in_scope, owned, &mut output);
+                    output.push(FinalizedExpression::new(
+                        line.expression_type,
+                        FinalizedEffects::new(span, FinalizedEffectType::NOP),
+                    ));
+                }
+            }
+        }
+    }
+    code.expressions = output;
+}
+
+/// Frees the variables, in the reverse order they were declared in.
+fn free(variables: &[String], owned: &HashMap<String, Vec<OwnedField>>, output: &mut Vec<FinalizedExpression>) {
+    for variable in variables.iter().rev() {
+        let loading = FinalizedEffects::new(Span::default(), FinalizedEffectType::LoadVariable(variable.clone()));
+        free_fields(&loading, &owned[variable], output);
+        output.push(free_line(loading));
+    }
+}
+
+/// Frees the owned fields of a structure, before the structure itself is freed.
+fn free_fields(structure: &FinalizedEffects, fields: &[OwnedField], output: &mut Vec<FinalizedExpression>) {
+    for field in fields.iter().rev() {
+        let loading = FinalizedEffects::new(
+            Span::default(),
+            FinalizedEffectType::Load(Box::new(structure.clone()), field.name.clone(), field.structure.clone()),
+        );
+        free_fields(&loading, &field.fields, output);
+        output.push(free_line(loading));
+    }
+}
+
+/// Makes the line freeing the value
+fn free_line(freeing: FinalizedEffects) -> FinalizedExpression {
+    return FinalizedExpression::new(
+        ExpressionType::Line,
+        FinalizedEffects::new(Span::default(), FinalizedEffectType::Free(Box::new(freeing))),
+    );
+}
@@ -14,12 +14,15 @@ use crate::degeneric::degeneric_type_no_generic_types;
 use syntax::async_util::NameResolver;
 use syntax::errors::ParsingError;
 use syntax::program::code::FinalizedEffectType;
+use syntax::program::function::CodelessFinalizedFunction;
 use syntax::program::syntax::Syntax;
 use syntax::program::types::{FinalizedTypes, Types};
 use syntax::{ParsingFuture, SimpleVariableManager};
 
 use crate::output::TypesChecker;
 
+/// Checks closures and calls to function values
+pub mod check_closure;
 /// Checks code to perform internal linking and find any errors
 pub mod check_code;
 /// Checks functions
@@ -34,6 +37,8 @@ pub mod check_operator;
 pub mod check_struct;
 /// Degenerics types
 pub mod degeneric;
+/// Frees owned heap allocations when they go out of scope
+pub mod drops;
 /// Used to send data to be checked by the checker and then send the result to the compiler
 pub mod output;
 
@@ -58,6 +63,10 @@ pub struct CodeVerifier<'a> {
     process_manager: &'a TypesChecker,
     resolver: Box<dyn NameResolver>,
     return_type: Option<FinalizedTypes>,
+    /// If the code doesn't declare a return type but returns a value, so the type is taken from its first return with one
+    infer_return: bool,
+    /// The function whose return type is being inferred, until its first return with a value is found
+    inferring: Option<CodelessFinalizedFunction>,
     syntax: Arc<Mutex<Syntax>>,
 }
 
@@ -79,14 +88,14 @@ pub async fn get_return(
                         .map(|(name, _)| (name.clone(), return_type.clone()))
                         .collect::<HashMap<_, _>>();
                     degeneric_type_no_generic_types(&mut inner, &generics, syntax).await;
-                } else if let Some(calling) = args.get(0) {
+                } else if let (Some(calling), Some(argument)) = (args.first(), function.arguments.first()) {
+                    // The first argument is self for methods, free functions like "allocate<T>(length: u64)" use their own
                     let other = get_return(&calling.types, variables, syntax).await;
                     if let Some(found) = other {
                         let mut generics = HashMap::new();
-                        function
-                            .parent
-                            .as_ref()
-                            .unwrap()
+                        argument
+                            .field
+                            .field_type
                             .resolve_generic(&found, syntax, &mut generics, Span::default())
                             .await
                             .unwrap();
@@ -123,20 +132,17 @@ pub async fn get_return(
         FinalizedEffectType::HeapStore(inner)
         | FinalizedEffectType::StackStore(inner)
         | FinalizedEffectType::Set(_, inner) => get_return(&inner.types, variables, syntax).await,
+        // Propagating returns the unwrapped value.
+        FinalizedEffectType::Propagate(_, _, _, unwrapped) => get_return(&unwrapped.types, variables, syntax).await,
         // References return their inner type as well.
         FinalizedEffectType::ReferenceLoad(inner) => match get_return(&inner.types, variables, syntax).await.unwrap() {
             FinalizedTypes::Reference(inner) => Some(*inner),
             _ => panic!("Tried to load non-reference!"),
         },
         // Gets the type of the field in the program with that name.
-        FinalizedEffectType::Load(effect, name, _) => get_return(&effect.types, variables, syntax)
-            .await
-            .unwrap()
-            .inner_struct()
-            .fields
-            .iter()
-            .find(|field| &field.field.name == name)
-            .map(|field| field.field.field_type.clone()),
+        FinalizedEffectType::Load(effect, name, _) => {
+            get_return(&effect.types, variables, syntax).await.unwrap().get_field_type(name)
+        }
         _ => types.get_nongeneric_return(variables),
     };
 }
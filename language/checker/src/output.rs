@@ -8,7 +8,7 @@ use std::sync::Mutex;
 use crate::check_function::{verify_function, verify_function_code};
 use crate::check_struct::verify_struct;
 use crate::degeneric::degeneric_function;
-use syntax::async_util::{HandleWrapper, NameResolver};
+use syntax::async_util::{fail_task, HandleWrapper, NameResolver};
 use syntax::program::function::{
     CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction, FunctionData, UnfinalizedFunction,
 };
@@ -48,6 +48,7 @@ impl ProcessManager for TypesChecker {
     ) -> (CodelessFinalizedFunction, CodeBody) {
         return verify_function(function, syntax, self.include_refs).await.unwrap_or_else(|error| {
             syntax.lock().unwrap().errors.push(error.clone());
+            fail_task();
             (
                 CodelessFinalizedFunction {
                     generics: IndexMap::default(),
@@ -70,6 +71,7 @@ impl ProcessManager for TypesChecker {
     ) -> FinalizedFunction {
         return verify_function_code(self, resolver, code, function, syntax).await.unwrap_or_else(|error| {
             syntax.lock().unwrap().errors.push(error.clone());
+            fail_task();
             FinalizedFunction {
                 generics: IndexMap::default(),
                 fields: vec![],
@@ -98,6 +100,7 @@ impl ProcessManager for TypesChecker {
             Ok(output) => return output,
             Err(error) => {
                 syntax.lock().unwrap().errors.push(error.clone());
+                fail_task();
                 FinalizedStruct {
                     generics: IndexMap::default(),
                     fields: vec![],
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+use syntax::program::code::FinalizedEffectType;
+use syntax::program::function::FinalizedCodeBody;
+use syntax::program::types::FinalizedTypes;
+use syntax::VariableManager;
+
+use crate::value::Value;
+
+/// The state of a function being run
+pub struct Frame<'a> {
+    /// Every code body of the function and the line to start at, by label.
+    /// The end of a nested body (its label plus "end") continues after the line that runs it.
+    blocks: HashMap<String, (&'a FinalizedCodeBody, usize)>,
+    /// The label of the code body each code body is in
+    parents: HashMap<String, String>,
+    /// The variables declared in each code body with their types, by the code body's label
+    variables: HashMap<String, HashMap<String, (FinalizedTypes, Value)>>,
+    /// The label of the code body being run
+    pub current: String,
+}
+
+impl<'a> Frame<'a> {
+    /// Creates the frame for running the code with the given variables, like the function's arguments
+    pub fn new(code: &'a FinalizedCodeBody, variables: HashMap<String, (FinalizedTypes, Value)>) -> Self {
+        let mut frame = Self {
+            blocks: HashMap::default(),
+            parents: HashMap::default(),
+            variables: HashMap::default(),
+            current: code.label.clone(),
+        };
+        frame.add_blocks(code);
+        frame.variables.insert(code.label.clone(), variables);
+        return frame;
+    }
+
+    /// Adds the code body and every code body in it.
+    /// Labels can be reused, in which case the first one is kept like the LLVM compiler does.
+    fn add_blocks(&mut self, code: &'a FinalizedCodeBody) {
+        self.blocks.entry(code.label.clone()).or_insert((code, 0));
+        for (i, line) in code.expressions.iter().enumerate() {
+            if let FinalizedEffectType::CodeBody(body) = &line.effect.types {
+                self.blocks.entry(body.label.clone() + "end").or_insert((code, i + 1));
+                self.parents.entry(body.label.clone()).or_insert(code.label.clone());
+                self.add_blocks(body);
+            }
+        }
+    }
+
+    /// Gets the code body with the given label and the line to start at
+    pub fn block(&self, label: &str) -> Option<(&'a FinalizedCodeBody, usize)> {
+        return self.blocks.get(label).copied();
+    }
+
+    /// Declares a variable in the code body being run
+    pub fn declare(&mut self, name: String, types: FinalizedTypes, value: Value) {
+        self.variables.entry(self.current.clone()).or_default().insert(name, (types, value));
+    }
+
+    /// Finds a variable in the code body being run or the code bodies it's in
+    pub fn find(&self, name: &String) -> Option<&(FinalizedTypes, Value)> {
+        let mut label = &self.current;
+        loop {
+            if let Some(found) = self.variables.get(label).and_then(|variables| variables.get(name)) {
+                return Some(found);
+            }
+            label = self.parents.get(label)?;
+        }
+    }
+}
+
+impl Debug for Frame<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return f.debug_map().entries(self.variables.iter().map(|(label, variables)| (label, variables.keys()))).finish();
+    }
+}
+
+impl VariableManager for Frame<'_> {
+    fn get_variable(&self, name: &String) -> Option<FinalizedTypes> {
+        return self.find(name).map(|found| found.0.clone());
+    }
+}
@@ -0,0 +1,332 @@
+use std::rc::Rc;
+
+use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects};
+use syntax::program::function::FinalizedCodeBody;
+use syntax::program::types::FinalizedTypes;
+use syntax::{is_modifier, Modifier};
+
+use crate::frame::Frame;
+use crate::runtime::Runtime;
+use crate::value::{sign_extend, FunctionValue, Value};
+
+/// Stops running the function's code, passed up through the effects running it
+pub enum Exit {
+    /// Returns from the function being run with the returned value
+    Return(Option<Value>),
+    /// Stops the program because the code can't be run, like when dividing by zero
+    Error(String),
+}
+
+impl From<String> for Exit {
+    fn from(error: String) -> Self {
+        return Exit::Error(error);
+    }
+}
+
+/// The value of an effect, or the function stopping
+pub type Ran = Result<Option<Value>, Exit>;
+
+/// Runs a code body, following its jumps to the function's other code bodies until it runs out of code
+pub fn run_block<'a>(runtime: &mut Runtime, frame: &mut Frame<'a>, code: &'a FinalizedCodeBody) -> Ran {
+    let outer = frame.current.clone();
+    let (mut code, mut index) = (code, 0);
+    frame.current = code.label.clone();
+    loop {
+        let line = match code.expressions.get(index) {
+            Some(line) => line,
+            None => match frame.block(&(code.label.clone() + "end")) {
+                // Nested code bodies continue after the line that ran them
+                Some(next) => {
+                    (code, index) = next;
+                    frame.current = code.label.clone();
+                    continue;
+                }
+                None => {
+                    frame.current = outer;
+                    return Ok(None);
+                }
+            },
+        };
+        index += 1;
+
+        let jumping = match (&line.expression_type, &line.effect.types) {
+            (ExpressionType::Break, _) => {
+                let output = run_effect(runtime, frame, &line.effect);
+                frame.current = outer;
+                return output;
+            }
+            (_, FinalizedEffectType::CodeBody(body)) => &body.label,
+            (ExpressionType::Return(_), FinalizedEffectType::NOP) => return Err(Exit::Return(None)),
+            (ExpressionType::Return(_), _) => {
+                let returned = expect(runtime, frame, &line.effect)?;
+                return Err(Exit::Return(Some(get_returned(returned))));
+            }
+            (ExpressionType::Line, FinalizedEffectType::Jump(label)) => label,
+            (ExpressionType::Line, FinalizedEffectType::CompareJump(effect, then_body, else_body)) => {
+                if expect(runtime, frame, effect)?.as_bool() {
+                    then_body
+                } else {
+                    else_body
+                }
+            }
+            (ExpressionType::Line, _) => {
+                run_effect(runtime, frame, &line.effect)?;
+                continue;
+            }
+        };
+
+        (code, index) = match frame.block(jumping) {
+            Some(found) => found,
+            None => return Err(Exit::Error(format!("Jumped to unknown label {}", jumping))),
+        };
+        frame.current = code.label.clone();
+    }
+}
+
+/// Runs an effect that has a value
+fn expect<'a>(runtime: &mut Runtime, frame: &mut Frame<'a>, effect: &'a FinalizedEffects) -> Result<Value, Exit> {
+    return match run_effect(runtime, frame, effect)? {
+        Some(value) => Ok(value),
+        None => Err(Exit::Error(format!("Expected a value from {:?}", effect.types))),
+    };
+}
+
+/// Runs a single effect
+// skipcq: RS-R1000 Match statements have complexity calculated incorrectly
+pub fn run_effect<'a>(runtime: &mut Runtime, frame: &mut Frame<'a>, effect: &'a FinalizedEffects) -> Ran {
+    return Ok(match &effect.types {
+        FinalizedEffectType::NOP => return Err(Exit::Error("Tried to run a NOP!".to_string())),
+        FinalizedEffectType::CreateVariable(name, inner, types) => {
+            let mut value = expect(runtime, frame, inner)?;
+            // Primitives get their own storage, so setting a variable never changes the one it was copied from
+            if types.is_primitive() {
+                value = Value::allocate(value.load());
+            }
+            frame.declare(name.clone(), types.clone(), value.clone());
+            Some(value)
+        }
+        FinalizedEffectType::Jump(_) | FinalizedEffectType::CompareJump(_, _, _) => {
+            return Err(Exit::Error("Tried to jump outside of a code body's lines!".to_string()))
+        }
+        FinalizedEffectType::CodeBody(body) => return run_block(runtime, frame, body),
+        FinalizedEffectType::MethodCall(_, function, arguments, _) => {
+            let mut values = Vec::default();
+            for (i, argument) in arguments.iter().enumerate() {
+                let value = expect(runtime, frame, argument)?;
+                values.push(if i < function.arguments.len() { value } else { promote_vararg(frame, argument, value) });
+            }
+            // Values returned directly (like primitives) are stored, like the LLVM compiler stores them on the stack
+            runtime.call(function, values)?.map(Value::stored)
+        }
+        FinalizedEffectType::Set(setting, value) => {
+            let output = expect(runtime, frame, setting)?;
+            let storing = expect(runtime, frame, value)?.load();
+            *output.pointer().borrow_mut() = storing;
+            Some(output)
+        }
+        FinalizedEffectType::LoadVariable(name) => match frame.find(name) {
+            Some((_, value)) => Some(value.clone()),
+            None => return Err(Exit::Error(format!("Unresolved variable {} from {:?}", name, frame))),
+        },
+        FinalizedEffectType::Load(loading_from, field, _) => {
+            let from = expect(runtime, frame, loading_from)?;
+            let structure = loading_from.types.get_nongeneric_return(frame).unwrap();
+            let loaded = from.pointer().borrow();
+            let fields = match &*loaded {
+                Value::Tuple(values) => return Ok(Some(values[field.parse::<usize>().unwrap()].clone())),
+                Value::Struct(_, fields) | Value::Enum(_, _, fields) => fields,
+                other => return Err(Exit::Error(format!("Tried to load {} from {:?}", field, other))),
+            };
+            match structure.inner_struct().fields.iter().position(|found| &found.field.name == field) {
+                Some(index) => Some(fields[index].clone()),
+                None => return Err(Exit::Error(format!("Tried to load {} from {}", field, structure))),
+            }
+        }
+        FinalizedEffectType::CreateStruct(effect, structure, arguments) => {
+            let fields = &structure.inner_struct().fields;
+            let values = create_fields(runtime, frame, fields.len(), arguments, |index| &fields[index].field.field_type)?;
+            let pointer = expect(runtime, frame, effect.as_ref().unwrap())?;
+            *pointer.pointer().borrow_mut() = Value::Struct(structure.id(), values);
+            Some(pointer)
+        }
+        FinalizedEffectType::CreateTuple(_, values) => {
+            let mut output = Vec::default();
+            for value in values {
+                // Every value is stored as a pointer, primitives are copied so they can't change
+                output.push(expect(runtime, frame, value)?.copy_primitive().stored());
+            }
+            Some(Value::allocate(Value::Tuple(output)))
+        }
+        FinalizedEffectType::CreateVariant(effect, structure, tag, arguments) => {
+            let pointer = expect(runtime, frame, effect)?;
+            let fields = &structure.inner_struct().fields;
+            let values = create_fields(runtime, frame, fields.len(), arguments, |index| &fields[index].field.field_type)?;
+            *pointer.pointer().borrow_mut() = Value::Enum(structure.id(), *tag as u64, values);
+            Some(pointer)
+        }
+        FinalizedEffectType::IsVariant(effect, tag) => {
+            let pointer = expect(runtime, frame, effect)?;
+            let found = match &*pointer.pointer().borrow() {
+                Value::Enum(_, found, _) => *found,
+                other => return Err(Exit::Error(format!("Expected an enum, found {:?}", other))),
+            };
+            Some(Value::bool(found == *tag as u64))
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            run_effect(runtime, frame, value)?;
+            if expect(runtime, frame, check)?.as_bool() {
+                let residual = expect(runtime, frame, residual)?;
+                return Err(Exit::Return(Some(get_returned(residual))));
+            }
+            return run_effect(runtime, frame, unwrapped);
+        }
+        FinalizedEffectType::ShortCircuit(first, second, and) => {
+            // The right side only runs if the left side is true for ands, or false for ors
+            let first = expect(runtime, frame, first)?.as_bool();
+            Some(Value::bool(if first == *and { expect(runtime, frame, second)?.as_bool() } else { first }))
+        }
+        FinalizedEffectType::CreateClosure(arguments, _, captures, body) => {
+            let mut captured = Vec::default();
+            for (name, types) in captures {
+                let mut value = frame.find(name).unwrap().1.clone();
+                // Primitives are copied so the closure keeps the value it was created with
+                if types.is_primitive() {
+                    value = value.copy_primitive();
+                }
+                captured.push((name.clone(), types.clone(), value));
+            }
+            let code = runtime.closure_code(body);
+            Some(Value::allocate(Value::Function(Rc::new(FunctionValue::Closure(arguments.clone(), captured, code)))))
+        }
+        FinalizedEffectType::FunctionReference(target) => {
+            Some(Value::allocate(Value::Function(Rc::new(FunctionValue::Reference(target.clone())))))
+        }
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            let function = match expect(runtime, frame, calling)?.load() {
+                Value::Function(function) => function,
+                other => return Err(Exit::Error(format!("Expected a function value, found {:?}", other))),
+            };
+            let mut values = Vec::default();
+            for argument in arguments {
+                values.push(expect(runtime, frame, argument)?);
+            }
+            runtime.call_value(&function, values)?.map(Value::stored)
+        }
+        FinalizedEffectType::Float(float, types) => Some(Value::float(*float, number_bits(types))),
+        FinalizedEffectType::UInt(int, types) => Some(Value::int(*int, number_bits(types))),
+        FinalizedEffectType::Bool(bool) => Some(Value::bool(*bool)),
+        FinalizedEffectType::String(string) => Some(Value::Str(string.as_bytes().to_vec())),
+        FinalizedEffectType::Char(char) => Some(Value::int(*char as u64, 8)),
+        FinalizedEffectType::HeapStore(inner) | FinalizedEffectType::StackStore(inner) => {
+            Some(expect(runtime, frame, inner)?.stored())
+        }
+        FinalizedEffectType::Free(inner) => {
+            // Values are reference counted, so their memory is freed once the last pointer to them is dropped
+            // and there's nothing to free early.
+            run_effect(runtime, frame, inner)?;
+            None
+        }
+        FinalizedEffectType::ReferenceLoad(inner) => Some(expect(runtime, frame, inner)?.load()),
+        FinalizedEffectType::HeapAllocate(_) => Some(Value::allocate(Value::Uninitialized)),
+        FinalizedEffectType::CreateArray(types, values) => {
+            let primitive = types.as_ref().map_or(false, |types| types.is_primitive());
+            let mut output = Vec::default();
+            for value in values {
+                let value = expect(runtime, frame, value)?;
+                // Primitives are stored in the array directly
+                output.push(if primitive { value.load() } else { value });
+            }
+            Some(Value::allocate(Value::Array(output)))
+        }
+        FinalizedEffectType::VirtualCall(offset, _, arguments, _) => {
+            let (base, functions) = match expect(runtime, frame, &arguments[0])?.load() {
+                Value::Trait(base, functions) => (*base, functions),
+                other => return Err(Exit::Error(format!("Tried to call a virtual method on {:?}", other))),
+            };
+            let mut values = vec![base];
+            for argument in &arguments[1..] {
+                values.push(expect(runtime, frame, argument)?);
+            }
+            runtime.call(&functions[*offset], values)?.map(Value::stored)
+        }
+        FinalizedEffectType::Downcast(base, target, functions) => {
+            let base_types = base.types.get_nongeneric_return(frame).unwrap();
+            if is_modifier(base_types.inner_struct().data.modifiers, Modifier::Trait) {
+                if !target.eq(&base_types) {
+                    return Err(Exit::Error("Downcasting to a trait that doesn't match! Not implemented yet!".to_string()));
+                }
+                return run_effect(runtime, frame, base);
+            }
+            let mut value = expect(runtime, frame, base)?;
+            // Primitives are copied so the trait keeps the value it was created with
+            if base_types.is_primitive() {
+                value = value.copy_primitive();
+            }
+            let table = runtime.vtable(target, &base_types, functions);
+            Some(Value::allocate(Value::Trait(Box::new(value), table)))
+        }
+        FinalizedEffectType::GenericMethodCall(function, types, _) => {
+            return Err(Exit::Error(format!("Tried to run generic method call! {} and {}", function.data.name, types)))
+        }
+        FinalizedEffectType::GenericVirtualCall(_, _, _, _, _) => {
+            return Err(Exit::Error("Generic virtual call not degeneric'd!".to_string()))
+        }
+    });
+}
+
+/// Runs the arguments of a struct or enum, with every field that isn't set left uninitialized
+fn create_fields<'a, 'b>(
+    runtime: &mut Runtime,
+    frame: &mut Frame<'a>,
+    length: usize,
+    arguments: &'a [(usize, FinalizedEffects)],
+    field_type: impl Fn(usize) -> &'b FinalizedTypes,
+) -> Result<Vec<Value>, Exit> {
+    let mut values = vec![Value::Uninitialized; length];
+    for (index, effect) in arguments {
+        let mut value = expect(runtime, frame, effect)?;
+        // Primitives are copied so the field can't be changed through the variable it came from
+        if field_type(*index).is_primitive() {
+            value = value.copy_primitive();
+        }
+        values[*index] = value;
+    }
+    return Ok(values);
+}
+
+/// Gets the value to return from the function, copying primitives so they can't be changed through a variable
+fn get_returned(returned: Value) -> Value {
+    return if returned.is_primitive() { returned.load() } else { returned };
+}
+
+/// Promotes primitive varargs to the types C expects, like the LLVM compiler does
+fn promote_vararg(frame: &Frame, argument: &FinalizedEffects, value: Value) -> Value {
+    let types = match argument.types.get_nongeneric_return(frame) {
+        Some(types) if types.is_primitive() => types,
+        _ => return value,
+    };
+
+    return match value.load() {
+        Value::Float(float, 32) => Value::Float(float, 64),
+        Value::Int(int, bits) if bits < 32 => {
+            if types.name_safe().map_or(false, |name| name.starts_with('i')) {
+                Value::int(sign_extend(int, bits) as u64, 32)
+            } else {
+                Value::Int(int, 32)
+            }
+        }
+        loaded => loaded,
+    };
+}
+
+/// Gets the bit width of a number type
+pub fn number_bits(types: &FinalizedTypes) -> u32 {
+    return match types.name_safe().as_deref() {
+        Some("i64" | "u64" | "f64") => 64,
+        Some("i32" | "u32" | "f32") => 32,
+        Some("i16" | "u16") => 16,
+        Some("i8" | "u8" | "char") => 8,
+        Some("bool") => 1,
+        _ => 64,
+    };
+}
@@ -0,0 +1,112 @@
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::program::types::FinalizedTypes;
+
+use crate::function_interpreter::number_bits;
+use crate::internal::math_internal::math_internal;
+use crate::internal::string_internal::string_internal;
+use crate::value::{sign_extend, Value};
+
+/// Runs a function with the internal keyword, or returns an error if it can't be run, like when dividing by zero
+pub fn call_internal(function: &CodelessFinalizedFunction, arguments: &[Value]) -> Result<Option<Value>, String> {
+    let name = &function.data.name;
+    if let Some(returning) = string_internal(name, arguments) {
+        return Ok(Some(returning));
+    }
+    if let Some(returning) = math_internal(name, arguments)? {
+        return Ok(Some(returning));
+    }
+
+    let returning = if name.starts_with("numbers::Cast") {
+        build_cast(
+            arguments[0].load(),
+            function.return_type.as_ref().unwrap(),
+            is_signed(&function.arguments.first().unwrap().field.field_type),
+            function.return_type.as_ref().map_or(false, is_signed),
+        )
+    } else if name.starts_with("math::RightShift") {
+        let ((value, bits), (shift, _)) = (arguments[0].as_int(), arguments[1].as_int());
+        Value::int(sign_extend(value, bits).wrapping_shr(shift as u32) as u64, bits)
+    } else if name.starts_with("math::LogicRightShift") {
+        let ((value, bits), (shift, _)) = (arguments[0].as_int(), arguments[1].as_int());
+        Value::int(value.wrapping_shr(shift as u32), bits)
+    } else if name.starts_with("math::LeftShift") {
+        let ((value, bits), (shift, _)) = (arguments[0].as_int(), arguments[1].as_int());
+        Value::int(value.wrapping_shl(shift as u32), bits)
+    } else if name.starts_with("array::Index") {
+        let index = arguments[1].as_int().0 as usize;
+        arguments[0].with_array(|values| match values.get(index) {
+            Some(found) => Ok(found.clone()),
+            None => Err(out_of_bounds(index, values)),
+        })?
+    } else if name.starts_with("array::empty") {
+        Value::allocate(Value::Array(Vec::default()))
+    } else if name.starts_with("array::allocate") {
+        Value::allocate(Value::Array(vec![Value::Uninitialized; arguments[0].as_int().0 as usize]))
+    } else if name.starts_with("array::resize") {
+        let capacity = arguments[1].as_int().0 as usize;
+        arguments[0].with_array(|values| values.resize(capacity, Value::Uninitialized));
+        arguments[0].clone()
+    } else if name.starts_with("array::set") {
+        let index = arguments[1].as_int().0 as usize;
+        // Primitives are stored in the array directly
+        let setting = if arguments[2].is_primitive() { arguments[2].load() } else { arguments[2].clone() };
+        arguments[0].with_array(|values| match values.get_mut(index) {
+            Some(found) => {
+                *found = setting;
+                Ok(())
+            }
+            None => Err(out_of_bounds(index, values)),
+        })?;
+        return Ok(None);
+    } else if name.starts_with("array::copy_within") {
+        let (from, to, length) =
+            (arguments[1].as_int().0 as usize, arguments[2].as_int().0 as usize, arguments[3].as_int().0 as usize);
+        // The ranges can overlap, so the values are copied out first
+        arguments[0].with_array(|values| {
+            if from.max(to) + length > values.len() {
+                return Err(out_of_bounds(from.max(to) + length - 1, values));
+            }
+            let copying = values[from..from + length].to_vec();
+            values[to..to + length].clone_from_slice(&copying);
+            Ok(())
+        })?;
+        return Ok(None);
+    } else if name.starts_with("array::Array") && name.contains("::length") {
+        Value::Int(arguments[0].with_array(|values| values.len()) as u64, 64)
+    } else if name.starts_with("mem::undefined") {
+        Value::Uninitialized
+    } else {
+        return Err(format!("Unknown internal operation: {}", name));
+    };
+    return Ok(Some(returning));
+}
+
+/// The error for indexing past the end of an array, which is undefined in compiled code
+fn out_of_bounds(index: usize, values: &[Value]) -> String {
+    return format!("Index {} is out of bounds for an array of length {}", index, values.len());
+}
+
+/// Casts a number from one type to another, using the signs of the types to extend or convert them correctly
+fn build_cast(value: Value, target: &FinalizedTypes, from_signed: bool, to_signed: bool) -> Value {
+    let bits = number_bits(target);
+    let float = matches!(target.name_safe().as_deref(), Some("f64" | "f32"));
+    return match (value, float) {
+        (Value::Int(int, from_bits), false) => {
+            Value::int(if from_signed { sign_extend(int, from_bits) as u64 } else { int }, bits)
+        }
+        (Value::Int(int, from_bits), true) => {
+            Value::float(if from_signed { sign_extend(int, from_bits) as f64 } else { int as f64 }, bits)
+        }
+        (Value::Float(float, _), false) => Value::int(if to_signed { float as i64 as u64 } else { float as u64 }, bits),
+        (Value::Float(float, _), true) => Value::float(float, bits),
+        (value, _) => value,
+    };
+}
+
+/// Checks if the number type is signed
+fn is_signed(types: &FinalizedTypes) -> bool {
+    return match types.name_safe() {
+        Some(name) => matches!(name.as_str(), "i64" | "i32" | "i16" | "i8" | "f64" | "f32"),
+        None => false,
+    };
+}
@@ -0,0 +1,287 @@
+use std::cmp::Ordering;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::io::Write;
+use std::process;
+use std::ptr;
+use std::ptr::addr_of_mut;
+use std::sync::Arc;
+
+use libffi::low::{call, ffi_abi_FFI_DEFAULT_ABI, ffi_cif, ffi_type, prep_cif, prep_cif_var, types, CodePtr};
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::program::types::FinalizedTypes;
+use syntax::Attribute;
+
+use crate::function_interpreter::number_bits;
+use crate::runtime::Runtime;
+use crate::value::{truncate, Value};
+
+/// Runs a method with the attribute #[llvm_intrinsic]. The C functions the Raven libraries use on strings are run
+/// on the interpreter's values, any other function (like malloc, which works on raw C memory) is called through libffi.
+pub fn call_intrinsic(
+    runtime: &mut Runtime,
+    function: &Arc<CodelessFinalizedFunction>,
+    arguments: &[Value],
+) -> Result<Option<Value>, String> {
+    let name = match Attribute::find_attribute("llvm_intrinsic", &function.data.attributes) {
+        Some(Attribute::String(_, name)) => name.as_str(),
+        _ => function.data.name.split("::").last().unwrap(),
+    };
+    let returning = match name {
+        "printf" => Value::int(printf(arguments)? as u64, 32),
+        "strlen" => Value::Int(arguments[0].as_str().len() as u64, 64),
+        "strcmp" => {
+            let compared = match arguments[0].as_str().cmp(&arguments[1].as_str()) {
+                Ordering::Less => -1i64,
+                Ordering::Equal => 0,
+                Ordering::Greater => 1,
+            };
+            Value::Int(compared as u64, 64)
+        }
+        "strcpy" => {
+            *arguments[0].pointer().borrow_mut() = Value::Str(arguments[1].as_str());
+            arguments[0].clone()
+        }
+        "strcat" => {
+            let mut output = arguments[0].as_str();
+            output.extend(arguments[1].as_str());
+            *arguments[0].pointer().borrow_mut() = Value::Str(output);
+            arguments[0].clone()
+        }
+        "llvm.sqrt.f64" => Value::Float(arguments[0].as_float().sqrt(), 64),
+        "abort" => process::abort(),
+        _ => return call_c(runtime, name, None, function, arguments),
+    };
+    return Ok(Some(returning));
+}
+
+/// Runs an extern function, which calls the C function of the same name from the linked library
+pub fn call_extern(
+    runtime: &mut Runtime,
+    function: &Arc<CodelessFinalizedFunction>,
+    arguments: &[Value],
+) -> Result<Option<Value>, String> {
+    let library = match Attribute::find_attribute("link", &function.data.attributes) {
+        Some(Attribute::String(_, library)) => Some(library.as_str()),
+        _ => None,
+    };
+    return call_c(runtime, function.data.name.split("::").last().unwrap(), library, function, arguments);
+}
+
+/// Formats like C's printf, formatting each conversion with snprintf so the output is the same
+fn printf(arguments: &[Value]) -> Result<i32, String> {
+    let format = arguments[0].as_str();
+    let mut values = arguments[1..].iter();
+    let mut output = Vec::default();
+    let mut i = 0;
+    while i < format.len() {
+        if format[i] != b'%' {
+            output.push(format[i]);
+            i += 1;
+            continue;
+        }
+
+        // Skips the flags, width, precision and length to find the conversion
+        let start = i;
+        i += 1;
+        while i < format.len() && !format[i].is_ascii_alphabetic() && format[i] != b'%' {
+            i += 1;
+        }
+        while i < format.len() && b"hlLqjzt".contains(&format[i]) {
+            i += 1;
+        }
+        let conversion = match format.get(i) {
+            Some(conversion) => *conversion,
+            None => break,
+        };
+        i += 1;
+
+        if conversion == b'%' {
+            output.push(b'%');
+            continue;
+        }
+        let spec = &format[start..i];
+        if spec.contains(&b'*') {
+            return Err("The interpreter doesn't support * in printf formats".to_string());
+        }
+        let spec = CString::new(spec).unwrap();
+        let value = values.next().map(Value::load).unwrap_or(Value::Int(0, 64));
+        output.extend(format_one(&spec, conversion, value)?);
+    }
+
+    let _ = std::io::stdout().write_all(&output);
+    return Ok(output.len() as i32);
+}
+
+/// Formats a single printf conversion of the value
+fn format_one(spec: &CStr, conversion: u8, value: Value) -> Result<Vec<u8>, String> {
+    let string = if conversion == b's' { Some(CString::new(value.as_str()).unwrap()) } else { None };
+    // SAFETY: the spec has a single conversion, which is given an argument of the type it reads
+    let format = |output: *mut c_char, size: usize| unsafe {
+        match (&string, &value) {
+            (Some(string), _) => Some(libc::snprintf(output, size, spec.as_ptr(), string.as_ptr())),
+            (None, Value::Float(float, _)) => Some(libc::snprintf(output, size, spec.as_ptr(), *float)),
+            (None, Value::Int(int, _)) => Some(libc::snprintf(output, size, spec.as_ptr(), *int)),
+            (None, Value::Pointer(pointer)) => Some(libc::snprintf(output, size, spec.as_ptr(), pointer.as_ptr())),
+            _ => None,
+        }
+    };
+
+    let length = match format(ptr::null_mut(), 0) {
+        Some(length) => length,
+        None => return Err(format!("Can't format {:?} with {}", value, spec.to_string_lossy())),
+    };
+    let mut buffer = vec![0u8; length.max(0) as usize + 1];
+    format(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    // Removes the null terminator
+    buffer.pop();
+    return Ok(buffer);
+}
+
+/// Calls a C function through libffi, loading the library first if there is one. The call is built from the Raven
+/// declaration like the LLVM compiler declares it: primitives and chars are passed by value, everything else
+/// (like strings) as a pointer to a copy of it laid out like the LLVM compiler's. Copies are read back after the call,
+/// so anything C writes through them is kept. Varargs are passed as the types they were promoted to.
+#[cfg(unix)]
+fn call_c(
+    runtime: &mut Runtime,
+    name: &str,
+    library: Option<&str>,
+    function: &Arc<CodelessFinalizedFunction>,
+    arguments: &[Value],
+) -> Result<Option<Value>, String> {
+    if let Some(library) = library {
+        if runtime.libraries.insert(library.to_string()) {
+            let path = CString::new(format!("{}{}{}", DLL_PREFIX, library, DLL_SUFFIX)).unwrap();
+            // Failing to load is fine, it could already be linked
+            unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+        }
+    }
+    let symbol = CString::new(name).unwrap();
+    let found = unsafe { libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()) };
+    if found.is_null() {
+        return Err(format!("Failed to find C function {}", name));
+    }
+
+    // Each argument is stored in its own slot, which libffi reads it from
+    let mut types = Vec::default();
+    let mut slots = Vec::default();
+    let mut exported = Vec::default();
+    for (i, value) in arguments.iter().enumerate() {
+        // Varargs have no declared type, they were already extended to the size C reads
+        let declared = function.arguments.get(i).map(|argument| &argument.field);
+        let signed =
+            declared.and_then(|declared| declared.field_type.name_safe()).map_or(false, |name| name.starts_with('i'));
+        let (types_found, slot) = match value.load() {
+            Value::Int(value, bits) => (int_type(bits, signed), int_slot(value, bits)),
+            Value::Float(value, 32) => (addr_of_mut!(types::float), slot(&(value as f32).to_ne_bytes())),
+            Value::Float(value, _) => (addr_of_mut!(types::double), value.to_bits()),
+            loaded => {
+                if let Some(declared) = declared.filter(|declared| declared.field_type.is_primitive()) {
+                    return Err(format!("Expected a primitive for {}, found {:?}", declared.name, loaded));
+                }
+                let memory = loaded.export();
+                if let Value::Pointer(pointer) = value {
+                    exported.push((pointer, memory));
+                }
+                (addr_of_mut!(types::pointer), memory as u64)
+            }
+        };
+        types.push(types_found);
+        slots.push(slot);
+    }
+
+    let returning = function.return_type.as_ref();
+    let return_name = returning.and_then(FinalizedTypes::name_safe).unwrap_or_default();
+    let return_type = match (returning, return_name.as_str()) {
+        (None, _) => addr_of_mut!(types::void),
+        (Some(_), "f32") => addr_of_mut!(types::float),
+        (Some(_), "f64") => addr_of_mut!(types::double),
+        (Some(_), "str") => addr_of_mut!(types::pointer),
+        (Some(returning), _) if returning.is_primitive() || return_name == "char" => {
+            int_type(number_bits(returning), return_name.starts_with('i'))
+        }
+        (Some(_), _) => return Err(format!("The interpreter can't read {} returned from C function {}", return_name, name)),
+    };
+
+    let mut cif = ffi_cif::default();
+    let prepared = unsafe {
+        if Attribute::find_attribute("vararg", &function.data.attributes).is_some() {
+            let fixed = function.arguments.len();
+            prep_cif_var(&mut cif, ffi_abi_FFI_DEFAULT_ABI, fixed, types.len(), return_type, types.as_mut_ptr())
+        } else {
+            prep_cif(&mut cif, ffi_abi_FFI_DEFAULT_ABI, types.len(), return_type, types.as_mut_ptr())
+        }
+    };
+    if let Err(error) = prepared {
+        return Err(format!("Failed to prepare the call to C function {}: {:?}", name, error));
+    }
+
+    let mut pointers: Vec<*mut c_void> = slots.iter_mut().map(|slot| slot as *mut u64 as *mut c_void).collect();
+    // Integers smaller than a register are returned extended to one, floats are in the first bytes
+    let returned = unsafe { call::<u64>(&mut cif, CodePtr(found), pointers.as_mut_ptr()) };
+
+    for (pointer, memory) in exported {
+        unsafe { pointer.borrow_mut().import(memory) };
+    }
+
+    let returning = match returning {
+        Some(returning) => returning,
+        None => return Ok(None),
+    };
+    return Ok(Some(match return_name.as_str() {
+        "f32" => Value::Float(f32::from_ne_bytes(returned.to_ne_bytes()[..4].try_into().unwrap()) as f64, 32),
+        "f64" => Value::Float(f64::from_bits(returned), 64),
+        "str" => Value::allocate(Value::Str(unsafe { CStr::from_ptr(returned as *const c_char) }.to_bytes().to_vec())),
+        _ => {
+            let bits = number_bits(returning);
+            Value::Int(truncate(returned, bits), bits)
+        }
+    }));
+}
+
+/// Gets the libffi type of an integer with the given bit width
+#[cfg(unix)]
+fn int_type(bits: u32, signed: bool) -> *mut ffi_type {
+    return match (bits, signed) {
+        (64, true) => addr_of_mut!(types::sint64),
+        (64, false) => addr_of_mut!(types::uint64),
+        (32, true) => addr_of_mut!(types::sint32),
+        (32, false) => addr_of_mut!(types::uint32),
+        (16, true) => addr_of_mut!(types::sint16),
+        (16, false) => addr_of_mut!(types::uint16),
+        (_, true) => addr_of_mut!(types::sint8),
+        (_, false) => addr_of_mut!(types::uint8),
+    };
+}
+
+/// Stores an integer in a slot as the type libffi reads it as
+#[cfg(unix)]
+fn int_slot(value: u64, bits: u32) -> u64 {
+    return match bits {
+        64 => value,
+        32 => slot(&(value as u32).to_ne_bytes()),
+        16 => slot(&(value as u16).to_ne_bytes()),
+        _ => slot(&[value as u8]),
+    };
+}
+
+/// Puts the bytes at the start of a slot, which is where libffi reads a smaller argument from
+#[cfg(unix)]
+fn slot(bytes: &[u8]) -> u64 {
+    let mut slot = [0u8; 8];
+    slot[..bytes.len()].copy_from_slice(bytes);
+    return u64::from_ne_bytes(slot);
+}
+
+/// Calls a C function, which isn't supported on this platform
+#[cfg(not(unix))]
+fn call_c(
+    _runtime: &mut Runtime,
+    name: &str,
+    _library: Option<&str>,
+    _function: &Arc<CodelessFinalizedFunction>,
+    _arguments: &[Value],
+) -> Result<Option<Value>, String> {
+    return Err(format!("The interpreter can't call C function {} on this platform", name));
+}
@@ -0,0 +1,113 @@
+use crate::value::{sign_extend, Value};
+
+/// Runs internal math functions, returning None if the function isn't one or an error if it can't be run
+pub fn math_internal(name: &str, arguments: &[Value]) -> Result<Option<Value>, String> {
+    if !name.starts_with("math::") {
+        return Ok(None);
+    }
+    let params = arguments.iter().map(Value::load).collect::<Vec<_>>();
+    if params.iter().any(|param| matches!(param, Value::Float(_, _))) {
+        return Ok(float_math_internal(name, &params));
+    }
+
+    let (first, bits) = match params.first() {
+        Some(first) => first.as_int(),
+        None => return Ok(None),
+    };
+    if name.starts_with("math::Not") || name.starts_with("math::BitInvert") {
+        return Ok(Some(Value::int(!first, bits)));
+    }
+    let (second, _) = match params.get(1) {
+        Some(second) => second.as_int(),
+        None => return Ok(None),
+    };
+    let unsigned = is_unsigned(name);
+    let returning = if name.starts_with("math::Add") {
+        Value::int(first.wrapping_add(second), bits)
+    } else if name.starts_with("math::Subtract") {
+        Value::int(first.wrapping_sub(second), bits)
+    } else if name.starts_with("math::Multiply") {
+        Value::int(first.wrapping_mul(second), bits)
+    } else if name.starts_with("math::Divide") {
+        check_divisor(name, second)?;
+        if unsigned {
+            Value::int(first / second, bits)
+        } else {
+            Value::int(sign_extend(first, bits).wrapping_div(sign_extend(second, bits)) as u64, bits)
+        }
+    } else if name.starts_with("math::Remainder") {
+        check_divisor(name, second)?;
+        if unsigned {
+            Value::int(first % second, bits)
+        } else {
+            Value::int(sign_extend(first, bits).wrapping_rem(sign_extend(second, bits)) as u64, bits)
+        }
+    } else if name.starts_with("math::Equal") {
+        Value::bool(first == second)
+    } else if name.starts_with("math::GreaterThan") {
+        Value::bool(if unsigned { first > second } else { sign_extend(first, bits) > sign_extend(second, bits) })
+    } else if name.starts_with("math::LessThan") {
+        Value::bool(if unsigned { first < second } else { sign_extend(first, bits) < sign_extend(second, bits) })
+    } else if name.starts_with("math::BitXOR") || name.starts_with("math::XOR") {
+        Value::int(first ^ second, bits)
+    } else if name.starts_with("math::BitOr") || name.starts_with("math::Or") {
+        Value::int(first | second, bits)
+    } else if name.starts_with("math::BitAnd") || name.starts_with("math::And") {
+        Value::int(first & second, bits)
+    } else {
+        return Ok(None);
+    };
+    return Ok(Some(returning));
+}
+
+/// Runs internal math functions on floats, converting any integer parameters to floats first
+fn float_math_internal(name: &str, params: &[Value]) -> Option<Value> {
+    // Both sides are converted to the widest float type
+    let bits = params.iter().filter_map(|param| if let Value::Float(_, bits) = param { Some(*bits) } else { None }).max()?;
+    let convert = |param: &Value| match param {
+        Value::Float(float, _) => *float,
+        Value::Int(int, bits) => {
+            if is_unsigned(name) {
+                *int as f64
+            } else {
+                sign_extend(*int, *bits) as f64
+            }
+        }
+        other => panic!("Expected a number, found {:?}", other),
+    };
+    let (first, second) = (convert(params.first()?), convert(params.get(1)?));
+
+    let returning = if name.starts_with("math::Add") {
+        Value::float(first + second, bits)
+    } else if name.starts_with("math::Subtract") {
+        Value::float(first - second, bits)
+    } else if name.starts_with("math::Multiply") {
+        Value::float(first * second, bits)
+    } else if name.starts_with("math::Divide") {
+        Value::float(first / second, bits)
+    } else if name.starts_with("math::Remainder") {
+        Value::float(first % second, bits)
+    } else if name.starts_with("math::Equal") {
+        Value::bool(first == second)
+    } else if name.starts_with("math::GreaterThan") {
+        Value::bool(first > second)
+    } else if name.starts_with("math::LessThan") {
+        Value::bool(first < second)
+    } else {
+        return None;
+    };
+    return Some(returning);
+}
+
+/// Errors when dividing by zero, which is undefined in compiled code
+fn check_divisor(name: &str, divisor: u64) -> Result<(), String> {
+    if divisor == 0 {
+        return Err(format!("Divided by zero in {}", name));
+    }
+    return Ok(());
+}
+
+/// Returns true if a number is unsigned
+pub fn is_unsigned(name: &str) -> bool {
+    return name.ends_with("u64") || name.ends_with("u32") || name.ends_with("u16") || name.ends_with("u8");
+}
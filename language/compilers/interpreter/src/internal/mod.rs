@@ -0,0 +1,8 @@
+/// Internal instructions
+pub mod instructions;
+/// Calls intrinsic C functions and extern functions
+pub mod intrinsics;
+/// Internal math instructions
+pub mod math_internal;
+/// Internal string instructions
+pub mod string_internal;
@@ -0,0 +1,38 @@
+use crate::value::Value;
+
+/// Runs internal string functions, returning None if the function isn't one
+pub fn string_internal(name: &str, arguments: &[Value]) -> Option<Value> {
+    let returning = if name.starts_with("string::Cast") {
+        // A char is a string of just that char
+        match arguments[0].load() {
+            Value::Int(char, 8) => Value::allocate(Value::Str(vec![char as u8])),
+            _ => arguments[0].clone(),
+        }
+    } else if name.starts_with("string::Add<char + u64>_char::add") {
+        Value::allocate(Value::int(arguments[0].as_int().0.wrapping_add(arguments[1].as_int().0), 8))
+    } else if name.starts_with("string::Add<str + str>_str::add") {
+        let mut output = arguments[0].as_str();
+        output.extend(arguments[1].as_str());
+        Value::allocate(Value::Str(output))
+    } else if name.starts_with("string::Add<str + char>_str::add") {
+        let mut output = arguments[0].as_str();
+        output.push(arguments[1].as_int().0 as u8);
+        Value::allocate(Value::Str(output))
+    } else if name.starts_with("string::Hash_char::hash") {
+        Value::Int(arguments[0].as_int().0, 64)
+    } else if name.starts_with("string::Hash_str::hash") {
+        Value::Int(hash_string(&arguments[0].as_str()), 64)
+    } else {
+        return None;
+    };
+    return Some(returning);
+}
+
+/// Hashes every char of the string with FNV-1a
+fn hash_string(string: &[u8]) -> u64 {
+    let mut hash: u64 = 14695981039346656037;
+    for char in string {
+        hash = (hash ^ *char as u64).wrapping_mul(1099511628211);
+    }
+    return hash;
+}
@@ -0,0 +1,103 @@
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+use dashmap::DashMap;
+use tokio::sync::mpsc::Receiver;
+
+use async_trait::async_trait;
+use data::tokens::Span;
+use data::{CompilerArguments, TargetPointer};
+use syntax::async_util::{EmptyNameResolver, MainFuture};
+use syntax::program::function::FinalizedFunction;
+use syntax::program::syntax::{Compiler, Syntax};
+
+use crate::runtime::Runtime;
+
+/// The state of a function being run
+pub mod frame;
+/// Runs a function's code
+pub mod function_interpreter;
+/// Implementations of internal functions
+pub mod internal;
+/// Runs functions and holds the state shared between them
+pub mod runtime;
+/// The values the interpreter works with
+pub mod value;
+
+/// The stack size of the thread running the code, which needs room for deep recursion
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// An interpreter that runs the checked code directly instead of compiling it, which starts up faster
+/// and gives a reference for what the compiled code should do
+pub struct Interpreter {
+    compiling: Arc<DashMap<String, Arc<FinalizedFunction>>>,
+    arguments: CompilerArguments,
+}
+
+impl Interpreter {
+    /// Creates a new interpreter
+    pub fn new(compiling: Arc<DashMap<String, Arc<FinalizedFunction>>>, arguments: CompilerArguments) -> Self {
+        return Self { compiling, arguments };
+    }
+
+    /// Finds the main function
+    async fn get_main(&self, syntax: &Arc<Mutex<Syntax>>) -> Option<Arc<FinalizedFunction>> {
+        Syntax::get_function(
+            syntax.clone(),
+            Span::default(),
+            self.arguments.target.clone(),
+            Box::new(EmptyNameResolver {}),
+            false,
+        )
+        .await
+        .ok()?;
+
+        return MainFuture { syntax: syntax.clone() }.await;
+    }
+}
+
+#[async_trait]
+impl<T: TargetPointer> Compiler<T> for Interpreter {
+    /// Runs the target once the receiver says the code is verified
+    async fn compile(&self, mut receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Result<Option<T>, String> {
+        if let Some(main) = self.get_main(syntax).await {
+            if receiver.recv().await.is_some() {
+                if self.arguments.output.is_some() {
+                    return Err("The interpreter can't build executables, use the LLVM compiler instead".to_string());
+                }
+
+                // Values aren't thread safe, so they never leave the thread running the code
+                let functions = self.compiling.clone();
+                let running = thread::Builder::new()
+                    .name("interpreter".to_string())
+                    .stack_size(STACK_SIZE)
+                    .spawn(move || Runtime::new(functions).run(&main).map(|returned| returned as usize))
+                    .unwrap();
+                let returned = match running.join() {
+                    Ok(returned) => returned?,
+                    Err(error) => return Err(panic_message(error)),
+                };
+
+                // Like the LLVM compiler, the target returns a pointer to the returned value
+                return Ok(Some(T::from_pointer(returned as *mut u8)));
+            }
+        } else {
+            receiver.recv().await;
+        }
+
+        return Ok(None);
+    }
+}
+
+/// Gets the message of a panic in the interpreter, so bugs in it are returned as errors too
+fn panic_message(error: Box<dyn Any + Send>) -> String {
+    return match error.downcast::<String>() {
+        Ok(message) => *message,
+        Err(error) => match error.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "The interpreter panicked".to_string(),
+        },
+    };
+}
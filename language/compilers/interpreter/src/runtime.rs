@@ -0,0 +1,128 @@
+use std::collections::{HashMap, HashSet};
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction};
+use syntax::program::types::FinalizedTypes;
+use syntax::{is_modifier, Attribute, Modifier};
+
+use crate::frame::Frame;
+use crate::function_interpreter::{run_block, Exit};
+use crate::internal::instructions::call_internal;
+use crate::internal::intrinsics::{call_extern, call_intrinsic};
+use crate::value::{FunctionValue, Value};
+
+/// Runs functions and keeps the state shared between them
+pub struct Runtime {
+    /// Every checked function, by name
+    functions: Arc<DashMap<String, Arc<FinalizedFunction>>>,
+    /// The code of each closure, by its address, so closure values don't each copy their code
+    closures: HashMap<usize, Rc<FinalizedCodeBody>>,
+    /// The virtual tables of each type's trait implementations, by the type and trait names.
+    /// Like the LLVM compiler, the first downcast to a trait decides the table for that type.
+    vtables: HashMap<(String, String), Rc<Vec<Arc<CodelessFinalizedFunction>>>>,
+    /// The libraries loaded for extern functions
+    pub libraries: HashSet<String>,
+}
+
+impl Runtime {
+    /// Creates a runtime that can call any of the functions
+    pub fn new(functions: Arc<DashMap<String, Arc<FinalizedFunction>>>) -> Self {
+        return Self { functions, closures: HashMap::default(), vtables: HashMap::default(), libraries: HashSet::default() };
+    }
+
+    /// Runs the target function, returning a pointer to the returned value laid out like the LLVM compiler's
+    /// or an error if the code can't be run
+    pub fn run(&mut self, target: &Arc<FinalizedFunction>) -> Result<*mut u8, String> {
+        return Ok(match self.run_function(target, Vec::default())? {
+            Some(returned) => returned.load().export(),
+            None => ptr::null_mut(),
+        });
+    }
+
+    /// Calls the function with the arguments
+    pub fn call(
+        &mut self,
+        function: &Arc<CodelessFinalizedFunction>,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, String> {
+        if Attribute::find_attribute("llvm_intrinsic", &function.data.attributes).is_some() {
+            return call_intrinsic(self, function, &arguments);
+        } else if is_modifier(function.data.modifiers, Modifier::Internal) {
+            return call_internal(function, &arguments);
+        } else if is_modifier(function.data.modifiers, Modifier::Extern) {
+            return call_extern(self, function, &arguments);
+        }
+
+        let found = match self.functions.get(&function.data.name) {
+            Some(found) => found.clone(),
+            None => return Err(format!("Tried to call {} before it was checked", function.data.name)),
+        };
+        return self.run_function(&found, arguments);
+    }
+
+    /// Runs the function's code with the arguments
+    fn run_function(&mut self, function: &Arc<FinalizedFunction>, arguments: Vec<Value>) -> Result<Option<Value>, String> {
+        let mut variables = HashMap::default();
+        for (field, argument) in function.fields.iter().zip(arguments) {
+            let types = &field.field.field_type;
+            // Primitives not taken by reference are passed by value
+            let argument = if matches!(types, FinalizedTypes::Reference(_)) { argument } else { argument.copy_primitive() };
+            variables.insert(field.field.name.clone(), (types.clone(), argument));
+        }
+
+        let mut frame = Frame::new(&function.code, variables);
+        return finish(run_block(self, &mut frame, &function.code));
+    }
+
+    /// Calls a closure or function reference with the arguments
+    pub fn call_value(&mut self, function: &FunctionValue, arguments: Vec<Value>) -> Result<Option<Value>, String> {
+        let (fields, captures, code) = match function {
+            FunctionValue::Closure(fields, captures, code) => (fields, captures, code.clone()),
+            FunctionValue::Reference(target) => return self.call(target, arguments),
+        };
+
+        let mut variables = HashMap::default();
+        for (name, types, value) in captures {
+            variables.insert(name.clone(), (types.clone(), value.clone()));
+        }
+        for (field, argument) in fields.iter().zip(arguments) {
+            variables.insert(field.name.clone(), (FinalizedTypes::Reference(Box::new(field.field_type.clone())), argument));
+        }
+
+        let mut frame = Frame::new(&code, variables);
+        return finish(run_block(self, &mut frame, &code));
+    }
+
+    /// Gets the shared copy of a closure's code
+    pub fn closure_code(&mut self, code: &FinalizedCodeBody) -> Rc<FinalizedCodeBody> {
+        return self
+            .closures
+            .entry(code as *const FinalizedCodeBody as usize)
+            .or_insert_with(|| Rc::new(code.clone()))
+            .clone();
+    }
+
+    /// Gets the virtual table of the type's implementation of the trait
+    pub fn vtable(
+        &mut self,
+        target: &FinalizedTypes,
+        structure: &FinalizedTypes,
+        functions: &[Arc<CodelessFinalizedFunction>],
+    ) -> Rc<Vec<Arc<CodelessFinalizedFunction>>> {
+        let key = (structure.inner_struct().data.name.clone(), target.inner_struct().data.name.clone());
+        return self.vtables.entry(key).or_insert_with(|| Rc::new(functions.to_vec())).clone();
+    }
+}
+
+/// Gets the value a function's code returned, or the error that stopped it
+fn finish(ran: Result<Option<Value>, Exit>) -> Result<Option<Value>, String> {
+    return match ran {
+        Ok(_) => Ok(None),
+        Err(Exit::Return(returned)) => Ok(returned),
+        Err(Exit::Error(error)) => Err(error),
+    };
+}
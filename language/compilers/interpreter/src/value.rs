@@ -0,0 +1,294 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use syntax::program::code::FinalizedField;
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody};
+use syntax::program::types::FinalizedTypes;
+
+/// A pointer to a value, like a heap allocation or a variable on the stack
+pub type Pointer = Rc<RefCell<Value>>;
+
+/// A value in the interpreter, laid out like the LLVM compiler's values so both run code the same way:
+/// primitives and chars can be used directly, everything else is behind a pointer.
+#[derive(Clone, Debug)]
+pub enum Value {
+    /// Memory that was allocated but never set
+    Uninitialized,
+    /// An integer with the given bit width, booleans are 1 bit and chars are 8 bits
+    Int(u64, u32),
+    /// A float with the given bit width
+    Float(f64, u32),
+    /// The characters of a string, without the null terminator
+    Str(Vec<u8>),
+    /// A pointer to another value
+    Pointer(Pointer),
+    /// A struct's type id and its fields
+    Struct(u64, Vec<Value>),
+    /// An enum's type id, its variant's tag and its fields, fields of other variants are uninitialized
+    Enum(u64, u64, Vec<Value>),
+    /// The values of an array, primitives are stored directly
+    Array(Vec<Value>),
+    /// A pointer to each value of a tuple
+    Tuple(Vec<Value>),
+    /// A value downcasted to a trait, with the trait's functions in virtual call order
+    Trait(Box<Value>, Rc<Vec<Arc<CodelessFinalizedFunction>>>),
+    /// A closure or function reference
+    Function(Rc<FunctionValue>),
+}
+
+/// A function that can be called as a value
+#[derive(Debug)]
+pub enum FunctionValue {
+    /// A closure's arguments, its captured variables with their types and values, and its code
+    Closure(Vec<FinalizedField>, Vec<(String, FinalizedTypes, Value)>, Rc<FinalizedCodeBody>),
+    /// A reference to a function
+    Reference(Arc<CodelessFinalizedFunction>),
+}
+
+impl Value {
+    /// Allocates the value and returns a pointer to it
+    pub fn allocate(value: Value) -> Value {
+        return Value::Pointer(Rc::new(RefCell::new(value)));
+    }
+
+    /// Creates an integer of the given bit width, cutting off any bits that don't fit
+    pub fn int(value: u64, bits: u32) -> Value {
+        return Value::Int(truncate(value, bits), bits);
+    }
+
+    /// Creates a float of the given bit width, rounding 32 bit floats
+    pub fn float(value: f64, bits: u32) -> Value {
+        return Value::Float(if bits == 32 { value as f32 as f64 } else { value }, bits);
+    }
+
+    /// Creates a boolean
+    pub fn bool(value: bool) -> Value {
+        return Value::Int(value as u64, 1);
+    }
+
+    /// Loads the value if it's a pointer
+    pub fn load(&self) -> Value {
+        return match self {
+            Value::Pointer(pointer) => pointer.borrow().clone(),
+            _ => self.clone(),
+        };
+    }
+
+    /// Gets the pointer, panicking if the value isn't one
+    pub fn pointer(&self) -> &Pointer {
+        return match self {
+            Value::Pointer(pointer) => pointer,
+            _ => panic!("Expected a pointer, found {:?}", self),
+        };
+    }
+
+    /// Gets the integer and its bit width, loading it if it's behind a pointer
+    pub fn as_int(&self) -> (u64, u32) {
+        return match self.load() {
+            Value::Int(value, bits) => (value, bits),
+            other => panic!("Expected an integer, found {:?}", other),
+        };
+    }
+
+    /// Gets the boolean, loading it if it's behind a pointer
+    pub fn as_bool(&self) -> bool {
+        return self.as_int().0 != 0;
+    }
+
+    /// Gets the float, loading it if it's behind a pointer
+    pub fn as_float(&self) -> f64 {
+        return match self.load() {
+            Value::Float(value, _) => value,
+            other => panic!("Expected a float, found {:?}", other),
+        };
+    }
+
+    /// Gets the string's characters up to the first null, like C would read them
+    pub fn as_str(&self) -> Vec<u8> {
+        let mut characters = match self.load() {
+            Value::Str(characters) => characters,
+            Value::Int(character, 8) => vec![character as u8],
+            other => panic!("Expected a string, found {:?}", other),
+        };
+        if let Some(end) = characters.iter().position(|character| *character == 0) {
+            characters.truncate(end);
+        }
+        return characters;
+    }
+
+    /// Checks if the value is a primitive or a pointer to one
+    pub fn is_primitive(&self) -> bool {
+        return match self {
+            Value::Pointer(pointer) => matches!(*pointer.borrow(), Value::Int(_, _) | Value::Float(_, _)),
+            Value::Int(_, _) | Value::Float(_, _) => true,
+            _ => false,
+        };
+    }
+
+    /// Copies primitives behind a pointer to a new pointer, so changing one never changes the other
+    pub fn copy_primitive(self) -> Value {
+        return if matches!(self, Value::Pointer(_)) && self.is_primitive() { Value::allocate(self.load()) } else { self };
+    }
+
+    /// Stores values that aren't behind a pointer, like returned primitives, in a new pointer
+    pub fn stored(self) -> Value {
+        return match self {
+            Value::Pointer(_) => self,
+            _ => Value::allocate(self),
+        };
+    }
+
+    /// Runs the function on the array this points to
+    pub fn with_array<R>(&self, function: impl FnOnce(&mut Vec<Value>) -> R) -> R {
+        return match &mut *self.pointer().borrow_mut() {
+            Value::Array(values) => function(values),
+            other => panic!("Expected an array, found {:?}", other),
+        };
+    }
+
+    /// Copies the value into memory laid out like C, which is how LLVM lays out structures, and returns a pointer to it.
+    /// The memory is leaked, since Rust and C can keep pointers to it.
+    pub fn export(&self) -> *mut u8 {
+        let mut bytes = Vec::default();
+        self.write(&mut bytes);
+        // Allocated as u64s so the values in it are aligned
+        let memory = vec![0u64; bytes.len().div_ceil(8).max(1)].leak();
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), memory.as_mut_ptr() as *mut u8, bytes.len());
+        }
+        return memory.as_mut_ptr() as *mut u8;
+    }
+
+    /// Writes the value's bytes, with every pointer pointing to an exported copy of the value behind it
+    fn write(&self, bytes: &mut Vec<u8>) {
+        // Values start at a multiple of their alignment, and structures are padded to a multiple of theirs
+        let alignment = self.alignment();
+        pad(bytes, alignment);
+        match self {
+            Value::Uninitialized | Value::Function(_) => bytes.extend(0u64.to_le_bytes()),
+            Value::Int(value, bits) => bytes.extend(&value.to_le_bytes()[..(*bits as usize).div_ceil(8)]),
+            Value::Float(value, 32) => bytes.extend((*value as f32).to_le_bytes()),
+            Value::Float(value, _) => bytes.extend(value.to_le_bytes()),
+            Value::Str(characters) => {
+                bytes.extend(characters);
+                bytes.push(0);
+            }
+            Value::Pointer(pointer) => bytes.extend((pointer.borrow().export() as u64).to_le_bytes()),
+            Value::Struct(id, fields) => {
+                bytes.extend(id.to_le_bytes());
+                fields.iter().for_each(|field| field.write(bytes));
+            }
+            Value::Enum(id, tag, fields) => {
+                bytes.extend(id.to_le_bytes());
+                bytes.extend(tag.to_le_bytes());
+                fields.iter().for_each(|field| field.write(bytes));
+            }
+            Value::Array(values) => {
+                bytes.extend((values.len() as u64).to_le_bytes());
+                values.iter().for_each(|value| value.write(bytes));
+            }
+            Value::Tuple(values) => values.iter().for_each(|value| value.write(bytes)),
+            Value::Trait(base, _) => {
+                base.write(bytes);
+                // The vtable can't be called from Rust
+                bytes.extend(0u64.to_le_bytes());
+            }
+        }
+        pad(bytes, alignment);
+    }
+
+    /// Reads the value back from memory it was exported to, keeping anything C changed in it.
+    ///
+    /// # Safety
+    ///
+    /// The memory must have been exported from a value with the same layout as this one.
+    pub unsafe fn import(&mut self, memory: *const u8) {
+        self.read(memory, &mut 0);
+    }
+
+    /// Reads the value's bytes at the offset, in the same order they're written
+    unsafe fn read(&mut self, memory: *const u8, offset: &mut usize) {
+        let alignment = self.alignment();
+        *offset = offset.next_multiple_of(alignment);
+        let read_u64 = |offset: &mut usize| {
+            let value = u64::from_le(ptr::read_unaligned(memory.add(*offset) as *const u64));
+            *offset += 8;
+            value
+        };
+        match self {
+            Value::Uninitialized | Value::Function(_) => *offset += 8,
+            Value::Int(value, bits) => {
+                let mut bytes = [0u8; 8];
+                let size = (*bits as usize).div_ceil(8);
+                ptr::copy_nonoverlapping(memory.add(*offset), bytes.as_mut_ptr(), size);
+                *value = truncate(u64::from_le_bytes(bytes), *bits);
+                *offset += size;
+            }
+            Value::Float(value, 32) => {
+                *value = f32::from_bits(u32::from_le(ptr::read_unaligned(memory.add(*offset) as *const u32))) as f64;
+                *offset += 4;
+            }
+            Value::Float(value, _) => *value = f64::from_bits(read_u64(offset)),
+            Value::Str(characters) => {
+                let length = characters.len() + 1;
+                *characters = CStr::from_ptr(memory.add(*offset) as *const c_char).to_bytes().to_vec();
+                *offset += length;
+            }
+            Value::Pointer(pointer) => {
+                let address = read_u64(offset);
+                pointer.borrow_mut().import(address as *const u8);
+            }
+            Value::Struct(_, fields) => {
+                *offset += 8;
+                fields.iter_mut().for_each(|field| field.read(memory, offset));
+            }
+            Value::Enum(_, tag, fields) => {
+                *offset += 8;
+                *tag = read_u64(offset);
+                fields.iter_mut().for_each(|field| field.read(memory, offset));
+            }
+            Value::Array(values) => {
+                *offset += 8;
+                values.iter_mut().for_each(|value| value.read(memory, offset));
+            }
+            Value::Tuple(values) => values.iter_mut().for_each(|value| value.read(memory, offset)),
+            Value::Trait(base, _) => {
+                base.read(memory, offset);
+                *offset += 8;
+            }
+        }
+        *offset = offset.next_multiple_of(alignment);
+    }
+
+    /// Gets the alignment C would give the value, which is the size of primitives and the biggest alignment in structures
+    fn alignment(&self) -> usize {
+        return match self {
+            Value::Int(_, bits) => (*bits as usize).div_ceil(8),
+            Value::Float(_, 32) => 4,
+            Value::Str(_) => 1,
+            Value::Struct(_, fields) | Value::Enum(_, _, fields) => {
+                fields.iter().fold(8, |alignment, field| alignment.max(field.alignment()))
+            }
+            Value::Tuple(values) => values.iter().fold(1, |alignment, value| alignment.max(value.alignment())),
+            _ => 8,
+        };
+    }
+}
+
+/// Pads the bytes with zeros until their length is a multiple of the alignment
+fn pad(bytes: &mut Vec<u8>, alignment: usize) {
+    bytes.resize(bytes.len().next_multiple_of(alignment), 0);
+}
+
+/// Cuts off the bits of the value that don't fit in the bit width
+pub fn truncate(value: u64, bits: u32) -> u64 {
+    return if bits >= 64 { value } else { value & ((1 << bits) - 1) };
+}
+
+/// Sign extends a value of the given bit width
+pub fn sign_extend(value: u64, bits: u32) -> i64 {
+    return if bits >= 64 { value as i64 } else { ((value << (64 - bits)) as i64) >> (64 - bits) };
+}
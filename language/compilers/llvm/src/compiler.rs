@@ -1,25 +1,27 @@
+use std::cell::RefCell;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use dashmap::DashMap;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
 use inkwell::execution_engine::ExecutionEngine;
 use inkwell::module::Module;
+use inkwell::passes::{PassManager, PassManagerBuilder};
 use inkwell::OptimizationLevel;
-use tokio::time;
 
 use data::tokens::Span;
-use data::CompilerArguments;
-use syntax::async_util::EmptyNameResolver;
+use data::{CompilerArguments, Optimization};
+use syntax::async_util::{EmptyNameResolver, MainFuture};
 use syntax::program::function::{CodelessFinalizedFunction, FinalizedFunction};
 use syntax::program::r#struct::FinalizedStruct;
 use syntax::program::syntax::Syntax;
 
-use crate::function_compiler::{compile_block, instance_function};
-use crate::main_future::MainFuture;
+use crate::debug::DebugInfo;
+use crate::function_compiler::{compile_block, declare_arguments};
 use crate::type_getter::CompilerTypeGetter;
+use crate::util::create_function_value;
 
 /// A compiler implementation which must wrap the context
 pub struct CompilerImpl<'ctx> {
@@ -31,14 +33,31 @@ pub struct CompilerImpl<'ctx> {
     pub builder: Builder<'ctx>,
     /// LLVM execution engine
     pub execution_engine: ExecutionEngine<'ctx>,
+    /// Libraries the extern functions are linked from, set with #[link(name)]
+    pub libraries: RefCell<Vec<String>>,
+    /// The module's debug info, if it's being emitted
+    pub debug: Option<DebugInfo<'ctx>>,
 }
 
 impl<'ctx> CompilerImpl<'ctx> {
-    /// Creates a new CompilerImpl from the context
-    pub fn new(context: &'ctx Context) -> Self {
+    /// Creates a new CompilerImpl from the context, with the JIT optimizing at the given level
+    /// and debug info for the syntax's source files if it's enabled
+    pub fn new(context: &'ctx Context, arguments: &CompilerArguments, syntax: &Syntax) -> Self {
         let module = context.create_module("main");
-        let execution_engine = module.create_jit_execution_engine(OptimizationLevel::None).unwrap();
-        return Self { module, context, builder: context.create_builder(), execution_engine };
+        let execution_engine = module.create_jit_execution_engine(optimization_level(arguments.optimization)).unwrap();
+        let debug = if arguments.debug_info {
+            Some(DebugInfo::new(context, &module, syntax, arguments.optimization != Optimization::None))
+        } else {
+            None
+        };
+        return Self {
+            module,
+            context,
+            builder: context.create_builder(),
+            execution_engine,
+            libraries: RefCell::new(Vec::default()),
+            debug,
+        };
     }
 
     /// Finds the main function
@@ -59,13 +78,7 @@ impl<'ctx> CompilerImpl<'ctx> {
             Err(_) => return None,
         };
 
-        let function = match time::timeout(Duration::from_secs(5), MainFuture { syntax: syntax.clone() }).await {
-            Ok(found) => found,
-            Err(_) => panic!(
-                "Something went wrong with finding main! {:?}",
-                syntax.lock().unwrap().compiling.iter().map(|pair| pair.data.name.clone()).collect::<Vec<_>>()
-            ),
-        };
+        let function = MainFuture { syntax: syntax.clone() }.await?;
 
         return Some(Arc::new(function.to_codeless()));
     }
@@ -77,7 +90,10 @@ impl<'ctx> CompilerImpl<'ctx> {
         functions: &Arc<DashMap<String, Arc<FinalizedFunction>>>,
         _structures: &Arc<DashMap<String, Arc<FinalizedStruct>>>,
     ) {
-        instance_function(main, type_getter);
+        // The target is called from Rust, which reads the returned value through a pointer
+        let target = create_function_value(&main, type_getter, None, true);
+        type_getter.target = Some(target);
+        type_getter.compiling.borrow_mut().push((target, main));
 
         let start = Instant::now();
         while !type_getter.compiling.borrow().is_empty() {
@@ -109,15 +125,22 @@ impl<'ctx> CompilerImpl<'ctx> {
                 continue;
             }
 
-            compile_block(
-                &finalized_function.code,
-                function_type,
-                &mut type_getter.for_function(&finalized_function, function_type),
-                &mut 0,
-            );
+            let mut function_getter = type_getter.for_function(&finalized_function, function_type);
+            let mut id = 0;
+            if let Some(debug) = &type_getter.compiler.debug {
+                let data = &finalized_function.data;
+                function_getter.scope = Some(debug.create_function(function_type, &data.name, &data.span));
+                function_getter.set_location(&data.span);
+                declare_arguments(&finalized_function, function_type, &mut function_getter, &mut id);
+            }
+            compile_block(&finalized_function.code, function_type, &mut function_getter, &mut id);
         }
 
-        //let pass_manager = PassManager::create(&self.compiler.module);
+        // Code generated after this, like the entry point, isn't in any Raven function
+        type_getter.compiler.builder.unset_current_debug_location();
+        if let Some(debug) = &type_getter.compiler.debug {
+            debug.finalize();
+        }
 
         /*unsafe {
             LLVMWriteBitcodeToFile(type_getter.compiler.module.as_mut_ptr(),
@@ -127,4 +150,39 @@ impl<'ctx> CompilerImpl<'ctx> {
 
         //print_formatted(type_getter.compiler.module.to_string());
     }
+
+    /// Runs LLVM's optimization passes over the module, matching the pipeline clang uses for the level
+    pub fn optimize(&self, optimization: Optimization) {
+        if optimization == Optimization::None {
+            return;
+        }
+
+        let builder = PassManagerBuilder::create();
+        builder.set_optimization_level(optimization_level(optimization));
+        // Same inlining thresholds as clang
+        match optimization {
+            Optimization::Size => {
+                builder.set_size_level(1);
+                builder.set_inliner_with_threshold(75);
+            }
+            Optimization::Aggressive => builder.set_inliner_with_threshold(275),
+            _ => builder.set_inliner_with_threshold(225),
+        }
+
+        let pass_manager = PassManager::create(());
+        // Every variable is given stack storage, so promoting them to registers comes first
+        pass_manager.add_promote_memory_to_register_pass();
+        builder.populate_module_pass_manager(&pass_manager);
+        pass_manager.run_on(&self.module);
+    }
+}
+
+/// Gets LLVM's optimization level for the optimization
+pub fn optimization_level(optimization: Optimization) -> OptimizationLevel {
+    return match optimization {
+        Optimization::None => OptimizationLevel::None,
+        Optimization::Less => OptimizationLevel::Less,
+        Optimization::Default | Optimization::Size => OptimizationLevel::Default,
+        Optimization::Aggressive => OptimizationLevel::Aggressive,
+    };
 }
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use inkwell::basic_block::BasicBlock;
+use inkwell::context::Context;
+use inkwell::debug_info::{
+    debug_metadata_version, AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants, DILocation, DISubprogram, DIType,
+    DWARFEmissionKind, DWARFSourceLanguage, DebugInfoBuilder,
+};
+use inkwell::module::{FlagBehavior, Module};
+use inkwell::values::{FunctionValue, PointerValue};
+use inkwell::AddressSpace;
+
+use data::tokens::Span;
+use syntax::program::syntax::Syntax;
+
+/// DWARF encoding of booleans
+const DW_ATE_BOOLEAN: u32 = 0x02;
+/// DWARF encoding of floats
+const DW_ATE_FLOAT: u32 = 0x04;
+/// DWARF encoding of signed integers
+const DW_ATE_SIGNED: u32 = 0x05;
+/// DWARF encoding of unsigned integers
+const DW_ATE_UNSIGNED: u32 = 0x08;
+
+/// The DWARF debug info of a module, which maps the compiled code back to the Raven source
+pub struct DebugInfo<'ctx> {
+    /// LLVM context
+    context: &'ctx Context,
+    /// Builds the debug info metadata
+    builder: DebugInfoBuilder<'ctx>,
+    /// The module's compile unit
+    unit: DICompileUnit<'ctx>,
+    /// Every source file and the line and column of each of its tokens, by the hash of the file
+    files: HashMap<u64, (DIFile<'ctx>, Vec<(u32, u32)>)>,
+    /// If the module is optimized
+    optimized: bool,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    /// Creates the module's compile unit with a file for every source file
+    pub fn new(context: &'ctx Context, module: &Module<'ctx>, syntax: &Syntax, optimized: bool) -> Self {
+        let version = context.i32_type();
+        module.add_basic_value_flag(
+            "Debug Info Version",
+            FlagBehavior::Warning,
+            version.const_int(debug_metadata_version() as u64, false),
+        );
+        module.add_basic_value_flag("Dwarf Version", FlagBehavior::Warning, version.const_int(4, false));
+
+        // There's no DWARF language for Raven, C is the closest debuggers understand
+        let (builder, unit) = module.create_debug_info_builder(
+            true,
+            DWARFSourceLanguage::C,
+            module.get_name().to_str().unwrap(),
+            ".",
+            "Raven",
+            optimized,
+            "",
+            0,
+            "",
+            DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        let mut files = HashMap::default();
+        for (hash, file) in &syntax.files {
+            let path = Path::new(&file.path);
+            let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let directory = path.parent().map(|parent| parent.to_string_lossy().to_string()).unwrap_or_default();
+            files.insert(*hash, (builder.create_file(&name, &directory), file.positions.clone()));
+        }
+        return Self { context, builder, unit, files, optimized };
+    }
+
+    /// Gets the file, line and column the span starts at.
+    /// Spans without a source file are put at line 0, which debuggers treat as generated code.
+    fn position(&self, span: &Span) -> (DIFile<'ctx>, u32, u32) {
+        return match self.files.get(&span.file) {
+            Some((file, positions)) => match positions.get(span.start) {
+                // Token columns start at 0, DWARF's start at 1
+                Some((line, column)) => (*file, *line, *column + 1),
+                None => (*file, 0, 0),
+            },
+            None => (self.unit.get_file(), 0, 0),
+        };
+    }
+
+    /// Creates the subprogram of a function and attaches it
+    pub fn create_function(&self, function: FunctionValue<'ctx>, name: &str, span: &Span) -> DISubprogram<'ctx> {
+        let (file, line, _) = self.position(span);
+        let types = self.builder.create_subroutine_type(file, None, &[], DIFlags::ZERO);
+        let subprogram = self.builder.create_function(
+            self.unit.as_debug_info_scope(),
+            name,
+            function.get_name().to_str().ok(),
+            file,
+            line,
+            types,
+            false,
+            true,
+            line,
+            DIFlags::ZERO,
+            self.optimized,
+        );
+        function.set_subprogram(subprogram);
+        return subprogram;
+    }
+
+    /// Gets the location of the span in the function
+    pub fn location(&self, span: &Span, scope: DISubprogram<'ctx>) -> DILocation<'ctx> {
+        let (_, line, column) = self.position(span);
+        return self.builder.create_debug_location(self.context, line, column, scope.as_debug_info_scope(), None);
+    }
+
+    /// Gets the type of a primitive, or None if the type isn't a primitive
+    pub fn primitive_type(&self, name: &str) -> Option<DIType<'ctx>> {
+        let (bits, encoding) = match name {
+            "i64" => (64, DW_ATE_SIGNED),
+            "i32" => (32, DW_ATE_SIGNED),
+            "i16" => (16, DW_ATE_SIGNED),
+            "i8" => (8, DW_ATE_SIGNED),
+            "u64" => (64, DW_ATE_UNSIGNED),
+            "u32" => (32, DW_ATE_UNSIGNED),
+            "u16" => (16, DW_ATE_UNSIGNED),
+            "u8" => (8, DW_ATE_UNSIGNED),
+            "f64" => (64, DW_ATE_FLOAT),
+            "f32" => (32, DW_ATE_FLOAT),
+            "bool" => (8, DW_ATE_BOOLEAN),
+            _ => return None,
+        };
+        return self.builder.create_basic_type(name, bits, encoding, DIFlags::ZERO).ok().map(|found| found.as_type());
+    }
+
+    /// Gets the type of a pointer to the named type, which debuggers only see the name of
+    pub fn pointer_type(&self, name: &str) -> DIType<'ctx> {
+        let pointee = self.builder.create_struct_type(
+            self.unit.as_debug_info_scope(),
+            name,
+            self.unit.get_file(),
+            0,
+            0,
+            0,
+            DIFlags::FWD_DECL,
+            None,
+            &[],
+            0,
+            None,
+            name,
+        );
+        return self.builder.create_pointer_type("", pointee.as_type(), 64, 0, AddressSpace::default()).as_type();
+    }
+
+    /// Declares a variable stored at the pointer, with the argument's number if it's an argument
+    pub fn declare_variable(
+        &self,
+        name: &str,
+        types: DIType<'ctx>,
+        storage: PointerValue<'ctx>,
+        argument: Option<u32>,
+        span: &Span,
+        scope: DISubprogram<'ctx>,
+        block: BasicBlock<'ctx>,
+    ) {
+        let (file, line, _) = self.position(span);
+        let scope_value = scope.as_debug_info_scope();
+        let variable = match argument {
+            Some(argument) => {
+                self.builder.create_parameter_variable(scope_value, name, argument, file, line, types, true, DIFlags::ZERO)
+            }
+            None => self.builder.create_auto_variable(scope_value, name, file, line, types, true, DIFlags::ZERO, 0),
+        };
+        self.builder.insert_declare_at_end(storage, Some(variable), None, self.location(span, scope), block);
+    }
+
+    /// Resolves the debug info, which has to be done before the module is verified or compiled
+    pub fn finalize(&self) {
+        self.builder.finalize();
+    }
+}
@@ -1,22 +1,25 @@
 use inkwell::basic_block::BasicBlock;
-use inkwell::module::Linkage;
-use inkwell::AddressSpace;
+use inkwell::{AddressSpace, IntPredicate};
+use std::collections::HashMap;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
 use std::sync::Arc;
 
 use inkwell::types::{BasicType, BasicTypeEnum};
-use inkwell::values::{BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallableValue, FunctionValue};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallableValue, FunctionValue, IntValue, PointerValue,
+};
 
-use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects};
-use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody};
+use data::tokens::Span;
+use syntax::program::code::{ExpressionType, FinalizedEffectType, FinalizedEffects, FinalizedField};
+use syntax::program::function::{CodelessFinalizedFunction, FinalizedCodeBody, FinalizedFunction};
 use syntax::program::types::FinalizedTypes;
 use syntax::{is_modifier, Attribute, Modifier};
 
 use crate::internal::instructions::{compile_internal, malloc_type};
-use crate::internal::intrinsics::compile_llvm_intrinsics;
+use crate::internal::intrinsics::{compile_extern, compile_llvm_intrinsics, get_intrinsic};
 use crate::type_getter::CompilerTypeGetter;
-use crate::util::create_function_value;
+use crate::util::{closure_function_type, closure_type, create_function_value};
 
 /// Instances a FunctionValue from its CodelessFinalizedFunction
 pub fn instance_function<'a, 'ctx>(
@@ -24,21 +27,21 @@ pub fn instance_function<'a, 'ctx>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
 ) -> FunctionValue<'ctx> {
     let value;
-    if function.data.attributes.iter().any(|attribute| {
-        if let Attribute::Basic(inner) = attribute {
-            inner == "llvm_intrinsic"
-        } else {
-            false
-        }
-    }) {
-        value = compile_llvm_intrinsics(function.data.name.split("::").last().unwrap(), type_getter);
+    if Attribute::find_attribute("llvm_intrinsic", &function.data.attributes).is_some() {
+        value = compile_llvm_intrinsics(&function, type_getter);
     } else if is_modifier(function.data.modifiers, Modifier::Internal) {
-        value = create_function_value(&function, type_getter, None);
-        compile_internal(&type_getter, &type_getter.compiler, &function.data.name, value);
+        value = create_function_value(&function, type_getter, None, false);
+        compile_internal(&type_getter, &type_getter.compiler, &function, value);
     } else if is_modifier(function.data.modifiers, Modifier::Extern) {
-        value = create_function_value(&function, type_getter, Some(Linkage::External))
+        if let Some(Attribute::String(_, library)) = Attribute::find_attribute("link", &function.data.attributes) {
+            let mut libraries = type_getter.compiler.libraries.borrow_mut();
+            if !libraries.contains(library) {
+                libraries.push(library.clone());
+            }
+        }
+        value = compile_extern(&function, type_getter);
     } else {
-        value = create_function_value(&function, type_getter, None);
+        value = create_function_value(&function, type_getter, None, false);
         type_getter.compiling.borrow_mut().push((value, function));
     }
     return value;
@@ -48,7 +51,27 @@ pub fn instance_function<'a, 'ctx>(
 pub fn instance_types<'ctx>(types: &FinalizedTypes, type_getter: &mut CompilerTypeGetter<'ctx>) -> BasicTypeEnum<'ctx> {
     return match types {
         FinalizedTypes::Reference(inner) => type_getter.get_type(inner),
-        FinalizedTypes::Array(inner) => type_getter.get_type(inner),
+        // Arrays store primitives directly and point to everything else, like structure fields
+        FinalizedTypes::Array(inner) => {
+            let inner = match inner.deref() {
+                FinalizedTypes::Reference(inner) => inner,
+                _ => inner,
+            };
+            if inner.is_primitive() {
+                type_getter.get_type(inner)
+            } else {
+                type_getter.get_type(&FinalizedTypes::Reference(inner.clone()))
+            }
+        }
+        FinalizedTypes::Function(_, _) => closure_type(type_getter).as_basic_type_enum(),
+        // Tuples are anonymous structs holding a pointer to each value, without a type id
+        FinalizedTypes::Tuple(types) => {
+            let mut fields = Vec::default();
+            for found in types {
+                fields.push(type_getter.get_type(&FinalizedTypes::Reference(Box::new(found.clone()))));
+            }
+            type_getter.compiler.context.struct_type(fields.as_slice(), false).as_basic_type_enum()
+        }
         _ => {
             if is_modifier(types.inner_struct().data.modifiers, Modifier::Trait) {
                 type_getter
@@ -62,6 +85,24 @@ pub fn instance_types<'ctx>(types: &FinalizedTypes, type_getter: &mut CompilerTy
                         false,
                     )
                     .as_basic_type_enum()
+            } else if is_modifier(types.inner_struct().data.modifiers, Modifier::Enum) {
+                // Enums are the type id, the variant's tag, then a union of the variants' fields.
+                // Every field is a pointer, so the union is big enough to fit the variant with the most fields.
+                let context = type_getter.compiler.context;
+                let payload = context
+                    .i8_type()
+                    .ptr_type(AddressSpace::default())
+                    .array_type(types.inner_struct().payload_size() as u32);
+                context
+                    .struct_type(
+                        &[
+                            context.i64_type().as_basic_type_enum(),
+                            context.i64_type().as_basic_type_enum(),
+                            payload.as_basic_type_enum(),
+                        ],
+                        false,
+                    )
+                    .as_basic_type_enum()
             } else {
                 let mut fields = vec![type_getter.compiler.context.i64_type().as_basic_type_enum()];
                 for field in &types.inner_struct().fields {
@@ -92,6 +133,8 @@ pub fn compile_block<'ctx>(
 
     type_getter.current_block = Some(block);
     type_getter.compiler.builder.position_at_end(block);
+    // Variables declared in the block go out of scope when it ends, uncovering any they shadowed
+    let outer = type_getter.variables.clone();
     let mut broke = false;
     for line in &code.expressions {
         match line.expression_type {
@@ -102,7 +145,9 @@ pub fn compile_block<'ctx>(
                         type_getter.compiler.builder.build_unconditional_branch(destination);
                     }
                     compile_effect(type_getter, function, &line.effect, id);
+                    // The body returns from inside itself, like a switch with a return in every case
                     broke = true;
+                    continue;
                 }
 
                 if matches!(&line.effect.types, FinalizedEffectType::NOP) {
@@ -113,6 +158,7 @@ pub fn compile_block<'ctx>(
                     let returned = compile_effect(type_getter, function, &line.effect, id).unwrap();
 
                     if !broke {
+                        let returned = get_returned(type_getter, function, returned, id);
                         type_getter.compiler.builder.build_return(Some(&returned));
                     }
                 }
@@ -155,13 +201,34 @@ pub fn compile_block<'ctx>(
                     }
                 }
             }
-            ExpressionType::Break => return compile_effect(type_getter, function, &line.effect, id),
+            ExpressionType::Break => {
+                let output = compile_effect(type_getter, function, &line.effect, id);
+                type_getter.variables = outer;
+                return output;
+            }
         }
     }
 
+    type_getter.variables = outer;
     return None;
 }
 
+/// Compiles an effect returning a bool, loading it if it's a pointer
+fn compile_bool<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    effect: &FinalizedEffects,
+    id: &mut u64,
+) -> IntValue<'ctx> {
+    let value = compile_effect(type_getter, function, effect, id).unwrap();
+    return if value.is_pointer_value() {
+        *id += 1;
+        type_getter.compiler.builder.build_load(value.into_pointer_value(), &(*id - 1).to_string()).into_int_value()
+    } else {
+        value.into_int_value()
+    };
+}
+
 /// Compiles a single effect
 // skipcq: RS-R1000 Match statements have complexity calculated incorrectly
 pub fn compile_effect<'ctx>(
@@ -170,12 +237,24 @@ pub fn compile_effect<'ctx>(
     effect: &FinalizedEffects,
     id: &mut u64,
 ) -> Option<BasicValueEnum<'ctx>> {
+    type_getter.set_location(&effect.span);
     return match &effect.types {
         FinalizedEffectType::NOP => {
             panic!("Tried to compile a NOP! For {}", function.get_name().to_str().unwrap())
         }
         FinalizedEffectType::CreateVariable(name, inner, types) => {
-            let compiled = compile_effect(type_getter, function, inner, id).unwrap();
+            let mut compiled = compile_effect(type_getter, function, inner, id).unwrap();
+            // Primitives get their own storage, so setting a variable never changes the one it was copied from
+            if types.is_primitive() {
+                let value = if compiled.is_pointer_value() {
+                    *id += 1;
+                    type_getter.compiler.builder.build_load(compiled.into_pointer_value(), &(*id - 1).to_string())
+                } else {
+                    compiled
+                };
+                compiled = store_and_load(type_getter, function, value.get_type(), value, id).unwrap();
+            }
+            declare_variable(type_getter, function, name, types, compiled, None, &effect.span, id);
             type_getter.variables.insert(name.clone(), (types.clone(), compiled.as_basic_value_enum()));
             Some(compiled.as_basic_value_enum())
         }
@@ -187,13 +266,7 @@ pub fn compile_effect<'ctx>(
         }
         //Comparison effect, and label to jump to the first if true, second if false
         FinalizedEffectType::CompareJump(effect, then_body, else_body) => {
-            let effect = compile_effect(type_getter, function, effect, id).unwrap();
-            let effect = if effect.is_pointer_value() {
-                *id += 1;
-                type_getter.compiler.builder.build_load(effect.into_pointer_value(), &(*id - 1).to_string()).into_int_value()
-            } else {
-                effect.into_int_value()
-            };
+            let effect = compile_bool(type_getter, function, effect, id);
             let then = get_block_or_create(then_body, function, type_getter);
             let else_block = get_block_or_create(else_body, function, type_getter);
             type_getter.compiler.builder.build_conditional_branch(effect, then, else_block);
@@ -212,13 +285,13 @@ pub fn compile_effect<'ctx>(
                     compile_effect(type_getter, function, pointer.as_ref().unwrap(), id).unwrap().into_pointer_value();
                 final_arguments.push(From::from(pointer));
 
-                add_args(&mut final_arguments, type_getter, function, arguments, true, id);
+                add_args(&mut final_arguments, type_getter, function, calling, arguments, true, id);
 
                 *id += 1;
                 type_getter.compiler.builder.build_call(calling, final_arguments.as_slice(), &(*id - 1).to_string());
                 Some(pointer.as_basic_value_enum())
             } else {
-                add_args(&mut final_arguments, type_getter, function, arguments, false, id);
+                add_args(&mut final_arguments, type_getter, function, calling, arguments, false, id);
 
                 let call = type_getter
                     .compiler
@@ -227,16 +300,13 @@ pub fn compile_effect<'ctx>(
                     .try_as_basic_value()
                     .left();
                 *id += 1;
+                // Values returned directly (like primitives) are stored on the stack
                 return match call {
                     Some(inner) => {
                         if inner.is_pointer_value() {
                             Some(inner)
                         } else {
-                            let pointer = compile_effect(type_getter, function, pointer.as_ref().unwrap(), id)
-                                .unwrap()
-                                .into_pointer_value();
-                            type_getter.compiler.builder.build_store(pointer, inner);
-                            Some(pointer.as_basic_value_enum())
+                            store_and_load(type_getter, function, inner.get_type(), inner, id)
                         }
                     }
                     None => None,
@@ -245,6 +315,24 @@ pub fn compile_effect<'ctx>(
         }
         //Sets pointer to value
         FinalizedEffectType::Set(setting, value) => {
+            // Fields point to their structures and arrays, so setting one points it somewhere else instead of
+            // writing over the old value, which could be too small for the new one
+            if let FinalizedEffectType::Load(loading_from, field, _) = &setting.types {
+                if !setting.types.get_nongeneric_return(type_getter).map_or(true, |found| found.is_primitive()) {
+                    let gep = field_pointer(type_getter, function, loading_from, field, id);
+                    let storing = compile_effect(type_getter, function, value, id).unwrap();
+                    let element = gep.get_type().get_element_type().into_pointer_type();
+                    let storing = type_getter.compiler.builder.build_pointer_cast(
+                        storing.into_pointer_value(),
+                        element,
+                        &id.to_string(),
+                    );
+                    *id += 1;
+                    type_getter.compiler.builder.build_store(gep, storing);
+                    return Some(storing.as_basic_value_enum());
+                }
+            }
+
             let output = compile_effect(type_getter, function, setting, id).unwrap();
             let mut storing = compile_effect(type_getter, function, value, id).unwrap();
             if storing.is_pointer_value() {
@@ -259,28 +347,21 @@ pub fn compile_effect<'ctx>(
         }
         //Loads variable/field pointer from program, or self if program is None
         FinalizedEffectType::Load(loading_from, field, _) => {
-            let from = compile_effect(type_getter, function, loading_from, id).unwrap();
-            //Compensate for type id
-            let mut offset = 1;
-            for struct_field in &loading_from.types.get_nongeneric_return(type_getter).unwrap().inner_struct().fields {
-                if &struct_field.field.name != field {
-                    offset += 1;
-                } else {
-                    break;
-                }
-            }
-
-            let gep =
-                type_getter.compiler.builder.build_struct_gep(from.into_pointer_value(), offset, &id.to_string()).unwrap();
-            *id += 2;
+            let gep = field_pointer(type_getter, function, loading_from, field, id);
+            *id += 1;
             Some(type_getter.compiler.builder.build_load(gep, &(*id - 1).to_string()))
         }
         //Struct to create and a tuple of the index of the argument and the argument
         FinalizedEffectType::CreateStruct(effect, structure, arguments) => {
             let mut out_arguments = vec![MaybeUninit::uninit(); arguments.len()];
 
+            let fields = &structure.inner_struct().fields;
             for (index, effect) in arguments {
-                let returned = compile_effect(type_getter, function, effect, id).unwrap();
+                let mut returned = compile_effect(type_getter, function, effect, id).unwrap();
+                // Primitives could be on the stack, so they're copied to the heap to outlive the function
+                if returned.is_pointer_value() && fields[*index].field.field_type.is_primitive() {
+                    returned = heap_copy(type_getter, returned.into_pointer_value(), id).as_basic_value_enum();
+                }
                 *out_arguments.get_mut(*index).unwrap() = MaybeUninit::new(returned);
             }
 
@@ -304,11 +385,162 @@ pub fn compile_effect<'ctx>(
 
             Some(pointer.as_basic_value_enum())
         }
-        FinalizedEffectType::Float(float) => {
-            Some(type_getter.compiler.context.f64_type().const_float(*float).as_basic_value_enum())
+        FinalizedEffectType::CreateTuple(types, values) => {
+            let tuple = type_getter.get_type(types);
+            let pointer = malloc_type(type_getter, tuple.ptr_type(AddressSpace::default()).const_zero(), id);
+            for (index, value) in values.iter().enumerate() {
+                let mut returned = compile_effect(type_getter, function, value, id).unwrap();
+                // Every value is stored as a pointer, primitives are copied to the heap to outlive the function
+                if returned.is_pointer_value() {
+                    if value.types.get_nongeneric_return(type_getter).map_or(false, |found| found.is_primitive()) {
+                        returned = heap_copy(type_getter, returned.into_pointer_value(), id).as_basic_value_enum();
+                    }
+                } else {
+                    let malloc =
+                        malloc_type(type_getter, returned.get_type().ptr_type(AddressSpace::default()).const_zero(), id);
+                    type_getter.compiler.builder.build_store(malloc, returned);
+                    returned = malloc.as_basic_value_enum();
+                }
+
+                let gep = type_getter.compiler.builder.build_struct_gep(pointer, index as u32, &id.to_string()).unwrap();
+                *id += 1;
+                type_getter.compiler.builder.build_store(gep, returned);
+            }
+            Some(pointer.as_basic_value_enum())
+        }
+        FinalizedEffectType::CreateVariant(effect, structure, tag, arguments) => {
+            let pointer = compile_effect(type_getter, function, effect, id).unwrap().into_pointer_value();
+            let context = type_getter.compiler.context;
+            type_getter.compiler.builder.build_store(pointer, context.i64_type().const_int(structure.id(), false));
+            let tag_pointer = type_getter.compiler.builder.build_struct_gep(pointer, 1, &id.to_string()).unwrap();
+            *id += 1;
+            type_getter.compiler.builder.build_store(tag_pointer, context.i64_type().const_int(*tag as u64, false));
+
+            let fields = &structure.inner_struct().fields;
+            for (index, effect) in arguments {
+                let mut returned = compile_effect(type_getter, function, effect, id).unwrap();
+                // Primitives could be on the stack, so they're copied to the heap to outlive the function
+                if returned.is_pointer_value() && fields[*index].field.field_type.is_primitive() {
+                    returned = heap_copy(type_getter, returned.into_pointer_value(), id).as_basic_value_enum();
+                }
+                let field = get_variant_field(type_getter, pointer, structure, *index, id);
+                type_getter.compiler.builder.build_store(field, returned);
+            }
+
+            Some(pointer.as_basic_value_enum())
         }
-        FinalizedEffectType::UInt(int) => {
-            Some(type_getter.compiler.context.i64_type().const_int(*int, false).as_basic_value_enum())
+        FinalizedEffectType::IsVariant(effect, tag) => {
+            let pointer = compile_effect(type_getter, function, effect, id).unwrap().into_pointer_value();
+            let tag_pointer = type_getter.compiler.builder.build_struct_gep(pointer, 1, &id.to_string()).unwrap();
+            let found = type_getter.compiler.builder.build_load(tag_pointer, &(*id + 1).to_string()).into_int_value();
+            *id += 3;
+            Some(
+                type_getter
+                    .compiler
+                    .builder
+                    .build_int_compare(
+                        IntPredicate::EQ,
+                        found,
+                        type_getter.compiler.context.i64_type().const_int(*tag as u64, false),
+                        &(*id - 1).to_string(),
+                    )
+                    .as_basic_value_enum(),
+            )
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            compile_effect(type_getter, function, value, id);
+            let check = compile_bool(type_getter, function, check, id);
+
+            let returning = type_getter.compiler.context.append_basic_block(function, &format!("propagate{}", id));
+            let continuing = type_getter.compiler.context.append_basic_block(function, &format!("propagate{}end", id));
+            *id += 1;
+            type_getter.compiler.builder.build_conditional_branch(check, returning, continuing);
+
+            type_getter.current_block = Some(returning);
+            type_getter.compiler.builder.position_at_end(returning);
+            let residual = compile_effect(type_getter, function, residual, id).unwrap();
+            let residual = get_returned(type_getter, function, residual, id);
+            type_getter.compiler.builder.build_return(Some(&residual));
+
+            type_getter.current_block = Some(continuing);
+            type_getter.compiler.builder.position_at_end(continuing);
+            compile_effect(type_getter, function, unwrapped, id)
+        }
+        FinalizedEffectType::ShortCircuit(first, second, and) => {
+            let first = compile_bool(type_getter, function, first, id);
+            let first_block = type_getter.compiler.builder.get_insert_block().unwrap();
+            let right = type_getter.compiler.context.append_basic_block(function, &format!("short{}", id));
+            let end = type_getter.compiler.context.append_basic_block(function, &format!("short{}end", id));
+            *id += 1;
+            // The right side only runs if the left side is true for ands, or false for ors
+            if *and {
+                type_getter.compiler.builder.build_conditional_branch(first, right, end);
+            } else {
+                type_getter.compiler.builder.build_conditional_branch(first, end, right);
+            }
+
+            type_getter.current_block = Some(right);
+            type_getter.compiler.builder.position_at_end(right);
+            let second = compile_bool(type_getter, function, second, id);
+            // The right side can add its own blocks, so the phi comes from wherever it ended
+            let second_block = type_getter.compiler.builder.get_insert_block().unwrap();
+            type_getter.compiler.builder.build_unconditional_branch(end);
+
+            type_getter.current_block = Some(end);
+            type_getter.compiler.builder.position_at_end(end);
+            let phi = type_getter.compiler.builder.build_phi(type_getter.compiler.context.bool_type(), &id.to_string());
+            *id += 1;
+            phi.add_incoming(&[(&first, first_block), (&second, second_block)]);
+            // Stored on the stack like other primitives, so it can be passed to functions
+            let phi = phi.as_basic_value();
+            store_and_load(type_getter, function, phi.get_type(), phi, id)
+        }
+        FinalizedEffectType::CreateClosure(arguments, returning, captures, body) => {
+            Some(compile_closure(type_getter, function, arguments, returning.as_ref(), captures, body, &effect.span, id))
+        }
+        FinalizedEffectType::FunctionReference(target) => Some(compile_function_reference(type_getter, target, id)),
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            let calling_type = calling.types.get_nongeneric_return(type_getter).unwrap();
+            let (argument_types, returning) = calling_type.inner_function().unwrap();
+            let function_type = closure_function_type(type_getter, argument_types, returning.as_deref());
+
+            let closure = compile_effect(type_getter, function, calling, id).unwrap().into_pointer_value();
+            let pointer = type_getter.compiler.builder.build_struct_gep(closure, 0, &id.to_string()).unwrap();
+            let pointer = type_getter.compiler.builder.build_load(pointer, &(*id + 1).to_string()).into_pointer_value();
+            let pointer = type_getter.compiler.builder.build_pointer_cast(
+                pointer,
+                function_type.ptr_type(AddressSpace::default()),
+                &(*id + 2).to_string(),
+            );
+            let environment = type_getter.compiler.builder.build_struct_gep(closure, 1, &(*id + 3).to_string()).unwrap();
+            let environment = type_getter.compiler.builder.build_load(environment, &(*id + 4).to_string());
+            *id += 5;
+
+            let mut final_arguments = vec![BasicMetadataValueEnum::from(environment)];
+            for argument in arguments {
+                final_arguments.push(From::from(compile_effect(type_getter, function, argument, id).unwrap()));
+            }
+
+            let call = type_getter
+                .compiler
+                .builder
+                .build_call(CallableValue::try_from(pointer).unwrap(), final_arguments.as_slice(), &id.to_string())
+                .try_as_basic_value()
+                .left();
+            *id += 1;
+            // Values returned directly (like primitives) are stored on the stack
+            match call {
+                Some(inner) if !inner.is_pointer_value() => {
+                    store_and_load(type_getter, function, inner.get_type(), inner, id)
+                }
+                _ => call,
+            }
+        }
+        FinalizedEffectType::Float(float, types) => {
+            Some(type_getter.get_type(types).into_float_type().const_float(*float).as_basic_value_enum())
+        }
+        FinalizedEffectType::UInt(int, types) => {
+            Some(type_getter.get_type(types).into_int_type().const_int(*int, false).as_basic_value_enum())
         }
         FinalizedEffectType::Bool(bool) => {
             Some(type_getter.compiler.context.bool_type().const_int(*bool as u64, false).as_basic_value_enum())
@@ -324,39 +556,14 @@ pub fn compile_effect<'ctx>(
 
             let pointer_type = if output.get_type().is_pointer_type() {
                 return Some(output);
+            } else if inner.types.get_nongeneric_return(type_getter).map_or(false, |types| types.is_primitive()) {
+                // Primitives are kept on the stack instead of being allocated
+                return store_and_load(type_getter, function, output.get_type(), output, id);
             } else {
                 output.get_type().ptr_type(AddressSpace::default())
             };
 
-            let size = unsafe {
-                type_getter.compiler.builder.build_gep(
-                    pointer_type.const_zero(),
-                    &[type_getter.compiler.context.i64_type().const_int(1, false)],
-                    &id.to_string(),
-                )
-            };
-
-            *id += 1;
-
-            let malloc = type_getter
-                .compiler
-                .builder
-                .build_call(
-                    type_getter
-                        .compiler
-                        .module
-                        .get_function("malloc")
-                        .unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-                    &[BasicMetadataValueEnum::PointerValue(size)],
-                    &id.to_string(),
-                )
-                .try_as_basic_value()
-                .unwrap_left()
-                .into_pointer_value();
-            *id += 1;
-
-            let malloc = type_getter.compiler.builder.build_pointer_cast(malloc, pointer_type, &id.to_string());
-            *id += 1;
+            let malloc = malloc_type(type_getter, pointer_type.const_zero(), id);
 
             if output.is_pointer_value() {
                 output = type_getter.compiler.builder.build_load(output.into_pointer_value(), &id.to_string());
@@ -368,11 +575,27 @@ pub fn compile_effect<'ctx>(
         FinalizedEffectType::StackStore(inner) => {
             let output = compile_effect(type_getter, function, inner, id).unwrap();
             if !output.is_pointer_value() {
-                store_and_load(type_getter, output.get_type(), output, id)
+                store_and_load(type_getter, function, output.get_type(), output, id)
             } else {
                 Some(output)
             }
         }
+        FinalizedEffectType::Free(inner) => {
+            let pointer = compile_effect(type_getter, function, inner, id).unwrap().into_pointer_value();
+            let pointer = type_getter.compiler.builder.build_ptr_to_int(
+                pointer,
+                type_getter.compiler.context.i64_type(),
+                &id.to_string(),
+            );
+            *id += 1;
+            type_getter.compiler.builder.build_call(
+                get_intrinsic("mem::free", type_getter),
+                &[BasicMetadataValueEnum::IntValue(pointer)],
+                &id.to_string(),
+            );
+            *id += 1;
+            None
+        }
         FinalizedEffectType::ReferenceLoad(inner) => {
             let inner = compile_effect(type_getter, function, inner, id).unwrap();
             let output = type_getter.compiler.builder.build_load(inner.into_pointer_value(), &id.to_string());
@@ -385,47 +608,7 @@ pub fn compile_effect<'ctx>(
             let pointer_type =
                 if output.is_pointer_type() { output.into_pointer_type() } else { output.ptr_type(AddressSpace::default()) };
 
-            let size = unsafe {
-                type_getter.compiler.builder.build_gep(
-                    pointer_type.const_zero(),
-                    &[type_getter.compiler.context.i64_type().const_int(1, false)],
-                    &id.to_string(),
-                )
-            };
-
-            *id += 1;
-
-            let size = type_getter
-                .compiler
-                .builder
-                .build_bitcast(
-                    size,
-                    type_getter.compiler.context.i64_type().ptr_type(AddressSpace::default()),
-                    &id.to_string(),
-                )
-                .into_pointer_value();
-
-            *id += 1;
-
-            let malloc = type_getter
-                .compiler
-                .builder
-                .build_call(
-                    type_getter
-                        .compiler
-                        .module
-                        .get_function("malloc")
-                        .unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-                    &[BasicMetadataValueEnum::PointerValue(size)],
-                    &id.to_string(),
-                )
-                .try_as_basic_value()
-                .unwrap_left()
-                .into_pointer_value();
-            *id += 1;
-
-            let malloc = type_getter.compiler.builder.build_pointer_cast(malloc, pointer_type, &id.to_string());
-            *id += 1;
+            let malloc = malloc_type(type_getter, pointer_type.const_zero(), id);
 
             Some(malloc.as_basic_value_enum())
         }
@@ -433,15 +616,10 @@ pub fn compile_effect<'ctx>(
             let ptr_type = types
                 .as_ref()
                 .map(|inner| {
-                    let inner = type_getter.get_type(inner);
+                    let element = type_getter.get_type(&FinalizedTypes::Array(Box::new(inner.clone())));
                     unsafe {
                         type_getter.compiler.builder.build_gep(
-                            if inner.is_pointer_type() {
-                                inner.into_pointer_type()
-                            } else {
-                                inner.ptr_type(AddressSpace::default())
-                            }
-                            .const_zero(),
+                            element.ptr_type(AddressSpace::default()).const_zero(),
                             &[type_getter.compiler.context.i64_type().const_int(values.len() as u64 + 1, false)],
                             &id.to_string(),
                         )
@@ -468,7 +646,12 @@ pub fn compile_effect<'ctx>(
                 };
                 i += 1;
                 *id += 1;
-                let effect = compile_effect(type_getter, function, value, id).unwrap();
+                let mut effect = compile_effect(type_getter, function, value, id).unwrap();
+                // Primitives are stored in the array directly, so they don't point to the stack
+                if effect.is_pointer_value() && types.as_ref().map_or(false, |types| types.is_primitive()) {
+                    *id += 1;
+                    effect = type_getter.compiler.builder.build_load(effect.into_pointer_value(), &(*id - 1).to_string());
+                }
                 type_getter.compiler.builder.build_store(gep, effect);
             }
 
@@ -527,7 +710,7 @@ pub fn compile_effect<'ctx>(
             *id += 1;
             let offset = type_getter.compiler.builder.build_load(offset, &id.to_string()).into_pointer_value();
             *id += 2;
-            type_getter
+            let call = type_getter
                 .compiler
                 .builder
                 .build_call(
@@ -536,7 +719,14 @@ pub fn compile_effect<'ctx>(
                     &(*id - 1).to_string(),
                 )
                 .try_as_basic_value()
-                .left()
+                .left();
+            // Values returned directly (like primitives) are stored on the stack
+            match call {
+                Some(inner) if !inner.is_pointer_value() => {
+                    store_and_load(type_getter, function, inner.get_type(), inner, id)
+                }
+                _ => call,
+            }
         }
         FinalizedEffectType::Downcast(base, target, functions) => {
             let base_return_types = base.types.get_nongeneric_return(type_getter).unwrap();
@@ -548,7 +738,11 @@ pub fn compile_effect<'ctx>(
                 }
             } else {
                 let table = type_getter.vtable.clone();
-                let base = compile_effect(type_getter, function, base, id).unwrap();
+                let mut base = compile_effect(type_getter, function, base, id).unwrap();
+                // Primitives could be on the stack, so they're copied to the heap to outlive the function
+                if base.is_pointer_value() && base_return_types.is_primitive() {
+                    base = heap_copy(type_getter, base.into_pointer_value(), id).as_basic_value_enum();
+                }
                 let table = table.borrow_mut().get_vtable(type_getter, target, &base_return_types, functions);
                 *id += 1;
 
@@ -576,36 +770,423 @@ pub fn compile_effect<'ctx>(
     };
 }
 
-/// Stores a value and then loads it
+/// Compiles a closure into its own function, which takes a pointer to an environment struct holding the
+/// captured variables before the closure's arguments. Returns the function value pointing to both.
+fn compile_closure<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    arguments: &[FinalizedField],
+    returning: Option<&FinalizedTypes>,
+    captures: &[(String, FinalizedTypes)],
+    body: &FinalizedCodeBody,
+    span: &Span,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    let context = type_getter.compiler.context;
+    let byte_pointer = context.i8_type().ptr_type(AddressSpace::default());
+
+    // Copy the captured variables into the environment
+    let mut values = Vec::default();
+    for (name, types) in captures {
+        let mut value = type_getter.variables.get(name).unwrap().1;
+        // Primitives could be on the stack, so they're copied to the heap to outlive the function
+        if value.is_pointer_value() && types.is_primitive() {
+            value = heap_copy(type_getter, value.into_pointer_value(), id).as_basic_value_enum();
+        }
+        values.push(value);
+    }
+    let environment_type = context.struct_type(&values.iter().map(|value| value.get_type()).collect::<Vec<_>>(), false);
+    let environment = if values.is_empty() {
+        byte_pointer.const_null()
+    } else {
+        let environment = malloc_type(type_getter, environment_type.ptr_type(AddressSpace::default()).const_zero(), id);
+        for (i, value) in values.iter().enumerate() {
+            let field = type_getter.compiler.builder.build_struct_gep(environment, i as u32, &id.to_string()).unwrap();
+            *id += 1;
+            type_getter.compiler.builder.build_store(field, *value);
+        }
+        type_getter.compiler.builder.build_pointer_cast(environment, byte_pointer, &id.to_string())
+    };
+    *id += 1;
+
+    let argument_types = arguments.iter().map(|argument| argument.field_type.clone()).collect::<Vec<_>>();
+    let function_type = closure_function_type(type_getter, &argument_types, returning);
+    let name = format!("{}$closure{}", function.get_name().to_str().unwrap(), id);
+    let closure_function = type_getter.compiler.module.add_function(&name, function_type, None);
+    *id += 1;
+    // The closure is its own function to the debugger too
+    let scope = type_getter.compiler.debug.as_ref().map(|debug| debug.create_function(closure_function, &name, span));
+    type_getter.compiler.builder.unset_current_debug_location();
+
+    // The entry block loads the captured variables out of the environment
+    let entry = context.append_basic_block(closure_function, "entry");
+    type_getter.compiler.builder.position_at_end(entry);
+    let mut variables = HashMap::default();
+    if !values.is_empty() {
+        let loading = type_getter.compiler.builder.build_pointer_cast(
+            closure_function.get_nth_param(0).unwrap().into_pointer_value(),
+            environment_type.ptr_type(AddressSpace::default()),
+            "environment",
+        );
+        for (i, (name, types)) in captures.iter().enumerate() {
+            let field = type_getter.compiler.builder.build_struct_gep(loading, i as u32, &format!("{}_ptr", i)).unwrap();
+            let value = type_getter.compiler.builder.build_load(field, &i.to_string());
+            variables.insert(name.clone(), (types.clone(), value));
+        }
+    }
+    for (i, argument) in arguments.iter().enumerate() {
+        variables.insert(
+            argument.name.clone(),
+            (
+                FinalizedTypes::Reference(Box::new(argument.field_type.clone())),
+                closure_function.get_nth_param(i as u32 + 1).unwrap(),
+            ),
+        );
+    }
+
+    let mut closure_getter = type_getter.for_closure(variables);
+    closure_getter.scope = scope;
+    let block = context.append_basic_block(closure_function, &body.label);
+    type_getter.compiler.builder.build_unconditional_branch(block);
+    closure_getter.blocks.insert(body.label.clone(), block);
+    compile_block(body, closure_function, &mut closure_getter, &mut 0);
+
+    type_getter.compiler.builder.position_at_end(type_getter.current_block.unwrap());
+    type_getter.set_location(span);
+    return build_function_value(type_getter, closure_function, environment, id);
+}
+
+/// Compiles a reference to a function, which is a function value with a wrapper that ignores the environment.
+fn compile_function_reference<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    target: &Arc<CodelessFinalizedFunction>,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    let context = type_getter.compiler.context;
+    let calling = type_getter.get_function(target);
+    type_getter.compiler.builder.position_at_end(type_getter.current_block.unwrap());
+
+    let name = format!("{}$reference", target.data.name);
+    let wrapper = match type_getter.compiler.module.get_function(&name) {
+        Some(found) => found,
+        None => {
+            let argument_types =
+                target.arguments.iter().map(|argument| argument.field.field_type.clone()).collect::<Vec<_>>();
+            let function_type = closure_function_type(type_getter, &argument_types, target.return_type.as_ref());
+            let wrapper = type_getter.compiler.module.add_function(&name, function_type, None);
+
+            let builder = context.create_builder();
+            builder.position_at_end(context.append_basic_block(wrapper, "0"));
+            let arguments = wrapper.get_param_iter().skip(1).map(From::from).collect::<Vec<BasicMetadataValueEnum>>();
+            let returned = builder.build_call(calling, arguments.as_slice(), "1").try_as_basic_value().left();
+            builder.build_return(returned.as_ref().map(|inner| inner as &dyn BasicValue));
+            wrapper
+        }
+    };
+
+    return build_function_value(type_getter, wrapper, context.i8_type().ptr_type(AddressSpace::default()).const_null(), id);
+}
+
+/// Allocates a function value pointing to the function and its environment
+fn build_function_value<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    environment: PointerValue<'ctx>,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    let byte_pointer = type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let output = malloc_type(type_getter, closure_type(type_getter).ptr_type(AddressSpace::default()).const_zero(), id);
+
+    let pointer = type_getter.compiler.builder.build_pointer_cast(
+        function.as_global_value().as_pointer_value(),
+        byte_pointer,
+        &id.to_string(),
+    );
+    let field = type_getter.compiler.builder.build_struct_gep(output, 0, &(*id + 1).to_string()).unwrap();
+    type_getter.compiler.builder.build_store(field, pointer);
+    let field = type_getter.compiler.builder.build_struct_gep(output, 1, &(*id + 2).to_string()).unwrap();
+    type_getter.compiler.builder.build_store(field, environment);
+    *id += 3;
+    return output.as_basic_value_enum();
+}
+
+/// Declares the function's arguments to the debugger in the function's first block
+pub fn declare_arguments<'ctx>(
+    function: &FinalizedFunction,
+    value: FunctionValue<'ctx>,
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    id: &mut u64,
+) {
+    let block = type_getter.compiler.context.append_basic_block(value, &function.code.label);
+    type_getter.blocks.insert(function.code.label.clone(), block);
+    type_getter.current_block = Some(block);
+    type_getter.compiler.builder.position_at_end(block);
+    for (i, argument) in function.fields.iter().enumerate() {
+        if let Some((types, found)) = type_getter.variables.get(&argument.field.name).cloned() {
+            declare_variable(
+                type_getter,
+                value,
+                &argument.field.name,
+                &types,
+                found,
+                Some(i as u32 + 1),
+                &function.data.span,
+                id,
+            );
+        }
+    }
+}
+
+/// Declares a variable to the debugger if debug info is being emitted.
+/// Primitives are already on the stack, other values are pointers that get a stack slot for the debugger to read.
+fn declare_variable<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    name: &str,
+    types: &FinalizedTypes,
+    value: BasicValueEnum<'ctx>,
+    argument: Option<u32>,
+    span: &Span,
+    id: &mut u64,
+) {
+    let compiler = type_getter.compiler.clone();
+    let (debug, scope) = match (&compiler.debug, type_getter.scope) {
+        (Some(debug), Some(scope)) => (debug, scope),
+        _ => return,
+    };
+    let type_name = types.name_safe().unwrap_or_default();
+    let (debug_type, storage) = match debug.primitive_type(&type_name) {
+        Some(found) if value.is_pointer_value() => (found, value.into_pointer_value()),
+        Some(found) => {
+            (found, store_and_load(type_getter, function, value.get_type(), value, id).unwrap().into_pointer_value())
+        }
+        None if value.is_pointer_value() => (
+            debug.pointer_type(&type_name),
+            store_and_load(type_getter, function, value.get_type(), value, id).unwrap().into_pointer_value(),
+        ),
+        None => return,
+    };
+    let block = compiler.builder.get_insert_block().unwrap();
+    debug.declare_variable(name, debug_type, storage, argument, span, scope, block);
+}
+
+/// Stores a value on the stack and returns the pointer to it
 fn store_and_load<'ctx, T: BasicType<'ctx>>(
     type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
     types: T,
     inputer: BasicValueEnum<'ctx>,
     id: &mut u64,
 ) -> Option<BasicValueEnum<'ctx>> {
-    let pointer = type_getter.compiler.builder.build_alloca(types, &id.to_string());
-    *id += 1;
+    let pointer = stack_allocate(type_getter, function, types, id);
     type_getter.compiler.builder.build_store(pointer, inputer);
     return Some(pointer.as_basic_value_enum());
 }
 
+/// Allocates the type on the stack at the start of the function.
+/// Allocating in the entry block keeps loops from growing the stack every iteration.
+fn stack_allocate<'ctx, T: BasicType<'ctx>>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    types: T,
+    id: &mut u64,
+) -> PointerValue<'ctx> {
+    let entry = function.get_first_basic_block().unwrap();
+    let builder = type_getter.compiler.context.create_builder();
+    match entry.get_first_instruction() {
+        Some(instruction) => builder.position_before(&instruction),
+        None => builder.position_at_end(entry),
+    }
+    let pointer = builder.build_alloca(types, &id.to_string());
+    *id += 1;
+    return pointer;
+}
+
+/// Copies a value to the heap so it can outlive the current function
+fn heap_copy<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    value: PointerValue<'ctx>,
+    id: &mut u64,
+) -> PointerValue<'ctx> {
+    let malloc = malloc_type(type_getter, value.get_type().const_zero(), id);
+    let loaded = type_getter.compiler.builder.build_load(value, &id.to_string());
+    *id += 1;
+    type_getter.compiler.builder.build_store(malloc, loaded);
+    return malloc;
+}
+
+/// Gets the value to return from the function, loading primitives returned by value
+/// and copying primitives returned by pointer to the heap so they don't point to the stack
+fn get_returned<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    returned: BasicValueEnum<'ctx>,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    let returning = match function.get_type().get_return_type() {
+        Some(returning) => returning,
+        None => return returned,
+    };
+
+    if !returned.is_pointer_value() {
+        return returned;
+    }
+
+    if !returning.is_pointer_type() {
+        *id += 1;
+        return type_getter.compiler.builder.build_load(returned.into_pointer_value(), &(*id - 1).to_string());
+    }
+
+    // Only the target returns primitives by pointer, other pointers to numbers are arrays
+    let target = type_getter.target == Some(function);
+    let element = returning.into_pointer_type().get_element_type();
+    if target && (element.is_int_type() || element.is_float_type()) {
+        return heap_copy(type_getter, returned.into_pointer_value(), id).as_basic_value_enum();
+    }
+    return returned;
+}
+
 /// Adds the arguments to final_arguments
 fn add_args<'ctx, 'a>(
     final_arguments: &'a mut Vec<BasicMetadataValueEnum<'ctx>>,
     type_getter: &mut CompilerTypeGetter<'ctx>,
     function: FunctionValue<'ctx>,
+    calling: FunctionValue<'ctx>,
     arguments: &'a Vec<FinalizedEffects>,
     offset: bool,
     id: &mut u64,
 ) {
     for i in offset as usize..arguments.len() {
         let argument = arguments.get(i).unwrap();
-        let value = compile_effect(type_getter, function, argument, id).unwrap();
+        let mut value = compile_effect(type_getter, function, argument, id).unwrap();
 
+        match calling.get_type().get_param_types().get(final_arguments.len()) {
+            Some(param) => value = match_param(type_getter, *param, value, id),
+            None => value = promote_vararg(type_getter, argument, value, id),
+        }
         final_arguments.push(From::from(value));
     }
 }
 
+/// Converts the argument to the parameter's type. Raven functions take pointers, but C functions
+/// declared with #[llvm_intrinsic] take primitives by value and other types as byte pointers.
+fn match_param<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    param: BasicTypeEnum<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    if value.get_type() == param || !value.is_pointer_value() {
+        return value;
+    }
+
+    let pointer = if param.is_pointer_type() { param } else { param.ptr_type(AddressSpace::default()).as_basic_type_enum() };
+    let mut output = type_getter.compiler.builder.build_bitcast(value, pointer, &id.to_string());
+    *id += 1;
+    if !param.is_pointer_type() {
+        output = type_getter.compiler.builder.build_load(output.into_pointer_value(), &id.to_string());
+        *id += 1;
+    }
+    return output;
+}
+
+/// Passes primitive varargs by value, promoting them to the types C expects (like printf's doubles).
+fn promote_vararg<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    argument: &FinalizedEffects,
+    mut value: BasicValueEnum<'ctx>,
+    id: &mut u64,
+) -> BasicValueEnum<'ctx> {
+    let types = match argument.types.get_nongeneric_return(type_getter) {
+        Some(types) if types.is_primitive() => types,
+        _ => return value,
+    };
+
+    if value.is_pointer_value() {
+        value = type_getter.compiler.builder.build_load(value.into_pointer_value(), &id.to_string());
+        *id += 1;
+    }
+
+    let context = type_getter.compiler.context;
+    let output = match value {
+        BasicValueEnum::FloatValue(float) if float.get_type() == context.f32_type() => {
+            type_getter.compiler.builder.build_float_ext(float, context.f64_type(), &id.to_string()).as_basic_value_enum()
+        }
+        BasicValueEnum::IntValue(int) if int.get_type().get_bit_width() < 32 => {
+            if types.name_safe().map_or(false, |name| name.starts_with('i')) {
+                type_getter.compiler.builder.build_int_s_extend(int, context.i32_type(), &id.to_string())
+            } else {
+                type_getter.compiler.builder.build_int_z_extend(int, context.i32_type(), &id.to_string())
+            }
+            .as_basic_value_enum()
+        }
+        _ => return value,
+    };
+    *id += 1;
+    return output;
+}
+
+/// Gets a pointer to the enum field's slot in the union of the enum's variants
+fn get_variant_field<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    pointer: PointerValue<'ctx>,
+    structure: &FinalizedTypes,
+    index: usize,
+    id: &mut u64,
+) -> PointerValue<'ctx> {
+    let field = &structure.inner_struct().fields[index].field;
+    let variant = field.name.split('.').next().unwrap();
+    let slot = structure.inner_struct().variant_fields(variant).iter().position(|found| *found == index).unwrap();
+
+    let context = type_getter.compiler.context;
+    let payload = type_getter.compiler.builder.build_struct_gep(pointer, 2, &id.to_string()).unwrap();
+    let slot = unsafe {
+        type_getter.compiler.builder.build_in_bounds_gep(
+            payload,
+            &[context.i64_type().const_zero(), context.i64_type().const_int(slot as u64, false)],
+            &(*id + 1).to_string(),
+        )
+    };
+    *id += 3;
+    let field_type = type_getter.get_type(&field.field_type).ptr_type(AddressSpace::default());
+    return type_getter.compiler.builder.build_pointer_cast(slot, field_type, &(*id - 1).to_string());
+}
+
+/// Gets a pointer to the field of the structure, tuple or enum variant the effect returns
+fn field_pointer<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    function: FunctionValue<'ctx>,
+    loading_from: &FinalizedEffects,
+    field: &String,
+    id: &mut u64,
+) -> PointerValue<'ctx> {
+    let from = compile_effect(type_getter, function, loading_from, id).unwrap();
+    let structure = loading_from.types.get_nongeneric_return(type_getter).unwrap();
+    if structure.inner_tuple().is_some() {
+        let index = field.parse::<u32>().unwrap();
+        let gep = type_getter.compiler.builder.build_struct_gep(from.into_pointer_value(), index, &id.to_string()).unwrap();
+        *id += 1;
+        return gep;
+    }
+    if is_modifier(structure.inner_struct().data.modifiers, Modifier::Enum) {
+        let field = structure.inner_struct().fields.iter().position(|found| &found.field.name == field).unwrap();
+        return get_variant_field(type_getter, from.into_pointer_value(), &structure, field, id);
+    }
+    //Compensate for type id
+    let mut offset = 1;
+    for struct_field in &structure.inner_struct().fields {
+        if &struct_field.field.name != field {
+            offset += 1;
+        } else {
+            break;
+        }
+    }
+
+    let gep = type_getter.compiler.builder.build_struct_gep(from.into_pointer_value(), offset, &id.to_string()).unwrap();
+    *id += 1;
+    return gep;
+}
+
 /// Gets a block with the given name, and if it's not found, create it
 fn get_block_or_create<'ctx>(
     name: &String,
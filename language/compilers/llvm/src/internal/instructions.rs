@@ -1,63 +1,61 @@
 use crate::compiler::CompilerImpl;
-use crate::internal::intrinsics::compile_llvm_intrinsics;
-use crate::internal::math_internal::math_internal;
+use crate::internal::intrinsics::get_intrinsic;
+use crate::internal::math_internal::{load_param, math_internal};
 use crate::internal::string_internal::string_internal;
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::builder::Builder;
-use inkwell::types::{BasicType, BasicTypeEnum};
-use inkwell::values::{BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, PointerValue};
+use inkwell::types::{BasicTypeEnum, PointerType};
+use inkwell::values::{BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, IntValue, PointerValue};
 use inkwell::AddressSpace;
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::program::types::FinalizedTypes;
 
 /// Compiles a method with the internal keyword
 pub fn compile_internal<'ctx>(
     type_getter: &CompilerTypeGetter<'ctx>,
     compiler: &CompilerImpl<'ctx>,
-    name: &String,
+    function: &CodelessFinalizedFunction,
     value: FunctionValue<'ctx>,
 ) {
+    let name = &function.data.name;
     let block = compiler.context.append_basic_block(value, "0");
     compiler.builder.position_at_end(block);
     let params = value.get_params();
-    if string_internal(type_getter, compiler, name, &value) || math_internal(type_getter, compiler, name, &value) {
+    let mut id = 1;
+    if string_internal(type_getter, compiler, name, &value) || math_internal(compiler, name, &value) {
         return;
     }
     if name.starts_with("numbers::Cast") {
-        build_cast(value.get_params().first().unwrap(), value.get_type().get_return_type().unwrap(), compiler);
+        build_cast(
+            value.get_params().first().unwrap(),
+            value.get_type().get_return_type().unwrap(),
+            is_signed(&function.arguments.first().unwrap().field.field_type),
+            function.return_type.as_ref().map_or(false, is_signed),
+            compiler,
+        );
     } else if name.starts_with("math::RightShift") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-
         let returning = compiler.builder.build_right_shift(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
+            load_param(compiler, params.first().unwrap()),
+            load_param(compiler, params.get(1).unwrap()),
             true,
             "1",
         );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+        compiler.builder.build_return(Some(&returning));
     } else if name.starts_with("math::LogicRightShift") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-
         let returning = compiler.builder.build_right_shift(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
+            load_param(compiler, params.first().unwrap()),
+            load_param(compiler, params.get(1).unwrap()),
             false,
             "1",
         );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+        compiler.builder.build_return(Some(&returning));
     } else if name.starts_with("math::LeftShift") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-
         let returning = compiler.builder.build_left_shift(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
+            load_param(compiler, params.first().unwrap()),
+            load_param(compiler, params.get(1).unwrap()),
             "1",
         );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+        compiler.builder.build_return(Some(&returning));
     } else if name.starts_with("array::Index") {
         let offset = get_loaded(&compiler.builder, params.get(1).unwrap()).into_int_value();
         let offset = compiler.builder.build_int_add(offset, compiler.context.i64_type().const_int(1, false), "3");
@@ -69,33 +67,106 @@ pub fn compile_internal<'ctx>(
 
         let gep = compiler.builder.build_load(gep, "2");
         compiler.builder.build_return(Some(&gep));
-    } else if name.starts_with("array::Empty") {
-        let size = unsafe {
-            type_getter.compiler.builder.build_gep(
-                value.get_type().get_return_type().unwrap().ptr_type(AddressSpace::default()).const_zero(),
-                &[type_getter.compiler.context.i64_type().const_int(1, false)],
-                "0",
-            )
-        };
+    } else if name.starts_with("array::empty") {
+        let returning = value.get_type().get_return_type().unwrap().into_pointer_type();
+        let malloc = allocate_array(type_getter, returning, compiler.context.i64_type().const_zero(), &mut id);
+        compiler.builder.build_return(Some(&malloc.as_basic_value_enum()));
+    } else if name.starts_with("array::allocate") {
+        let returning = value.get_type().get_return_type().unwrap().into_pointer_type();
+        let length = get_loaded(&compiler.builder, params.first().unwrap()).into_int_value();
+        let malloc = allocate_array(type_getter, returning, length, &mut id);
+        compiler.builder.build_return(Some(&malloc.as_basic_value_enum()));
+    } else if name.starts_with("array::resize") {
+        let array = params.first().unwrap().into_pointer_value();
+        let capacity = get_loaded(&compiler.builder, params.get(1).unwrap()).into_int_value();
 
-        let malloc = compiler
+        let size = array_size(type_getter, array.get_type(), capacity, &mut id);
+        let raw = compiler.builder.build_ptr_to_int(array, compiler.context.i64_type(), &id.to_string());
+        id += 1;
+        let realloc = compiler
             .builder
             .build_call(
-                compiler.module.get_function("malloc").unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-                &[BasicMetadataValueEnum::PointerValue(size)],
-                "1",
+                get_intrinsic("mem::realloc", type_getter),
+                &[BasicMetadataValueEnum::IntValue(raw), BasicMetadataValueEnum::IntValue(size)],
+                &id.to_string(),
             )
             .try_as_basic_value()
             .unwrap_left()
-            .into_pointer_value();
+            .into_int_value();
+        id += 1;
+        let realloc = compiler.builder.build_int_to_ptr(realloc, array.get_type(), &id.to_string());
+        id += 1;
 
-        compiler.builder.build_store(malloc, compiler.context.i64_type().const_zero());
-        compiler.builder.build_return(Some(&malloc.as_basic_value_enum()));
+        store_length(compiler, realloc, capacity, &mut id);
+        compiler.builder.build_return(Some(&realloc.as_basic_value_enum()));
+    } else if name.starts_with("array::set") {
+        let array = params.first().unwrap().into_pointer_value();
+        let index = get_loaded(&compiler.builder, params.get(1).unwrap()).into_int_value();
+        let target = array_offset(compiler, array, index, &mut id);
+
+        // The value is passed by reference unless it's already the array's element type
+        let mut setting = *params.get(2).unwrap();
+        if setting.get_type() != target.get_type().get_element_type().try_into().unwrap() {
+            setting = compiler.builder.build_load(setting.into_pointer_value(), &id.to_string());
+        }
+        compiler.builder.build_store(target, setting);
+        compiler.builder.build_return(None);
+    } else if name.starts_with("array::copy_within") {
+        let array = params.first().unwrap().into_pointer_value();
+        let from = get_loaded(&compiler.builder, params.get(1).unwrap()).into_int_value();
+        let to = get_loaded(&compiler.builder, params.get(2).unwrap()).into_int_value();
+        let length = get_loaded(&compiler.builder, params.get(3).unwrap()).into_int_value();
+
+        let source = array_offset(compiler, array, from, &mut id);
+        let source = compiler.builder.build_ptr_to_int(source, compiler.context.i64_type(), &id.to_string());
+        id += 1;
+        let target = array_offset(compiler, array, to, &mut id);
+        let target = compiler.builder.build_ptr_to_int(target, compiler.context.i64_type(), &id.to_string());
+        id += 1;
+        let size = unsafe { compiler.builder.build_gep(array.get_type().const_zero(), &[length], &id.to_string()) };
+        id += 1;
+        let size = compiler.builder.build_ptr_to_int(size, compiler.context.i64_type(), &id.to_string());
+        id += 1;
+
+        // The ranges can overlap, so this has to be a memmove instead of a memcpy
+        compiler.builder.build_call(
+            get_intrinsic("mem::memmove", type_getter),
+            &[
+                BasicMetadataValueEnum::IntValue(target),
+                BasicMetadataValueEnum::IntValue(source),
+                BasicMetadataValueEnum::IntValue(size),
+            ],
+            &id.to_string(),
+        );
+        compiler.builder.build_return(None);
+    } else if name.starts_with("array::Array") && name.contains("::length") {
+        let length = compiler.builder.build_pointer_cast(
+            params.first().unwrap().into_pointer_value(),
+            compiler.context.i64_type().ptr_type(AddressSpace::default()),
+            "1",
+        );
+        let length = compiler.builder.build_load(length, "2");
+        compiler.builder.build_return(Some(&length));
+    } else if name.starts_with("mem::undefined") {
+        let returning = value.get_type().get_return_type().unwrap();
+        compiler.builder.build_return(Some(&undefined(returning)));
     } else {
         panic!("Unknown internal operation: {}", name)
     }
 }
 
+/// Gets an undefined value of the type, which is never read
+fn undefined(types: BasicTypeEnum<'_>) -> BasicValueEnum<'_> {
+    return match types {
+        BasicTypeEnum::ArrayType(types) => types.get_undef().as_basic_value_enum(),
+        BasicTypeEnum::FloatType(types) => types.get_undef().as_basic_value_enum(),
+        BasicTypeEnum::IntType(types) => types.get_undef().as_basic_value_enum(),
+        BasicTypeEnum::PointerType(types) => types.get_undef().as_basic_value_enum(),
+        BasicTypeEnum::StructType(types) => types.get_undef().as_basic_value_enum(),
+        BasicTypeEnum::VectorType(types) => types.get_undef().as_basic_value_enum(),
+    };
+}
+
 /// Creates a malloc for the type
 pub fn malloc_type<'a>(
     type_getter: &CompilerTypeGetter<'a>,
@@ -110,48 +181,125 @@ pub fn malloc_type<'a>(
         )
     };
     *id += 1;
-    let size = type_getter
-        .compiler
-        .builder
-        .build_bitcast(size, type_getter.compiler.context.i64_type().ptr_type(AddressSpace::default()), &id.to_string())
-        .into_pointer_value();
+    let size = type_getter.compiler.builder.build_ptr_to_int(size, type_getter.compiler.context.i64_type(), &id.to_string());
     *id += 1;
 
+    return call_malloc(type_getter, size, pointer_type.get_type(), id);
+}
+
+/// Mallocs the amount of bytes, returning a pointer of the given type to them
+pub fn call_malloc<'a>(
+    type_getter: &CompilerTypeGetter<'a>,
+    size: IntValue<'a>,
+    pointer_type: PointerType<'a>,
+    id: &mut u64,
+) -> PointerValue<'a> {
     let malloc = type_getter
         .compiler
         .builder
-        .build_call(
-            type_getter
-                .compiler
-                .module
-                .get_function("malloc")
-                .unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-            &[BasicMetadataValueEnum::PointerValue(size)],
-            &id.to_string(),
-        )
+        .build_call(get_intrinsic("mem::malloc", type_getter), &[BasicMetadataValueEnum::IntValue(size)], &id.to_string())
         .try_as_basic_value()
         .unwrap_left()
-        .into_pointer_value();
+        .into_int_value();
+    *id += 1;
+    let malloc = type_getter.compiler.builder.build_int_to_ptr(malloc, pointer_type, &id.to_string());
     *id += 1;
-    let malloc = type_getter.compiler.builder.build_bitcast(
-        malloc.as_basic_value_enum(),
-        pointer_type.as_basic_value_enum().get_type(),
+    return malloc;
+}
+
+/// Mallocs an array with room for the given amount of values, setting its length to that amount
+fn allocate_array<'a>(
+    type_getter: &CompilerTypeGetter<'a>,
+    array_type: PointerType<'a>,
+    length: IntValue<'a>,
+    id: &mut u64,
+) -> PointerValue<'a> {
+    let size = array_size(type_getter, array_type, length, id);
+    let malloc = call_malloc(type_getter, size, array_type, id);
+    store_length(&type_getter.compiler, malloc, length, id);
+    return malloc;
+}
+
+/// Gets the size in bytes of an array holding the given amount of values, including its length
+fn array_size<'a>(
+    type_getter: &CompilerTypeGetter<'a>,
+    array_type: PointerType<'a>,
+    length: IntValue<'a>,
+    id: &mut u64,
+) -> IntValue<'a> {
+    let compiler = &type_getter.compiler;
+    let length = compiler.builder.build_int_add(length, compiler.context.i64_type().const_int(1, false), &id.to_string());
+    *id += 1;
+    let size = unsafe { compiler.builder.build_gep(array_type.const_zero(), &[length], &id.to_string()) };
+    *id += 1;
+    let size = compiler.builder.build_ptr_to_int(size, compiler.context.i64_type(), &id.to_string());
+    *id += 1;
+    return size;
+}
+
+/// Stores the length in the first slot of the array
+fn store_length<'a>(compiler: &CompilerImpl<'a>, array: PointerValue<'a>, length: IntValue<'a>, id: &mut u64) {
+    let target = compiler.builder.build_pointer_cast(
+        array,
+        compiler.context.i64_type().ptr_type(AddressSpace::default()),
         &id.to_string(),
     );
     *id += 1;
-    return malloc.into_pointer_value();
+    compiler.builder.build_store(target, length);
+}
+
+/// Gets a pointer to the value at the index of the array, skipping over the length
+fn array_offset<'a>(
+    compiler: &CompilerImpl<'a>,
+    array: PointerValue<'a>,
+    index: IntValue<'a>,
+    id: &mut u64,
+) -> PointerValue<'a> {
+    let offset = compiler.builder.build_int_add(index, compiler.context.i64_type().const_int(1, false), &id.to_string());
+    *id += 1;
+    let offset = unsafe { compiler.builder.build_in_bounds_gep(array, &[offset], &id.to_string()) };
+    *id += 1;
+    return offset;
 }
 
 /// Loads the type if it's a pointer
-fn get_loaded<'ctx>(compiler: &Builder<'ctx>, value: &BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
+pub fn get_loaded<'ctx>(compiler: &Builder<'ctx>, value: &BasicValueEnum<'ctx>) -> BasicValueEnum<'ctx> {
     if value.is_pointer_value() {
         return compiler.build_load(value.into_pointer_value(), "0");
     }
     return *value;
 }
 
-/// Casts a number from one type to another
-fn build_cast(first: &BasicValueEnum, _second: BasicTypeEnum, compiler: &CompilerImpl) {
-    //TODO float casting
-    compiler.builder.build_return(Some(&compiler.builder.build_load(first.into_pointer_value(), "1")));
+/// Casts a number from one type to another, using the signs of the types to extend or convert them correctly
+fn build_cast(first: &BasicValueEnum, second: BasicTypeEnum, from_signed: bool, to_signed: bool, compiler: &CompilerImpl) {
+    let returning = match (get_loaded(&compiler.builder, first), second) {
+        (BasicValueEnum::IntValue(int), BasicTypeEnum::IntType(target)) => {
+            compiler.builder.build_int_cast_sign_flag(int, target, from_signed, "1").as_basic_value_enum()
+        }
+        (BasicValueEnum::IntValue(int), BasicTypeEnum::FloatType(target)) => if from_signed {
+            compiler.builder.build_signed_int_to_float(int, target, "1")
+        } else {
+            compiler.builder.build_unsigned_int_to_float(int, target, "1")
+        }
+        .as_basic_value_enum(),
+        (BasicValueEnum::FloatValue(float), BasicTypeEnum::IntType(target)) => if to_signed {
+            compiler.builder.build_float_to_signed_int(float, target, "1")
+        } else {
+            compiler.builder.build_float_to_unsigned_int(float, target, "1")
+        }
+        .as_basic_value_enum(),
+        (BasicValueEnum::FloatValue(float), BasicTypeEnum::FloatType(target)) => {
+            compiler.builder.build_float_cast(float, target, "1").as_basic_value_enum()
+        }
+        (value, _) => value,
+    };
+    compiler.builder.build_return(Some(&returning));
+}
+
+/// Checks if the number type is signed
+fn is_signed(types: &FinalizedTypes) -> bool {
+    return match types.name_safe() {
+        Some(name) => matches!(name.as_str(), "i64" | "i32" | "i16" | "i8" | "f64" | "f32"),
+        None => false,
+    };
 }
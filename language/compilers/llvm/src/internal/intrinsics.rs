@@ -1,51 +1,132 @@
+use std::sync::Arc;
+
+use crate::internal::instructions::malloc_type;
+use crate::internal::structs::get_internal_struct;
 use crate::type_getter::CompilerTypeGetter;
-use inkwell::types::BasicMetadataTypeEnum;
-use inkwell::values::FunctionValue;
+use crate::util::create_function_value;
+use inkwell::module::Linkage;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum};
+use inkwell::values::{BasicMetadataValueEnum, FunctionValue};
 use inkwell::AddressSpace;
+use syntax::program::function::CodelessFinalizedFunction;
+use syntax::program::types::FinalizedTypes;
+use syntax::Attribute;
+
+/// Compiles a method with the attribute #[llvm_intrinsic], declaring the C function or LLVM intrinsic it calls.
+/// The signature comes from the Raven declaration, and the attribute can name the symbol: #[llvm_intrinsic(llvm.sqrt.f64)]
+pub fn compile_llvm_intrinsics<'ctx>(
+    function: &Arc<CodelessFinalizedFunction>,
+    type_getter: &CompilerTypeGetter<'ctx>,
+) -> FunctionValue<'ctx> {
+    let name = match Attribute::find_attribute("llvm_intrinsic", &function.data.attributes) {
+        Some(Attribute::String(_, name)) => name.as_str(),
+        _ => function.data.name.split("::").last().unwrap(),
+    };
+    return declare_c_function(function, name, type_getter);
+}
+
+/// Gets an intrinsic declared in Raven by its full name (like mem::malloc), for code generated by the compiler.
+pub fn get_intrinsic<'ctx>(name: &str, type_getter: &CompilerTypeGetter<'ctx>) -> FunctionValue<'ctx> {
+    let function = {
+        let syntax = type_getter.syntax.lock().unwrap();
+        match syntax.functions.types.get(name).and_then(|data| syntax.functions.data.get(data)) {
+            Some(found) => found.clone(),
+            None => panic!("Tried to compile unknown LLVM intrinsic {}", name),
+        }
+    };
+    return compile_llvm_intrinsics(&function, type_getter);
+}
+
+/// Declares the C function with the given symbol, mapping the Raven function's types to C types.
+/// If the symbol was already declared, the existing declaration is used.
+fn declare_c_function<'ctx>(
+    function: &Arc<CodelessFinalizedFunction>,
+    name: &str,
+    type_getter: &CompilerTypeGetter<'ctx>,
+) -> FunctionValue<'ctx> {
+    if let Some(found) = type_getter.compiler.module.get_function(name) {
+        return found;
+    }
+
+    let mut params = Vec::default();
+    for argument in &function.arguments {
+        params.push(BasicMetadataTypeEnum::from(c_type(&argument.field.field_type, type_getter)));
+    }
+    let vararg = Attribute::find_attribute("vararg", &function.data.attributes).is_some();
+    let function_type = match &function.return_type {
+        Some(returning) => c_type(returning, type_getter).fn_type(params.as_slice(), vararg),
+        None => type_getter.compiler.context.void_type().fn_type(params.as_slice(), vararg),
+    };
+    return type_getter.compiler.module.add_function(name, function_type, Some(Linkage::External));
+}
+
+/// Compiles an extern function, which calls a C function of the same name linked from an external binary.
+/// Raven passes arguments as pointers, so this creates a wrapper that passes them the way C expects:
+/// primitives and chars by value, everything else (like strings) as a byte pointer.
+pub fn compile_extern<'ctx>(
+    function: &Arc<CodelessFinalizedFunction>,
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+) -> FunctionValue<'ctx> {
+    let value = create_function_value(function, type_getter, None, false);
+    let context = type_getter.compiler.context;
+    let byte_pointer = context.i8_type().ptr_type(AddressSpace::default());
+    let calling = declare_c_function(function, function.data.name.split("::").last().unwrap(), type_getter);
+
+    let block = context.append_basic_block(value, "0");
+    type_getter.compiler.builder.position_at_end(block);
+    let mut id = 1;
 
-/// Compiles a method with the attribute #[llvm_intrinsic]
-pub fn compile_llvm_intrinsics<'ctx>(name: &str, type_getter: &CompilerTypeGetter<'ctx>) -> FunctionValue<'ctx> {
-    if let Some(func) = type_getter.compiler.module.get_function(&name) {
-        return func;
+    let mut arguments = Vec::default();
+    for (i, param) in calling.get_type().get_param_types().into_iter().enumerate() {
+        let mut argument = value.get_nth_param(i as u32).unwrap();
+        if param.is_pointer_type() {
+            argument = type_getter.compiler.builder.build_bitcast(argument, param, &id.to_string());
+        } else if argument.is_pointer_value() {
+            // Chars are empty structs to Raven, so the pointer has to be cast to the value type first
+            let pointer = type_getter.compiler.builder.build_bitcast(
+                argument,
+                param.ptr_type(AddressSpace::default()),
+                &id.to_string(),
+            );
+            id += 1;
+            argument = type_getter.compiler.builder.build_load(pointer.into_pointer_value(), &id.to_string());
+        }
+        id += 1;
+        arguments.push(BasicMetadataValueEnum::from(argument));
     }
-    return type_getter.compiler.module.add_function(
-        &name,
-        match name {
-            "printf" => type_getter.compiler.context.i32_type().fn_type(
-                &[BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()))],
-                true,
-            ),
-            "malloc" => type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()).fn_type(
-                &[BasicMetadataTypeEnum::from(type_getter.compiler.context.i64_type().ptr_type(AddressSpace::default()))],
-                false,
-            ),
-            "strcat" => type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()).fn_type(
-                &[
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                ],
-                false,
-            ),
-            "strcpy" => type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()).fn_type(
-                &[
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                ],
-                false,
-            ),
-            "strlen" => type_getter.compiler.context.i64_type().fn_type(
-                &[BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()))],
-                false,
-            ),
-            "strcmp" => type_getter.compiler.context.i64_type().fn_type(
-                &[
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                    BasicMetadataTypeEnum::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default())),
-                ],
-                false,
-            ),
-            _ => panic!("Tried to compile unknown LLVM intrinsic {}", name),
-        },
-        None,
-    );
+
+    let returned = type_getter.compiler.builder.build_call(calling, arguments.as_slice(), &id.to_string());
+    id += 1;
+    let returned = match returned.try_as_basic_value().left() {
+        Some(returned) => returned,
+        None => {
+            type_getter.compiler.builder.build_return(None);
+            return value;
+        }
+    };
+
+    let returning = value.get_type().get_return_type().unwrap();
+    let returned = if returning == returned.get_type() {
+        returned
+    } else if returned.is_pointer_value() {
+        type_getter.compiler.builder.build_bitcast(returned, returning, &id.to_string())
+    } else {
+        // Chars are returned as a pointer, so the value is copied to the heap
+        let pointer = malloc_type(type_getter, byte_pointer.const_zero(), &mut id);
+        type_getter.compiler.builder.build_store(pointer, returned);
+        type_getter.compiler.builder.build_bitcast(pointer, returning, &id.to_string())
+    };
+    type_getter.compiler.builder.build_return(Some(&returned));
+    return value;
+}
+
+/// Gets the C type of a Raven type. Primitives and chars are passed by value, everything else is a byte pointer.
+fn c_type<'ctx>(types: &FinalizedTypes, type_getter: &CompilerTypeGetter<'ctx>) -> BasicTypeEnum<'ctx> {
+    let context = type_getter.compiler.context;
+    return match types.name_safe() {
+        Some(name) if name == "char" => context.i8_type().as_basic_type_enum(),
+        Some(name) => get_internal_struct(context, &name)
+            .unwrap_or_else(|| context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum()),
+        None => context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum(),
+    };
 }
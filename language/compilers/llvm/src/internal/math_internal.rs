@@ -1,232 +1,186 @@
 use crate::compiler::CompilerImpl;
-use crate::internal::instructions::malloc_type;
-use crate::type_getter::CompilerTypeGetter;
-use inkwell::values::{BasicValueEnum, FunctionValue};
-use inkwell::{AddressSpace, IntPredicate};
+use crate::internal::instructions::get_loaded;
+use inkwell::types::{AnyTypeEnum, BasicTypeEnum};
+use inkwell::values::{BasicValue, BasicValueEnum, FloatValue, FunctionValue, IntValue};
+use inkwell::{FloatPredicate, IntPredicate};
 
-/// Compiles internal math functions
-pub fn math_internal<'ctx>(
-    type_getter: &CompilerTypeGetter<'ctx>,
-    compiler: &CompilerImpl<'ctx>,
-    name: &String,
-    value: &FunctionValue<'ctx>,
-) -> bool {
+/// Compiles internal math functions.
+/// Primitives are returned by value, so the caller can keep them on the stack instead of allocating them.
+pub fn math_internal<'ctx>(compiler: &CompilerImpl<'ctx>, name: &String, value: &FunctionValue<'ctx>) -> bool {
     let params = value.get_params();
-    if name.starts_with("math::Add") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
+    // Only math operations are float operations, casts from floats are built with the other instructions
+    if name.starts_with("math::") && params.iter().any(is_float) {
+        return float_math_internal(compiler, name, &params);
+    }
 
-        let returning = compiler.builder.build_int_add(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+    let returning = if name.starts_with("math::Add") {
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_int_add(first, second, "1")
     } else if name.starts_with("math::Subtract") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-        let returning = compiler.builder.build_int_sub(
-            compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_int_sub(first, second, "1")
     } else if name.starts_with("math::Multiply") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-        let returning = compiler.builder.build_int_mul(
-            compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_int_mul(first, second, "1")
     } else if name.starts_with("math::Divide") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-        let returning = if name.ends_with("u64") {
-            compiler.builder.build_int_unsigned_div(
-                compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-                compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-                "1",
-            )
+        let (first, second) = load_params(compiler, name, &params);
+        if is_unsigned(name) {
+            compiler.builder.build_int_unsigned_div(first, second, "1")
         } else {
-            compiler.builder.build_int_signed_div(
-                compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-                compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-                "1",
-            )
-        };
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+            compiler.builder.build_int_signed_div(first, second, "1")
+        }
     } else if name.starts_with("math::Remainder") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-        let returning = if name.ends_with("u64") {
-            compiler.builder.build_int_unsigned_rem(
-                compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-                compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-                "1",
-            )
+        let (first, second) = load_params(compiler, name, &params);
+        if is_unsigned(name) {
+            compiler.builder.build_int_unsigned_rem(first, second, "1")
         } else {
-            compiler.builder.build_int_signed_rem(
-                compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-                compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-                "1",
-            )
-        };
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+            compiler.builder.build_int_signed_rem(first, second, "1")
+        }
     } else if name.starts_with("math::Equal") {
-        compile_relational_op(IntPredicate::EQ, compiler, &params, type_getter);
+        compile_relational_op(IntPredicate::EQ, compiler, name, &params)
     } else if name.starts_with("math::GreaterThan") {
         if is_unsigned(name) {
-            compile_relational_op(IntPredicate::UGT, compiler, &params, type_getter)
+            compile_relational_op(IntPredicate::UGT, compiler, name, &params)
         } else {
-            compile_relational_op(IntPredicate::SGT, compiler, &params, type_getter)
-        };
+            compile_relational_op(IntPredicate::SGT, compiler, name, &params)
+        }
     } else if name.starts_with("math::LessThan") {
         if is_unsigned(name) {
-            compile_relational_op(IntPredicate::ULT, compiler, &params, type_getter)
+            compile_relational_op(IntPredicate::ULT, compiler, name, &params)
         } else {
-            compile_relational_op(IntPredicate::SLT, compiler, &params, type_getter)
-        };
-    } else if name.starts_with("math::Not") {
-        let malloc = malloc_type(
-            type_getter,
-            type_getter.compiler.context.bool_type().ptr_type(AddressSpace::default()).const_zero(),
-            &mut 0,
-        );
-        let returning = compiler
-            .builder
-            .build_not(compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "1").into_int_value(), "0");
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::BitInvert") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-        let returning = compiler
-            .builder
-            .build_not(compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "1").into_int_value(), "0");
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::BitXOR") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
-
-        let returning = compiler.builder.build_xor(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::BitOr") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
+            compile_relational_op(IntPredicate::SLT, compiler, name, &params)
+        }
+    } else if name.starts_with("math::Not") || name.starts_with("math::BitInvert") {
+        compiler.builder.build_not(load_param(compiler, params.first().unwrap()), "0")
+    } else if name.starts_with("math::BitXOR") || name.starts_with("math::XOR") {
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_xor(first, second, "1")
+    } else if name.starts_with("math::BitOr") || name.starts_with("math::Or") {
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_or(first, second, "1")
+    } else if name.starts_with("math::BitAnd") || name.starts_with("math::And") {
+        let (first, second) = load_params(compiler, name, &params);
+        compiler.builder.build_and(first, second, "1")
+    } else {
+        return false;
+    };
+    // Numbers of different sizes are worked on at the bigger size, so the result is cut back to the returned size
+    let returning = match value.get_type().get_return_type() {
+        Some(BasicTypeEnum::IntType(int_type)) if int_type != returning.get_type() => {
+            compiler.builder.build_int_cast_sign_flag(returning, int_type, !is_unsigned(name), "2")
+        }
+        _ => returning,
+    };
+    compiler.builder.build_return(Some(&returning));
+    return true;
+}
 
-        let returning = compiler.builder.build_or(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::BitAnd") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
+/// Compiles internal math functions on floats, converting any integer parameters to floats first.
+fn float_math_internal<'ctx>(compiler: &CompilerImpl<'ctx>, name: &String, params: &Vec<BasicValueEnum<'ctx>>) -> bool {
+    let (first, second) = load_float_params(compiler, name, params);
+    let returning = if name.starts_with("math::Add") {
+        compiler.builder.build_float_add(first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::Subtract") {
+        compiler.builder.build_float_sub(first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::Multiply") {
+        compiler.builder.build_float_mul(first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::Divide") {
+        compiler.builder.build_float_div(first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::Remainder") {
+        compiler.builder.build_float_rem(first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::Equal") {
+        compiler.builder.build_float_compare(FloatPredicate::OEQ, first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::GreaterThan") {
+        compiler.builder.build_float_compare(FloatPredicate::OGT, first, second, "1").as_basic_value_enum()
+    } else if name.starts_with("math::LessThan") {
+        compiler.builder.build_float_compare(FloatPredicate::OLT, first, second, "1").as_basic_value_enum()
+    } else {
+        return false;
+    };
+    compiler.builder.build_return(Some(&returning));
+    return true;
+}
 
-        let returning = compiler.builder.build_and(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::BitXOR") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(type_getter, pointer_type.get_type().const_zero(), &mut 0);
+/// Checks if a parameter is a float or a pointer to one
+fn is_float(param: &BasicValueEnum) -> bool {
+    return match param {
+        BasicValueEnum::FloatValue(_) => true,
+        BasicValueEnum::PointerValue(pointer) => {
+            matches!(pointer.get_type().get_element_type(), AnyTypeEnum::FloatType(_))
+        }
+        _ => false,
+    };
+}
 
-        let returning = compiler.builder.build_xor(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::And") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(
-            type_getter,
-            type_getter.compiler.context.bool_type().ptr_type(AddressSpace::default()).const_zero(),
-            &mut 0,
-        );
+/// Loads the first two parameters of an internal function as floats, converting both to the widest float type
+fn load_float_params<'ctx>(
+    compiler: &CompilerImpl<'ctx>,
+    name: &String,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) -> (FloatValue<'ctx>, FloatValue<'ctx>) {
+    let first = get_loaded(&compiler.builder, params.first().unwrap());
+    let second = get_loaded(&compiler.builder, params.get(1).unwrap());
+    let float_type = match (first, second) {
+        (BasicValueEnum::FloatValue(first), BasicValueEnum::FloatValue(second)) => {
+            if first.get_type() == compiler.context.f64_type() || second.get_type() == compiler.context.f64_type() {
+                compiler.context.f64_type()
+            } else {
+                compiler.context.f32_type()
+            }
+        }
+        (BasicValueEnum::FloatValue(float), _) | (_, BasicValueEnum::FloatValue(float)) => float.get_type(),
+        _ => unreachable!(),
+    };
 
-        let returning = compiler.builder.build_and(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::XOR") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(
-            type_getter,
-            type_getter.compiler.context.bool_type().ptr_type(AddressSpace::default()).const_zero(),
-            &mut 0,
-        );
+    let convert = |value: BasicValueEnum<'ctx>| match value {
+        BasicValueEnum::FloatValue(float) => compiler.builder.build_float_cast(float, float_type, "2"),
+        BasicValueEnum::IntValue(int) => {
+            if is_unsigned(name) {
+                compiler.builder.build_unsigned_int_to_float(int, float_type, "2")
+            } else {
+                compiler.builder.build_signed_int_to_float(int, float_type, "2")
+            }
+        }
+        _ => unreachable!(),
+    };
+    return (convert(first), convert(second));
+}
 
-        let returning = compiler.builder.build_xor(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
-    } else if name.starts_with("math::Or") {
-        let pointer_type = params.first().unwrap().into_pointer_value();
-        let malloc = malloc_type(
-            type_getter,
-            type_getter.compiler.context.bool_type().ptr_type(AddressSpace::default()).const_zero(),
-            &mut 0,
-        );
+/// Compiles relational operators
+fn compile_relational_op<'ctx>(
+    op: IntPredicate,
+    compiler: &CompilerImpl<'ctx>,
+    name: &String,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) -> IntValue<'ctx> {
+    let (first, second) = load_params(compiler, name, params);
+    return compiler.builder.build_int_compare(op, first, second, "1");
+}
 
-        let returning = compiler.builder.build_or(
-            compiler.builder.build_load(pointer_type, "2").into_int_value(),
-            compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-            "1",
-        );
-        compiler.builder.build_store(malloc, returning);
-        compiler.builder.build_return(Some(&malloc));
+/// Loads the first two parameters of an internal function, extending the smaller one to the bigger one's size
+fn load_params<'ctx>(
+    compiler: &CompilerImpl<'ctx>,
+    name: &String,
+    params: &Vec<BasicValueEnum<'ctx>>,
+) -> (IntValue<'ctx>, IntValue<'ctx>) {
+    let first = load_param(compiler, params.first().unwrap());
+    let second = load_param(compiler, params.get(1).unwrap());
+    let int_type = if first.get_type().get_bit_width() >= second.get_type().get_bit_width() {
+        first.get_type()
     } else {
-        return false;
-    }
-    return true;
+        second.get_type()
+    };
+    let extend = |value: IntValue<'ctx>| compiler.builder.build_int_cast_sign_flag(value, int_type, !is_unsigned(name), "2");
+    return (extend(first), extend(second));
 }
 
-/// Compiles relational operators
-fn compile_relational_op(
-    op: IntPredicate,
-    compiler: &CompilerImpl,
-    params: &Vec<BasicValueEnum>,
-    type_getter: &CompilerTypeGetter,
-) {
-    let malloc = malloc_type(
-        type_getter,
-        type_getter.compiler.context.bool_type().ptr_type(AddressSpace::default()).const_zero(),
-        &mut 0,
-    );
-    let returning = compiler.builder.build_int_compare(
-        op,
-        compiler.builder.build_load(params.first().unwrap().into_pointer_value(), "2").into_int_value(),
-        compiler.builder.build_load(params.get(1).unwrap().into_pointer_value(), "3").into_int_value(),
-        "1",
-    );
-    compiler.builder.build_store(malloc, returning);
-    compiler.builder.build_return(Some(&malloc));
+/// Loads a parameter if it's a pointer
+pub fn load_param<'ctx>(compiler: &CompilerImpl<'ctx>, param: &BasicValueEnum<'ctx>) -> IntValue<'ctx> {
+    return if param.is_pointer_value() {
+        compiler.builder.build_load(param.into_pointer_value(), "2").into_int_value()
+    } else {
+        param.into_int_value()
+    };
 }
 
 /// Returns true if a number is unsigned
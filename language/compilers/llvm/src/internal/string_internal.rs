@@ -1,9 +1,9 @@
 use crate::compiler::CompilerImpl;
-use crate::internal::instructions::malloc_type;
-use crate::internal::intrinsics::compile_llvm_intrinsics;
+use crate::internal::instructions::{call_malloc, malloc_type};
+use crate::internal::intrinsics::get_intrinsic;
 use crate::type_getter::CompilerTypeGetter;
-use inkwell::values::{BasicMetadataValueEnum, BasicValue, FunctionValue};
-use inkwell::AddressSpace;
+use inkwell::values::{BasicMetadataValueEnum, BasicValue, FunctionValue, PointerValue};
+use inkwell::{AddressSpace, IntPredicate};
 
 /// Compiles internal string methods
 pub fn string_internal<'ctx>(
@@ -34,11 +34,7 @@ pub fn string_internal<'ctx>(
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strlen")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strlen", type_getter)),
+                get_intrinsic("string::strlen", type_getter),
                 &[BasicMetadataValueEnum::PointerValue(value.get_params().first().unwrap().into_pointer_value())],
                 "0",
             )
@@ -49,11 +45,7 @@ pub fn string_internal<'ctx>(
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strlen")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strlen", type_getter)),
+                get_intrinsic("string::strlen", type_getter),
                 &[BasicMetadataValueEnum::PointerValue(value.get_params().get(1).unwrap().into_pointer_value())],
                 "2",
             )
@@ -61,30 +53,23 @@ pub fn string_internal<'ctx>(
             .unwrap_left()
             .into_int_value();
         let total = type_getter.compiler.builder.build_int_add(length, second_length, "4");
-        let malloc = type_getter
-            .compiler
-            .builder
-            .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("malloc")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-                &[BasicMetadataValueEnum::IntValue(total)],
-                "5",
-            )
-            .try_as_basic_value()
-            .unwrap_left()
-            .into_pointer_value();
+        // Leaves space for the null terminator
+        let total = type_getter.compiler.builder.build_int_add(
+            total,
+            type_getter.compiler.context.i64_type().const_int(1, false),
+            "4",
+        );
+        let malloc = call_malloc(
+            type_getter,
+            total,
+            type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            &mut 5,
+        );
         type_getter
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strcpy")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strcpy", type_getter)),
+                get_intrinsic("string::strcpy", type_getter),
                 &[
                     BasicMetadataValueEnum::PointerValue(malloc),
                     BasicMetadataValueEnum::PointerValue(value.get_params().first().unwrap().into_pointer_value()),
@@ -98,11 +83,7 @@ pub fn string_internal<'ctx>(
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strcat")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strcat", type_getter)),
+                get_intrinsic("string::strcat", type_getter),
                 &[
                     BasicMetadataValueEnum::PointerValue(malloc),
                     BasicMetadataValueEnum::PointerValue(value.get_params().get(1).unwrap().into_pointer_value()),
@@ -118,11 +99,7 @@ pub fn string_internal<'ctx>(
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strlen")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strlen", type_getter)),
+                get_intrinsic("string::strlen", type_getter),
                 &[BasicMetadataValueEnum::PointerValue(value.get_params().first().unwrap().into_pointer_value())],
                 "0",
             )
@@ -134,30 +111,17 @@ pub fn string_internal<'ctx>(
             type_getter.compiler.context.i64_type().const_int(1, false),
             "4",
         );
-        let malloc = type_getter
-            .compiler
-            .builder
-            .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("malloc")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("malloc", type_getter)),
-                &[BasicMetadataValueEnum::IntValue(total)],
-                "5",
-            )
-            .try_as_basic_value()
-            .unwrap_left()
-            .into_pointer_value();
+        let malloc = call_malloc(
+            type_getter,
+            total,
+            type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            &mut 5,
+        );
         type_getter
             .compiler
             .builder
             .build_call(
-                type_getter
-                    .compiler
-                    .module
-                    .get_function("strcpy")
-                    .unwrap_or_else(|| compile_llvm_intrinsics("strcpy", type_getter)),
+                get_intrinsic("string::strcpy", type_getter),
                 &[
                     BasicMetadataValueEnum::PointerValue(malloc),
                     BasicMetadataValueEnum::PointerValue(value.get_params().first().unwrap().into_pointer_value()),
@@ -181,8 +145,53 @@ pub fn string_internal<'ctx>(
             type_getter.compiler.context.i8_type().const_zero(),
         );
         type_getter.compiler.builder.build_return(Some(&malloc.as_basic_value_enum()));
+    } else if name.starts_with("string::Hash_char::hash") {
+        let pointer = compiler.builder.build_pointer_cast(
+            params.first().unwrap().into_pointer_value(),
+            compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            "1",
+        );
+        let char = compiler.builder.build_load(pointer, "2").into_int_value();
+        let returning = compiler.builder.build_int_z_extend(char, compiler.context.i64_type(), "3");
+        compiler.builder.build_return(Some(&returning));
+    } else if name.starts_with("string::Hash_str::hash") {
+        hash_string(compiler, value, params.first().unwrap().into_pointer_value());
     } else {
         return false;
     }
     return true;
 }
+
+/// Hashes every char of the string with FNV-1a, stopping at the null terminator
+fn hash_string<'ctx>(compiler: &CompilerImpl<'ctx>, value: &FunctionValue<'ctx>, string: PointerValue<'ctx>) {
+    let i64_type = compiler.context.i64_type();
+    let entry = compiler.builder.get_insert_block().unwrap();
+    let string =
+        compiler.builder.build_pointer_cast(string, compiler.context.i8_type().ptr_type(AddressSpace::default()), "1");
+    let looping = compiler.context.append_basic_block(*value, "2");
+    let body = compiler.context.append_basic_block(*value, "3");
+    let end = compiler.context.append_basic_block(*value, "4");
+    compiler.builder.build_unconditional_branch(looping);
+
+    compiler.builder.position_at_end(looping);
+    let hash = compiler.builder.build_phi(i64_type, "5");
+    let index = compiler.builder.build_phi(i64_type, "6");
+    let char = unsafe { compiler.builder.build_in_bounds_gep(string, &[index.as_basic_value().into_int_value()], "7") };
+    let char = compiler.builder.build_load(char, "8").into_int_value();
+    let finished = compiler.builder.build_int_compare(IntPredicate::EQ, char, char.get_type().const_zero(), "9");
+    compiler.builder.build_conditional_branch(finished, end, body);
+
+    compiler.builder.position_at_end(body);
+    let char = compiler.builder.build_int_z_extend(char, i64_type, "10");
+    let next_hash = compiler.builder.build_xor(hash.as_basic_value().into_int_value(), char, "11");
+    let next_hash = compiler.builder.build_int_mul(next_hash, i64_type.const_int(1099511628211, false), "12");
+    let next_index =
+        compiler.builder.build_int_add(index.as_basic_value().into_int_value(), i64_type.const_int(1, false), "13");
+    compiler.builder.build_unconditional_branch(looping);
+
+    hash.add_incoming(&[(&i64_type.const_int(14695981039346656037, false), entry), (&next_hash, body)]);
+    index.add_incoming(&[(&i64_type.const_zero(), entry), (&next_index, body)]);
+
+    compiler.builder.position_at_end(end);
+    compiler.builder.build_return(Some(&hash.as_basic_value()));
+}
@@ -15,16 +15,19 @@ use syntax::program::r#struct::FinalizedStruct;
 use syntax::program::syntax::{Compiler, Syntax};
 
 use crate::compiler::CompilerImpl;
+use crate::linker::{build_executable, create_entry_point, emit_module, load_libraries};
 use crate::type_getter::CompilerTypeGetter;
 
 /// The compiler that compiles a syntax
 pub mod compiler;
+/// Emits DWARF debug info from the spans of the compiled code
+pub mod debug;
 /// Compiles a function to LLVM
 pub mod function_compiler;
 /// Implementations of internal types
 pub mod internal;
-/// A future that waits on main to finish verifying
-pub mod main_future;
+/// Writes compiled code to object files and links them into executables
+pub mod linker;
 /// Handles translating Raven types into LLVM
 pub mod type_getter;
 /// Utility functions used in other files
@@ -60,17 +63,29 @@ impl LLVMCompiler {
 #[async_trait]
 impl<T> Compiler<T> for LLVMCompiler {
     /// Compiles a syntax, with a receiver that is used to wait for verification before running
-    async fn compile(&self, mut receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<T> {
+    async fn compile(&self, mut receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Result<Option<T>, String> {
         if let Some(main) = CompilerImpl::get_main(&self.arguments, syntax).await {
             if receiver.recv().await.is_some() {
-                let mut binding = CompilerTypeGetter::new(Rc::new(CompilerImpl::new(&self.context)), syntax.clone());
+                let compiler = CompilerImpl::new(&self.context, &self.arguments, &syntax.lock().unwrap());
+                let mut binding = CompilerTypeGetter::new(Rc::new(compiler), syntax.clone());
                 CompilerImpl::compile(main, &mut binding, &self.compiling, &self.struct_compiling);
-                return binding.get_target(&self.arguments.target).map(|inner| unsafe { inner.call() });
+                binding.compiler.optimize(self.arguments.optimization);
+
+                // Build an executable instead of running the target if there is an output
+                if let Some(output) = &self.arguments.output {
+                    create_entry_point(&binding.compiler, &self.arguments.target)?;
+                    emit_module(&binding.compiler, &self.arguments)?;
+                    build_executable(&binding.compiler, &self.arguments.temp_folder, output, self.arguments.optimization)?;
+                    return Ok(None);
+                }
+                emit_module(&binding.compiler, &self.arguments)?;
+                load_libraries(&binding.compiler);
+                return Ok(binding.get_target(&self.arguments.target).map(|inner| unsafe { inner.call() }));
             }
         } else {
             receiver.recv().await;
         }
 
-        return None;
+        return Ok(None);
     }
 }
@@ -0,0 +1,134 @@
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use data::{CompilerArguments, Emit, Optimization};
+use inkwell::support::load_library_permanently;
+use inkwell::targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine};
+
+use crate::compiler::{optimization_level, CompilerImpl};
+
+/// Creates a C main function that calls the target, so the program can be run as an executable
+pub fn create_entry_point(compiler: &CompilerImpl, target: &str) -> Result<(), String> {
+    let calling = match compiler.module.get_function(target) {
+        Some(found) => found,
+        None => return Err(format!("Failed to find the target {} to build!", target)),
+    };
+
+    let main = compiler.module.add_function("main", compiler.context.i32_type().fn_type(&[], false), None);
+    let block = compiler.context.append_basic_block(main, "0");
+    compiler.builder.position_at_end(block);
+    compiler.builder.build_call(calling, &[], "1");
+    compiler.builder.build_return(Some(&compiler.context.i32_type().const_zero()));
+    return Ok(());
+}
+
+/// Creates a target machine for the host, and sets the module to target it
+fn host_machine(compiler: &CompilerImpl, optimization: Optimization) -> Result<TargetMachine, String> {
+    Target::initialize_native(&InitializationConfig::default())
+        .map_err(|error| format!("Failed to initialize the native target: {}", error))?;
+
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).map_err(|error| format!("Unknown target {}: {}", triple, error))?;
+    let machine = target
+        .create_target_machine(
+            &triple,
+            TargetMachine::get_host_cpu_name().to_str().unwrap(),
+            TargetMachine::get_host_cpu_features().to_str().unwrap(),
+            optimization_level(optimization),
+            // Position independent code is required by most modern linkers
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| "Failed to create a target machine for the host!".to_string())?;
+
+    compiler.module.set_triple(&triple);
+    compiler.module.set_data_layout(&machine.get_target_data().get_data_layout());
+    return Ok(machine);
+}
+
+/// Writes the LLVM module and native assembly to the temp folder, if they're being emitted
+pub fn emit_module(compiler: &CompilerImpl, arguments: &CompilerArguments) -> Result<(), String> {
+    if arguments.emit.is_empty() {
+        return Ok(());
+    }
+    create_folder(&arguments.temp_folder)?;
+
+    if arguments.emit.contains(&Emit::LlvmIr) {
+        if let Err(error) = compiler.module.print_to_file(arguments.temp_folder.join("output.ll")) {
+            return Err(format!("Failed to write output.ll: {}", error));
+        }
+    }
+    if arguments.emit.contains(&Emit::Bitcode)
+        && !compiler.module.write_bitcode_to_path(&arguments.temp_folder.join("output.bc"))
+    {
+        return Err("Failed to write output.bc!".to_string());
+    }
+    if arguments.emit.contains(&Emit::Assembly) {
+        let machine = host_machine(compiler, arguments.optimization)?;
+        if let Err(error) =
+            machine.write_to_file(&compiler.module, FileType::Assembly, &arguments.temp_folder.join("output.s"))
+        {
+            return Err(format!("Failed to write output.s: {}", error));
+        }
+    }
+    return Ok(());
+}
+
+/// Writes the module to an object file for the host in the temp folder, then links it into an executable at the output
+pub fn build_executable(
+    compiler: &CompilerImpl,
+    temp_folder: &Path,
+    output: &Path,
+    optimization: Optimization,
+) -> Result<(), String> {
+    let machine = host_machine(compiler, optimization)?;
+
+    create_folder(temp_folder)?;
+    let object = temp_folder.join("output.o");
+    if let Err(error) = machine.write_to_file(&compiler.module, FileType::Object, &object) {
+        return Err(format!("Failed to write object file: {}", error));
+    }
+
+    if let Some(parent) = output.parent() {
+        create_folder(parent)?;
+    }
+
+    return link(&object, output, &compiler.libraries.borrow());
+}
+
+/// Creates the folder and any missing parents of it
+fn create_folder(folder: &Path) -> Result<(), String> {
+    return fs::create_dir_all(folder).map_err(|error| format!("Failed to create {}: {}", folder.display(), error));
+}
+
+/// Loads the libraries the extern functions are linked from, so the JIT can find their symbols.
+pub fn load_libraries(compiler: &CompilerImpl) {
+    for library in compiler.libraries.borrow().iter() {
+        // Failing to load is fine, libraries like libc are already in the process
+        // and symbols that can't be found anywhere are reported by the JIT.
+        load_library_permanently(&format!("{}{}{}", DLL_PREFIX, library, DLL_SUFFIX));
+    }
+}
+
+/// Links the object file and libraries into an executable using the system's C compiler, which also links libc.
+/// The compiler can be changed with the CC environmental variable.
+fn link(object: &Path, output: &Path, libraries: &[String]) -> Result<(), String> {
+    let linker = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let mut command = Command::new(&linker);
+    command.arg(object).arg("-o").arg(output);
+    // Libraries come after the object so the linker knows which of their symbols are needed
+    for library in libraries {
+        command.arg(format!("-l{}", library));
+    }
+    let status = match command.status() {
+        Ok(status) => status,
+        Err(error) => return Err(format!("Failed to run linker {}: {}", linker, error)),
+    };
+
+    if !status.success() {
+        return Err(format!("Linker {} failed with {}", linker, status));
+    }
+    return Ok(());
+}
@@ -9,7 +9,9 @@ use crate::compiler::CompilerImpl;
 use crate::function_compiler::{instance_function, instance_types};
 use crate::internal::structs::get_internal_struct;
 use crate::vtable_manager::VTableManager;
+use data::tokens::Span;
 use inkwell::basic_block::BasicBlock;
+use inkwell::debug_info::DISubprogram;
 use inkwell::execution_engine::JitFunction;
 use inkwell::types::{BasicType, BasicTypeEnum};
 use inkwell::values::{BasicValueEnum, FunctionValue};
@@ -35,6 +37,10 @@ pub struct CompilerTypeGetter<'ctx> {
     pub current_block: Option<BasicBlock<'ctx>>,
     /// Current function's variables
     pub variables: HashMap<String, (FinalizedTypes, BasicValueEnum<'ctx>)>,
+    /// Current function's debug info scope, if debug info is being emitted
+    pub scope: Option<DISubprogram<'ctx>>,
+    /// The target function, which returns primitives by pointer because it's called from Rust
+    pub target: Option<FunctionValue<'ctx>>,
 }
 
 impl<'ctx> CompilerTypeGetter<'ctx> {
@@ -48,6 +54,8 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
             blocks: HashMap::default(),
             current_block: None,
             variables: HashMap::default(),
+            scope: None,
+            target: None,
         };
     }
 
@@ -67,6 +75,23 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
             blocks: self.blocks.clone(),
             current_block: self.current_block.clone(),
             variables,
+            scope: None,
+            target: self.target,
+        };
+    }
+
+    /// Creates a CompilerTypeGetter for compiling a closure's function with the given variables
+    pub fn for_closure(&self, variables: HashMap<String, (FinalizedTypes, BasicValueEnum<'ctx>)>) -> Self {
+        return Self {
+            syntax: self.syntax.clone(),
+            vtable: self.vtable.clone(),
+            compiler: self.compiler.clone(),
+            compiling: self.compiling.clone(),
+            blocks: HashMap::default(),
+            current_block: None,
+            variables,
+            scope: None,
+            target: self.target,
         };
     }
 
@@ -75,11 +100,26 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
         match self.compiler.module.get_function(&function.data.name) {
             Some(found) => found,
             None => {
-                return instance_function(function.clone(), self);
+                // Instancing can generate code in other functions, which can't have this function's location.
+                // Locations are only set with a scope, without one LLVM returns an empty location that breaks codegen.
+                let location = self.scope.and_then(|_| self.compiler.builder.get_current_debug_location());
+                self.compiler.builder.unset_current_debug_location();
+                let value = instance_function(function.clone(), self);
+                if let Some(location) = location {
+                    self.compiler.builder.set_current_debug_location(location);
+                }
+                return value;
             }
         }
     }
 
+    /// Sets the location of the code being generated to the span, if debug info is being emitted
+    pub fn set_location(&self, span: &Span) {
+        if let (Some(debug), Some(scope)) = (&self.compiler.debug, self.scope) {
+            self.compiler.builder.set_current_debug_location(debug.location(span, scope));
+        }
+    }
+
     /// Gets the LLVM version of the type
     pub fn get_type(&mut self, types: &FinalizedTypes) -> BasicTypeEnum<'ctx> {
         let found = match self.compiler.module.get_struct_type(&types.name()) {
@@ -88,7 +128,10 @@ impl<'ctx> CompilerTypeGetter<'ctx> {
         }
         .as_basic_type_enum();
         return match types {
-            FinalizedTypes::Struct(_) | FinalizedTypes::Array(_) => found,
+            FinalizedTypes::Struct(_)
+            | FinalizedTypes::Array(_)
+            | FinalizedTypes::Function(_, _)
+            | FinalizedTypes::Tuple(_) => found,
             FinalizedTypes::Reference(_) => found.ptr_type(AddressSpace::default()).as_basic_type_enum(),
             _ => panic!("Can't compile a generic! {:?}", found),
         };
@@ -1,7 +1,8 @@
 use crate::type_getter::CompilerTypeGetter;
 use inkwell::module::Linkage;
-use inkwell::types::BasicType;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, FunctionType, StructType};
 use inkwell::values::FunctionValue;
+use inkwell::AddressSpace;
 use std::ops::Deref;
 use std::sync::Arc;
 use syntax::program::function::CodelessFinalizedFunction;
@@ -31,11 +32,13 @@ pub fn print_formatted(input: String) {
     println!("{}", output);
 }
 
-/// Creates a LLVM function from its normal type
+/// Creates a LLVM function from its normal type.
+/// Primitives are returned by value, unless returns_pointer is set for functions called from Rust.
 pub fn create_function_value<'ctx>(
     function: &Arc<CodelessFinalizedFunction>,
     type_getter: &mut CompilerTypeGetter<'ctx>,
     linkage: Option<Linkage>,
+    returns_pointer: bool,
 ) -> FunctionValue<'ctx> {
     let mut params = Vec::default();
 
@@ -46,10 +49,15 @@ pub fn create_function_value<'ctx>(
     let llvm_function = match &function.return_type {
         Some(returning) => {
             let mut returning = returning;
-            if let FinalizedTypes::Reference(inner) = returning {
+            // Generics can resolve to nested references, like the result of math used in more math
+            while let FinalizedTypes::Reference(inner) = returning {
                 returning = inner.deref();
             }
-            let types = type_getter.get_type(&FinalizedTypes::Reference(Box::new(returning.clone())));
+            let types = if returning.is_primitive() && !returns_pointer {
+                type_getter.get_type(returning)
+            } else {
+                type_getter.get_type(&FinalizedTypes::Reference(Box::new(returning.clone())))
+            };
             //Structs deallocate their memory when the function ends, so instead the parent function passes a pointer to it.
             //TODO not used for now cause malloc is used, but for future speed ups will be needed
             /*if types.is_struct_type() {
@@ -64,3 +72,40 @@ pub fn create_function_value<'ctx>(
 
     return type_getter.compiler.module.add_function(&function.data.name, llvm_function, linkage);
 }
+
+/// Gets the struct every function value (closures and function references) is stored as:
+/// a pointer to the function, then a pointer to the environment holding its captured variables.
+pub fn closure_type<'ctx>(type_getter: &CompilerTypeGetter<'ctx>) -> StructType<'ctx> {
+    let pointer = type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()).as_basic_type_enum();
+    return type_getter.compiler.context.struct_type(&[pointer, pointer], false);
+}
+
+/// Gets the LLVM type of a function value's function, which takes the environment before its arguments.
+/// Arguments and return types follow the same rules as normal functions.
+pub fn closure_function_type<'ctx>(
+    type_getter: &mut CompilerTypeGetter<'ctx>,
+    arguments: &[FinalizedTypes],
+    returning: Option<&FinalizedTypes>,
+) -> FunctionType<'ctx> {
+    let mut params: Vec<BasicMetadataTypeEnum> =
+        vec![From::from(type_getter.compiler.context.i8_type().ptr_type(AddressSpace::default()))];
+    for argument in arguments {
+        params.push(From::from(type_getter.get_type(&FinalizedTypes::Reference(Box::new(dereference(argument).clone())))));
+    }
+
+    return match returning.map(dereference) {
+        Some(returning) if returning.is_primitive() => type_getter.get_type(returning).fn_type(params.as_slice(), false),
+        Some(returning) => {
+            type_getter.get_type(&FinalizedTypes::Reference(Box::new(returning.clone()))).fn_type(params.as_slice(), false)
+        }
+        None => type_getter.compiler.context.void_type().fn_type(params.as_slice(), false),
+    };
+}
+
+/// Gets the type a reference points to, or the type itself if it isn't a reference
+fn dereference(types: &FinalizedTypes) -> &FinalizedTypes {
+    return match types {
+        FinalizedTypes::Reference(inner) => inner.deref(),
+        _ => types,
+    };
+}
@@ -2,6 +2,7 @@
 
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicPtr;
 
 use tokio::runtime::{Builder, Runtime};
 
@@ -10,6 +11,18 @@ use crate::tokens::Token;
 /// The type of the main LLVM function called by the program
 pub type Main<T> = unsafe extern "C" fn() -> T;
 
+/// A value the pointer returned by the target can be turned into, for compilers that don't call the target through LLVM
+pub trait TargetPointer {
+    /// Wraps the pointer to the value returned by the target
+    fn from_pointer(pointer: *mut u8) -> Self;
+}
+
+impl<T> TargetPointer for AtomicPtr<T> {
+    fn from_pointer(pointer: *mut u8) -> Self {
+        return AtomicPtr::new(pointer as *mut T);
+    }
+}
+
 /// Handles the externals for translating Raven types to Rust types
 pub mod externs;
 /// Tokens
@@ -32,6 +45,77 @@ pub struct CompilerArguments {
     pub target: String,
     /// The temp folder to use while compiling
     pub temp_folder: PathBuf,
+    /// The executable to build, or None to run the target instead
+    pub output: Option<PathBuf>,
+    /// How much to optimize the program
+    pub optimization: Optimization,
+    /// The stages of compilation to write to the temp folder
+    pub emit: Vec<Emit>,
+    /// Whether to emit DWARF debug info so debuggers can step through the Raven source, set with -g
+    pub debug_info: bool,
+}
+
+/// A stage of compilation to write to the temp folder for debugging, set with --emit.
+/// Every stage is written as text that's the same between runs, except bitcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+    /// The tokens of each file, written to tokens.txt
+    Tokens,
+    /// The code of each function before checking, written to ast.txt
+    Ast,
+    /// The code of each function after checking and degenericing, written to checked.txt
+    Checked,
+    /// The LLVM module, written to output.ll
+    LlvmIr,
+    /// The LLVM module as bitcode, written to output.bc
+    Bitcode,
+    /// The native assembly, written to output.s
+    Assembly,
+}
+
+impl Emit {
+    /// Gets the stage from its name, like llvm-ir
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "tokens" => Some(Emit::Tokens),
+            "ast" => Some(Emit::Ast),
+            "checked" => Some(Emit::Checked),
+            "llvm-ir" => Some(Emit::LlvmIr),
+            "llvm-bc" => Some(Emit::Bitcode),
+            "asm" => Some(Emit::Assembly),
+            _ => None,
+        };
+    }
+}
+
+/// How much the compiler optimizes the program, set with the -O0 to -O3 and -Os flags
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Optimization {
+    /// No optimizations, -O0
+    #[default]
+    None,
+    /// Quick optimizations, -O1
+    Less,
+    /// Most optimizations, -O2
+    Default,
+    /// Every optimization, -O3
+    Aggressive,
+    /// Optimizations that keep the program small, -Os
+    Size,
+}
+
+impl Optimization {
+    /// Gets the optimization level from its flag, like -O2
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        return match flag {
+            "-O0" => Some(Optimization::None),
+            "-O1" => Some(Optimization::Less),
+            "-O2" => Some(Optimization::Default),
+            "-O3" => Some(Optimization::Aggressive),
+            "-Os" => Some(Optimization::Size),
+            _ => None,
+        };
+    }
 }
 
 /// Arguments for running Raven
@@ -69,7 +153,7 @@ impl RunnerSettings {
     /// Whether to include references, LLVM requires it but runtimes like the JVM doesn't
     pub fn include_references(&self) -> bool {
         return match self.compiler_arguments.compiler.to_lowercase().as_str() {
-            "llvm" => true,
+            "llvm" | "interpreter" => true,
             _ => panic!("Unknown compiler {}", self.compiler_arguments.compiler),
         };
     }
@@ -250,4 +250,22 @@ pub enum TokenTypes {
     Char = 70,
     /// A blank line
     BlankLine = 71,
+    /// The arrow between a switch arm's pattern and its code ("=>")
+    ArmArrow = 72,
+    /// The continue keyword
+    Continue = 73,
+    /// The start of an enum ("enum")
+    EnumStart = 74,
+    /// The name of an enum variant
+    EnumVariant = 75,
+    /// The start of a variant's fields ("(")
+    VariantFieldsStart = 76,
+    /// The type of a variant's field
+    VariantField = 77,
+    /// The end of a variant's fields (")")
+    VariantFieldsEnd = 78,
+    /// The end of a variant (",")
+    VariantEnd = 79,
+    /// The mut keyword
+    Mut = 80,
 }
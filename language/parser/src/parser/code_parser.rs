@@ -1,4 +1,4 @@
-use crate::parser::control_parser::{parse_do_while, parse_for, parse_if, parse_while};
+use crate::parser::control_parser::{parse_do_while, parse_for, parse_if, parse_switch, parse_while};
 use crate::parser::operator_parser::parse_operator;
 use crate::parser::util::{parse_generics, ParserUtils};
 use data::tokens::{Span, Token, TokenTypes};
@@ -6,7 +6,7 @@ use std::mem;
 use syntax::async_util::UnparsedType;
 use syntax::errors::ParsingError;
 use syntax::errors::{ErrorSource, ParsingMessage};
-use syntax::program::code::{EffectType, Effects, Expression, ExpressionType};
+use syntax::program::code::{EffectType, Effects, Expression, ExpressionType, BREAK_LABEL, CONTINUE_LABEL};
 use syntax::program::function::CodeBody;
 
 /// Parsers a block of code into its return type (if all code paths lead to a single type, or else a line) and the code body.
@@ -42,6 +42,8 @@ pub enum ParseState {
     InOperator,
     /// When inside both an operator and control variable.
     ControlOperator,
+    /// When inside both an operator and the arguments of a function, where commas end the argument.
+    ArgumentOperator,
     /// When inside a new expression.
     New,
 }
@@ -72,7 +74,8 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                 | TokenTypes::If
                 | TokenTypes::For
                 | TokenTypes::While
-                | TokenTypes::Do => {
+                | TokenTypes::Do
+                | TokenTypes::Switch => {
                     return Err(span.make_error(ParsingMessage::UnexpectedValue()));
                 }
                 _ => {}
@@ -106,19 +109,13 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                     if is_generic(&token, parser_utils) {
                         continue;
                     } else {
-                        effect = Some(Effects::new(
-                            Span::new(parser_utils.file, parser_utils.index),
-                            EffectType::LoadVariable(token.to_string(parser_utils.buffer)),
-                        ))
+                        effect = Some(Effects::new(span, EffectType::LoadVariable(token.to_string(parser_utils.buffer))))
                     }
                 } else {
                     if effect.is_some() {
                         return Err(span.make_error(ParsingMessage::UnexpectedValue()));
                     }
-                    effect = Some(Effects::new(
-                        Span::new(parser_utils.file, parser_utils.index),
-                        EffectType::LoadVariable(token.to_string(parser_utils.buffer)),
-                    ))
+                    effect = Some(Effects::new(span, EffectType::LoadVariable(token.to_string(parser_utils.buffer))))
                 }
             }
             TokenTypes::Return => expression_type = ExpressionType::Return(Span::new(parser_utils.file, parser_utils.index)),
@@ -143,8 +140,16 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                     if matches!(expression_type, ExpressionType::Line) {
                         expression_type = returning;
                     }
-                    effect =
-                        Some(Effects::new(Span::new(parser_utils.file, parser_utils.index), EffectType::CodeBody(body)));
+                    effect = Some(Effects::new(span, EffectType::CodeBody(body)));
+                }
+            }
+            TokenTypes::ArmArrow => {
+                // Ends the pattern of a switch arm
+                if ParseState::ControlVariable == state || ParseState::ControlOperator == state {
+                    parser_utils.index -= 1;
+                    break;
+                } else {
+                    return Err(span.make_error(ParsingMessage::UnexpectedSymbol()));
                 }
             }
             TokenTypes::Let => {
@@ -199,13 +204,19 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                 } else {
                     // It must be an operator, parse it like one.
                     let operator = parse_operator(effect, parser_utils, &state)?;
-                    if ParseState::InOperator == state || ParseState::ControlOperator == state {
+                    if ParseState::InOperator == state
+                        || ParseState::ControlOperator == state
+                        || ParseState::ArgumentOperator == state
+                    {
                         return Ok(Some(Expression::new(expression_type, operator)));
                     } else {
                         effect = Some(operator);
                     }
                 }
             }
+            TokenTypes::Operator if effect.is_none() && token.to_string(parser_utils.buffer) == "|" => {
+                effect = Some(parse_closure(parser_utils, &span)?);
+            }
             TokenTypes::Operator => {
                 let last = parser_utils.tokens.get(parser_utils.index - 2).unwrap();
                 // If there is a variable right next to a less than, it's probably a generic method call.
@@ -221,7 +232,10 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                     let operator = parse_operator(effect, parser_utils, &state)?;
                     // Operators inside operators return immediately so operators can be combined
                     // later on for operators like [].
-                    if ParseState::InOperator == state || ParseState::ControlOperator == state {
+                    if ParseState::InOperator == state
+                        || ParseState::ControlOperator == state
+                        || ParseState::ArgumentOperator == state
+                    {
                         return Ok(Some(Expression::new(expression_type, operator)));
                     } else {
                         effect = Some(operator);
@@ -233,13 +247,17 @@ pub fn parse_line(parser_utils: &mut ParserUtils, state: ParseState) -> Result<O
                 if next.token_type == TokenTypes::ParenOpen || is_generic(&token, parser_utils) {
                     // Ignored, ParenOpen or Operator handles this
                 } else {
-                    if effect.is_none() {
-                        return Err(span.make_error(ParsingMessage::ExtraSymbol()));
-                    }
-                    effect = Some(Effects::new(
-                        Span::new(parser_utils.file, parser_utils.index),
-                        EffectType::Load(Box::new(effect.unwrap()), token.to_string(parser_utils.buffer)),
-                    ))
+                    let name = token.to_string(parser_utils.buffer);
+                    let types = match effect {
+                        Some(effect) => EffectType::Load(Box::new(effect), name),
+                        // The end of a range like "0..end" is read like a field, but it's a variable
+                        None if parser_utils.tokens[parser_utils.index - 2].token_type == TokenTypes::Period => {
+                            EffectType::LoadVariable(name)
+                        }
+                        None => return Err(span.make_error(ParsingMessage::ExtraSymbol())),
+                    };
+                    // Errors loading the field, like it not being visible, point at the field
+                    effect = Some(Effects::new(span, types))
                 }
             }
             TokenTypes::Else => return Err(span.make_error(ParsingMessage::UnexpectedElse())),
@@ -278,38 +296,38 @@ fn parse_basic_line(
             ControlFlow::Skipping
         }
         TokenTypes::Float => {
-            *effect = Some(Effects::new(
-                Span::new(parser_utils.file, parser_utils.index),
-                EffectType::Float(token.to_string(parser_utils.buffer).parse().unwrap()),
-            ));
+            *effect = Some(Effects::new(span, EffectType::Float(token.to_string(parser_utils.buffer).parse().unwrap())));
             ControlFlow::Skipping
         }
         TokenTypes::Integer => {
-            *effect = Some(Effects::new(
-                Span::new(parser_utils.file, parser_utils.index),
-                EffectType::Int(token.to_string(parser_utils.buffer).parse().unwrap()),
-            ));
+            *effect = Some(Effects::new(span, EffectType::Int(token.to_string(parser_utils.buffer).parse().unwrap())));
             ControlFlow::Skipping
         }
         TokenTypes::Char => {
-            *effect = Some(Effects::new(
-                Span::new(parser_utils.file, parser_utils.index),
-                EffectType::Char(token.to_string(parser_utils.buffer).as_bytes()[1] as char),
-            ));
+            *effect = Some(Effects::new(span, EffectType::Char(token.to_string(parser_utils.buffer).as_bytes()[1] as char)));
             ControlFlow::Skipping
         }
         TokenTypes::True => {
-            *effect = Some(Effects::new(Span::new(parser_utils.file, parser_utils.index), EffectType::Bool(true)));
+            *effect = Some(Effects::new(span, EffectType::Bool(true)));
             ControlFlow::Skipping
         }
         TokenTypes::False => {
-            *effect = Some(Effects::new(Span::new(parser_utils.file, parser_utils.index), EffectType::Bool(false)));
+            *effect = Some(Effects::new(span, EffectType::Bool(false)));
             ControlFlow::Skipping
         }
         TokenTypes::StringStart => {
             *effect = Some(parse_string(parser_utils)?);
             ControlFlow::Skipping
         }
+        // Breaks and continues jump to placeholder labels, which are linked when the loop is created.
+        TokenTypes::Break | TokenTypes::Continue => {
+            if effect.is_some() {
+                return Err(span.make_error(ParsingMessage::UnexpectedValue()));
+            }
+            let label = if token.token_type == TokenTypes::Break { BREAK_LABEL } else { CONTINUE_LABEL };
+            *effect = Some(Effects::new(span, EffectType::Jump(label.to_string())));
+            ControlFlow::Skipping
+        }
         TokenTypes::Let => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_let(parser_utils)?)),
         TokenTypes::If => {
             let expression = parse_if(parser_utils)?;
@@ -320,6 +338,15 @@ fn parse_basic_line(
             }
             ControlFlow::Returning(Expression::new(expression_type, expression.effect))
         }
+        TokenTypes::Switch => {
+            let expression = parse_switch(parser_utils)?;
+            let mut expression_type = expression_type.clone();
+            // If the switch returns/breaks, the outer block should too
+            if expression_type == ExpressionType::Line {
+                expression_type = expression.expression_type;
+            }
+            ControlFlow::Returning(Expression::new(expression_type, expression.effect))
+        }
         TokenTypes::For => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_for(parser_utils)?)),
         TokenTypes::While => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_while(parser_utils)?)),
         TokenTypes::Do => ControlFlow::Returning(Expression::new(expression_type.clone(), parse_do_while(parser_utils)?)),
@@ -345,18 +372,18 @@ fn parse_basic_line(
                     });
                     ControlFlow::Skipping
                 }
-                // If it's not a method call, it's a parenthesized effect.
+                // If it's not a method call, it's a parenthesized effect or a tuple like (a, b).
                 _ => {
-                    if let Some(expression) = parse_line(parser_utils, ParseState::None)? {
-                        *effect = Some(Effects::new(
+                    let mut values = get_effects(parser_utils)?;
+                    *effect = Some(match values.len() {
+                        0 => return Err(span.make_error(ParsingMessage::ExpectedEffect())),
+                        1 => Effects::new(
                             Span::new(parser_utils.file, parser_utils.index),
-                            EffectType::Paren(Box::new(expression.effect)),
-                        ));
-                        ControlFlow::Skipping
-                    } else {
-                        //effect = None;
-                        panic!("Unknown code path - report this!");
-                    }
+                            EffectType::Paren(Box::new(values.pop().unwrap())),
+                        ),
+                        _ => Effects::new(span, EffectType::CreateTuple(values)),
+                    });
+                    ControlFlow::Skipping
                 }
             }
         }
@@ -367,7 +394,10 @@ fn parse_basic_line(
                 let operator = parse_operator(temp, parser_utils, &state)?;
                 // Operators inside operators return immediately so operators can be combined
                 // later on for operators like [].
-                if ParseState::InOperator == *state || ParseState::ControlOperator == *state {
+                if ParseState::InOperator == *state
+                    || ParseState::ControlOperator == *state
+                    || ParseState::ArgumentOperator == *state
+                {
                     return Ok(ControlFlow::Returning(Expression::new(expression_type.clone(), operator)));
                 } else {
                     *effect = Some(operator);
@@ -489,7 +519,7 @@ fn get_effects(parser_utils: &mut ParserUtils) -> Result<Vec<Effects>, ParsingEr
     // Parse the method call arguments
     if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::ParenClose {
         let start = parser_utils.index;
-        while let Some(mut expression) = parse_line(parser_utils, ParseState::None)? {
+        while let Some(mut expression) = parse_line(parser_utils, ParseState::Argument)? {
             expression.effect.span.extend_span_backwards(start);
             effects.push(expression.effect);
             if parser_utils.tokens[parser_utils.index - 1].token_type != TokenTypes::ArgumentEnd {
@@ -505,32 +535,164 @@ fn get_effects(parser_utils: &mut ParserUtils) -> Result<Vec<Effects>, ParsingEr
 /// Parses a let statement
 fn parse_let(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
     let name;
+    let mut types = None;
     let mut error_token;
+    let mut mutable = false;
     {
+        // Variables are immutable unless declared like "let mut name = value"
+        if TokenTypes::Mut == parser_utils.tokens[parser_utils.index].token_type {
+            mutable = true;
+            parser_utils.index += 1;
+        }
+
+        // The span starts at the name so errors about the variable point to its declaration
+        error_token = Span::new(parser_utils.file, parser_utils.index);
+        if TokenTypes::ParenOpen == parser_utils.tokens[parser_utils.index].token_type {
+            if mutable {
+                return Err(error_token.make_error(ParsingMessage::UnexpectedToken()));
+            }
+            return parse_destructure(parser_utils, error_token);
+        }
         let next = &parser_utils.tokens[parser_utils.index];
         if TokenTypes::Variable == next.token_type {
             name = next.to_string(parser_utils.buffer);
         } else {
             return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedToken()));
         }
+        parser_utils.index += 1;
 
-        if TokenTypes::Equals != parser_utils.tokens.get(parser_utils.index + 1).unwrap().token_type {
+        // The type is optional, like "let name: Type = value"
+        if TokenTypes::Colon == parser_utils.tokens[parser_utils.index].token_type {
+            parser_utils.index += 1;
+            types = Some(UnparsedType::Basic(parse_inline_type(parser_utils)));
+        }
+
+        if TokenTypes::Equals != parser_utils.tokens[parser_utils.index].token_type {
             return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedSymbol()));
         }
-        parser_utils.index += 2;
-        error_token = Span::new(parser_utils.file, parser_utils.index);
+        parser_utils.index += 1;
     }
 
     // If the rest of the line doesn't exist, return an error because the value must be set to something.
     return match parse_line(parser_utils, ParseState::None)? {
         Some(line) => {
             error_token.extend_span(parser_utils.index - 2);
-            Ok(Effects::new(error_token, EffectType::CreateVariable(name, Box::new(line.effect))))
+            Ok(Effects::new(error_token, EffectType::CreateVariable(name, mutable, types, Box::new(line.effect))))
         }
         None => Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid())),
     };
 }
 
+/// Parses a destructuring let, like let (first, mut second) = value;
+fn parse_destructure(parser_utils: &mut ParserUtils, mut span: Span) -> Result<Effects, ParsingError> {
+    parser_utils.index += 1;
+    let mut names = Vec::default();
+    // Each name can be mutable on its own
+    loop {
+        let mut mutable = false;
+        if TokenTypes::Mut == parser_utils.tokens[parser_utils.index].token_type {
+            mutable = true;
+            parser_utils.index += 1;
+        }
+
+        let next = &parser_utils.tokens[parser_utils.index];
+        if TokenTypes::Variable != next.token_type {
+            return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::ExpectedVariableName()));
+        }
+        names.push((next.to_string(parser_utils.buffer), mutable));
+        parser_utils.index += 1;
+
+        let next = &parser_utils.tokens[parser_utils.index];
+        parser_utils.index += 1;
+        match next.token_type {
+            TokenTypes::ArgumentEnd => {}
+            TokenTypes::ParenClose => break,
+            _ => {
+                return Err(
+                    Span::new(parser_utils.file, parser_utils.index - 1).make_error(ParsingMessage::UnexpectedSymbol())
+                )
+            }
+        }
+    }
+
+    if TokenTypes::Equals != parser_utils.tokens[parser_utils.index].token_type {
+        return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedSymbol()));
+    }
+    parser_utils.index += 1;
+
+    return match parse_line(parser_utils, ParseState::None)? {
+        Some(line) => {
+            span.extend_span(parser_utils.index - 2);
+            Ok(Effects::new(span, EffectType::Destructure(names, Box::new(line.effect))))
+        }
+        None => Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid())),
+    };
+}
+
+/// Parses a closure, like |value: i64| -> i64 { return value + 1; }
+fn parse_closure(parser_utils: &mut ParserUtils, span: &Span) -> Result<Effects, ParsingError> {
+    let mut arguments = Vec::default();
+    // Parse the arguments until the closing |
+    loop {
+        let token = &parser_utils.tokens[parser_utils.index];
+        parser_utils.index += 1;
+        match token.token_type {
+            TokenTypes::Variable => {
+                let name = token.to_string(parser_utils.buffer);
+                if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::Colon {
+                    return Err(span.make_error(ParsingMessage::ExpectedClosureArgument()));
+                }
+                parser_utils.index += 1;
+                arguments.push((name, UnparsedType::Basic(parse_inline_type(parser_utils))));
+            }
+            TokenTypes::ArgumentEnd => {}
+            TokenTypes::Operator if token.to_string(parser_utils.buffer) == "|" => break,
+            _ => return Err(span.make_error(ParsingMessage::ExpectedClosureArgument())),
+        }
+    }
+
+    // Parse the return type if there is one
+    let mut returning = None;
+    if parser_utils.tokens[parser_utils.index].to_string(parser_utils.buffer) == "-"
+        && parser_utils.tokens[parser_utils.index + 1].to_string(parser_utils.buffer) == ">"
+    {
+        parser_utils.index += 2;
+        returning = Some(UnparsedType::Basic(parse_inline_type(parser_utils)));
+    }
+
+    if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::BlockStart {
+        return Err(span.make_error(ParsingMessage::ExpectedCodeBlock()));
+    }
+    parser_utils.index += 1;
+    let (_, body) = parse_code(parser_utils)?;
+    return Ok(Effects::new(span.clone(), EffectType::Closure(arguments, returning, body)));
+}
+
+/// Parses a type inside code, like a closure's argument or a variable's type. Stops at the end of the argument,
+/// the start of the code block, or the equals sign.
+fn parse_inline_type(parser_utils: &mut ParserUtils) -> String {
+    let mut output = String::default();
+    let mut depth = 0;
+    loop {
+        let token = &parser_utils.tokens[parser_utils.index];
+        let text = token.to_string(parser_utils.buffer);
+        match token.token_type {
+            TokenTypes::BlockStart | TokenTypes::Equals | TokenTypes::EOF => break,
+            TokenTypes::ArgumentEnd if depth == 0 => break,
+            TokenTypes::Operator if depth == 0 && text == "|" => break,
+            TokenTypes::ParenOpen => depth += 1,
+            TokenTypes::ParenClose => depth -= 1,
+            // The > in -> doesn't close a generic
+            TokenTypes::Operator if text == "<" => depth += 1,
+            TokenTypes::Operator if text == ">" && !output.ends_with('-') => depth -= 1,
+            _ => {}
+        }
+        output += &text;
+        parser_utils.index += 1;
+    }
+    return output;
+}
+
 /// Parses a new program call
 fn parse_new(parser_utils: &mut ParserUtils, span: &Span) -> Result<Effects, ParsingError> {
     let mut types: Option<UnparsedType> = None;
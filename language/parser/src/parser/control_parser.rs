@@ -1,6 +1,7 @@
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
-use syntax::program::code::{EffectType, Effects, Expression, ExpressionType};
+use syntax::program::code::{EffectType, Effects, Expression, ExpressionType, BREAK_LABEL, CONTINUE_LABEL};
 use syntax::program::function::CodeBody;
+use syntax::program::r#struct::variant_field;
 
 use crate::parser::code_parser::{parse_code, parse_line, ParseState};
 use crate::ParserUtils;
@@ -76,7 +77,8 @@ pub fn parse_if(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingErr
         returning = ExpressionType::Line;
     }
 
-    let adding = 1 + else_ifs.len() as u32 + else_body.is_some() as u32;
+    // One id for the if, one for each else if, and one for the else
+    let adding = 2 + else_ifs.len() as u32;
     parser_utils.imports.last_id += adding;
     return Ok(Expression::new(
         returning,
@@ -84,6 +86,196 @@ pub fn parse_if(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingErr
     ));
 }
 
+/// Parses a switch statement into a single expression.
+/// Switches are turned into an if statement checking if the value matches each arm's pattern in order.
+/// ex:
+/// switch value {
+///     1 => { ... }
+///     2..5 => { ... }
+///     else => { ... }
+/// }
+pub fn parse_switch(parser_utils: &mut ParserUtils) -> Result<Expression, ParsingError> {
+    // Get the value being switched on
+    let value = parse_line(parser_utils, ParseState::ControlVariable)?;
+    if value.is_none() {
+        return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid()));
+    }
+
+    if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::BlockStart {
+        return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::ExpectedCodeBlock()));
+    }
+
+    parser_utils.index += 1;
+
+    let mut arms = Vec::default();
+    let mut else_body = None;
+    let mut returning = None;
+    loop {
+        match parser_utils.tokens[parser_utils.index].token_type {
+            TokenTypes::BlockEnd => {
+                parser_utils.index += 1;
+                break;
+            }
+            TokenTypes::Comment | TokenTypes::ArgumentEnd => parser_utils.index += 1,
+            TokenTypes::Else => {
+                parser_utils.index += 1;
+                let (other_returning, body) = parse_arm(parser_utils)?;
+                merge_returning(&mut returning, other_returning);
+                else_body = Some(body);
+            }
+            _ => {
+                // The else arm must be the last arm
+                if else_body.is_some() {
+                    return Err(
+                        Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedElse())
+                    );
+                }
+
+                let pattern = parse_line(parser_utils, ParseState::ControlVariable)?;
+                if pattern.is_none() {
+                    return Err(
+                        Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::UnexpectedVoid())
+                    );
+                }
+                let (other_returning, body) = parse_arm(parser_utils)?;
+                merge_returning(&mut returning, other_returning);
+                arms.push((pattern.unwrap().effect, body));
+            }
+        }
+    }
+
+    if arms.is_empty() {
+        return Err(Span::new(parser_utils.file, parser_utils.index - 1).make_error(ParsingMessage::ExpectedSwitchArm()));
+    }
+
+    // If there is no else, the switch can't be the return.
+    let returning = match returning {
+        Some(returning) if else_body.is_some() => returning,
+        _ => ExpressionType::Line,
+    };
+
+    // One id for the first arm, one for each arm after it, and one for the else
+    let adding = arms.len() as u32 + 1;
+    parser_utils.imports.last_id += adding;
+    let id = parser_utils.imports.last_id - adding;
+
+    // The value is only calculated once, then each pattern is checked against it.
+    let variable = format!("$switch{}", id);
+    let match_trait =
+        parser_utils.imports.imports.iter().find(|import| import.rsplit("::").next() == Some("Match")).cloned();
+    let mut arms = arms
+        .into_iter()
+        .map(|(pattern, body)| create_arm(&variable, match_trait.as_ref(), pattern, body))
+        .collect::<Vec<_>>();
+    let (effect, body) = arms.remove(0);
+
+    let mut effect = create_if(effect, body, arms, else_body, id)?;
+    if let EffectType::CodeBody(body) = &mut effect.types {
+        body.expressions.insert(
+            0,
+            Expression::new(
+                ExpressionType::Line,
+                Effects::new(
+                    Span::default(),
+                    EffectType::CreateVariable(variable, false, None, Box::new(value.unwrap().effect)),
+                ),
+            ),
+        );
+    }
+    return Ok(Expression::new(returning, effect));
+}
+
+/// Creates the condition of a switch arm. Patterns like "Enum::Variant(first, second)" check the enum's variant
+/// and bind its fields to the variables. Every other pattern is checked with the imported Match trait, or if the
+/// file doesn't import one, compared to the value with the equality operators (ranges check both of their ends).
+fn create_arm(variable: &str, match_trait: Option<&String>, pattern: Effects, mut body: CodeBody) -> (Effects, CodeBody) {
+    let load = || Box::new(Effects::new(Span::default(), EffectType::LoadVariable(variable.to_string())));
+    let (variant, bindings) = match &pattern.types {
+        EffectType::LoadVariable(name) if name.contains("::") => (name.clone(), Vec::default()),
+        EffectType::MethodCall(None, name, arguments, None)
+            if name.contains("::")
+                && arguments.iter().all(|argument| matches!(argument.types, EffectType::LoadVariable(_))) =>
+        {
+            let bindings = arguments
+                .iter()
+                .map(|argument| match &argument.types {
+                    EffectType::LoadVariable(binding) => binding.clone(),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>();
+            (name.clone(), bindings)
+        }
+        _ => {
+            let span = pattern.span.clone();
+            let condition = match (match_trait, pattern.types) {
+                (Some(match_trait), types) => EffectType::ImplementationCall(
+                    load(),
+                    match_trait.clone(),
+                    "matches".to_string(),
+                    vec![Effects::new(span.clone(), types)],
+                    None,
+                ),
+                (None, EffectType::Operation(operation, mut values)) if operation == "{}..{}" && values.len() == 2 => {
+                    let end = values.pop().unwrap();
+                    let start = values.pop().unwrap();
+                    EffectType::Operation(
+                        "{}&&{}".to_string(),
+                        vec![
+                            Effects::new(span.clone(), EffectType::Operation("{}<={}".to_string(), vec![start, *load()])),
+                            Effects::new(span.clone(), EffectType::Operation("{}<{}".to_string(), vec![*load(), end])),
+                        ],
+                    )
+                }
+                (None, types) => {
+                    EffectType::Operation("{}=={}".to_string(), vec![*load(), Effects::new(span.clone(), types)])
+                }
+            };
+            return (Effects::new(span, condition), body);
+        }
+    };
+
+    let name = variant.split("::").last().unwrap();
+    for (i, binding) in bindings.into_iter().enumerate() {
+        body.expressions.insert(
+            i,
+            Expression::new(
+                ExpressionType::Line,
+                Effects::new(
+                    pattern.span.clone(),
+                    EffectType::CreateVariable(
+                        binding,
+                        false,
+                        None,
+                        Box::new(Effects::new(pattern.span.clone(), EffectType::Load(load(), variant_field(name, i)))),
+                    ),
+                ),
+            ),
+        );
+    }
+    return (Effects::new(pattern.span.clone(), EffectType::IsVariant(load(), variant)), body);
+}
+
+/// Parses the "=> { ... }" of a switch arm
+fn parse_arm(parser_utils: &mut ParserUtils) -> Result<(ExpressionType, CodeBody), ParsingError> {
+    if parser_utils.tokens[parser_utils.index].token_type != TokenTypes::ArmArrow
+        || parser_utils.tokens[parser_utils.index + 1].token_type != TokenTypes::BlockStart
+    {
+        return Err(Span::new(parser_utils.file, parser_utils.index).make_error(ParsingMessage::ExpectedSwitchArm()));
+    }
+
+    parser_utils.index += 2;
+    return parse_code(parser_utils);
+}
+
+/// A switch is only the return of the block if every arm returns, so if they differ it can't be a return block.
+fn merge_returning(returning: &mut Option<ExpressionType>, other: ExpressionType) {
+    match returning {
+        Some(found) if *found != other => *found = ExpressionType::Line,
+        Some(_) => {}
+        None => *returning = Some(other),
+    }
+}
+
 /// Parses a for statement into a single expression
 pub fn parse_for(parser_utils: &mut ParserUtils) -> Result<Effects, ParsingError> {
     let name = &parser_utils.tokens[parser_utils.index];
@@ -171,6 +363,8 @@ fn create_do_while(effect: Effects, mut body: CodeBody, id: u32) -> Result<Effec
     let mut top = Vec::default();
 
     let label = body.label.clone();
+    // Continuing skips to the condition check after the body
+    link_loop(&mut body, &(id.to_string() + "end"), &(label.clone() + "end"));
     body.expressions.push(Expression::new(
         ExpressionType::Line,
         Effects::new(Span::default(), EffectType::Jump((id - 1).to_string() + "end")),
@@ -188,6 +382,8 @@ fn create_do_while(effect: Effects, mut body: CodeBody, id: u32) -> Result<Effec
 fn create_while(effect: Effects, mut body: CodeBody, id: u32) -> Result<Effects, ParsingError> {
     let mut top = Vec::default();
 
+    link_loop(&mut body, &(id.to_string() + "end"), &id.to_string());
+
     top.push(Expression::new(
         ExpressionType::Line,
         Effects::new(Span::default(), EffectType::CompareJump(Box::new(effect), body.label.clone(), id.to_string() + "end")),
@@ -217,7 +413,14 @@ fn create_if(
         ));
         Some(body)
     } else if !else_ifs.is_empty() {
-        Some(CodeBody::new(Vec::default(), id.to_string()))
+        // Without an else, the last else if falls through to an empty else that skips to the end
+        Some(CodeBody::new(
+            vec![Expression::new(
+                ExpressionType::Line,
+                Effects::new(Span::default(), EffectType::Jump(id.to_string() + "end")),
+            )],
+            (id + else_ifs.len() as u32 + 1).to_string(),
+        ))
     } else {
         None
     };
@@ -297,11 +500,12 @@ fn create_if(
 fn create_for(name: String, effect: Effects, mut body: CodeBody, id: u32) -> Result<Effects, ParsingError> {
     let mut top = Vec::default();
     let variable = format!("$iter{}", id);
+    link_loop(&mut body, &(id.to_string() + "end"), &(id + 1).to_string());
     top.insert(
         0,
         Expression::new(
             ExpressionType::Line,
-            Effects::new(Span::default(), EffectType::CreateVariable(variable.clone(), Box::new(effect))),
+            Effects::new(Span::default(), EffectType::CreateVariable(variable.clone(), true, None, Box::new(effect))),
         ),
     );
     top.push(Expression::new(ExpressionType::Line, Effects::new(Span::default(), EffectType::Jump((id + 1).to_string()))));
@@ -314,6 +518,8 @@ fn create_for(name: String, effect: Effects, mut body: CodeBody, id: u32) -> Res
                 Span::default(),
                 EffectType::CreateVariable(
                     name.clone(),
+                    false,
+                    None,
                     Box::new(Effects::new(
                         Span::default(),
                         EffectType::ImplementationCall(
@@ -363,3 +569,16 @@ fn create_for(name: String, effect: Effects, mut body: CodeBody, id: u32) -> Res
 
     return Ok(Effects::new(Span::default(), EffectType::CodeBody(CodeBody::new(top, id.to_string()))));
 }
+
+/// Links the break and continue statements in a loop's body to the loop's end and header labels.
+/// Nested loops are created first, so only the statements belonging to this loop are left.
+fn link_loop(body: &mut CodeBody, end: &str, header: &str) {
+    for line in &mut body.expressions {
+        match &mut line.effect.types {
+            EffectType::Jump(label) if label == BREAK_LABEL => *label = end.to_string(),
+            EffectType::Jump(label) if label == CONTINUE_LABEL => *label = header.to_string(),
+            EffectType::CodeBody(inner) => link_loop(inner, end, header),
+            _ => {}
+        }
+    }
+}
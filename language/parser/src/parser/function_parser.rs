@@ -30,6 +30,8 @@ pub fn parse_function(
 
     let mut last_arg = String::default();
     let mut last_arg_type = String::default();
+    let mut last_arg_modifiers = 0;
+    let mut last_arg_span = Span::new(parser_utils.file, parser_utils.index);
 
     let token = parser_utils.index;
 
@@ -40,7 +42,15 @@ pub fn parse_function(
             TokenTypes::Identifier => name = parser_utils.file_name.clone() + "::" + &*token.to_string(parser_utils.buffer),
             TokenTypes::GenericsStart => parse_generics(parser_utils, &mut generics),
             TokenTypes::ArgumentsStart | TokenTypes::ArgumentSeparator | TokenTypes::ArgumentTypeSeparator => {}
-            TokenTypes::ArgumentName => last_arg = token.to_string(parser_utils.buffer),
+            TokenTypes::ArgumentName => {
+                last_arg = token.to_string(parser_utils.buffer);
+                last_arg_span = Span::new(parser_utils.file, parser_utils.index - 1);
+                // Arguments that can be changed are marked like "mut self" or "mut value: Type"
+                if let Some(name) = last_arg.strip_prefix("mut ") {
+                    last_arg = name.trim().to_string();
+                    last_arg_modifiers = Modifier::Mut as u8;
+                }
+            }
             TokenTypes::ArgumentType => last_arg_type = token.to_string(parser_utils.buffer),
             TokenTypes::ArgumentEnd => {
                 if last_arg_type.is_empty() {
@@ -53,25 +63,28 @@ pub fn parse_function(
                     fields.push(Box::pin(to_field(
                         Syntax::parse_type(
                             parser_utils.syntax.clone(),
-                            Span::default(),
+                            Span::new(parser_utils.file, parser_utils.index - 1),
                             Box::new(parser_utils.imports.clone()),
                             parser_utils.imports.parent.clone().unwrap(),
                             vec![],
                         ),
                         Vec::default(),
-                        0,
+                        last_arg_modifiers,
                         last_arg,
+                        last_arg_span.clone(),
                     )));
                 } else {
                     fields.push(Box::pin(to_field(
                         parser_utils.get_struct(&Span::new(parser_utils.file, parser_utils.index - 1), last_arg_type),
                         Vec::default(),
-                        0,
+                        last_arg_modifiers,
                         last_arg,
+                        last_arg_span.clone(),
                     )));
                     last_arg_type = String::default();
                 }
                 last_arg = String::default();
+                last_arg_modifiers = 0;
             }
             TokenTypes::ArgumentsEnd | TokenTypes::ReturnTypeArrow => {}
             TokenTypes::ReturnType => {
@@ -32,22 +32,26 @@ pub fn parse_operator(
         parser_utils.index += 1;
     }
 
+    // Commas in a function's arguments end the argument, unless they're inside a list like [a, b]
+    let right_state = match state {
+        ParseState::ControlVariable | ParseState::ControlOperator => ParseState::ControlOperator,
+        ParseState::Argument | ParseState::ArgumentOperator if !operation.ends_with('[') => ParseState::ArgumentOperator,
+        _ => ParseState::InOperator,
+    };
+
+    let start = parser_utils.index;
     let mut first_element_token = Span::new(parser_utils.file, parser_utils.index);
     let (mut index, mut tokens) = (parser_utils.index.clone(), parser_utils.tokens.len());
-    let mut right = match parse_line(
-        parser_utils,
-        match state {
-            ParseState::ControlVariable | ParseState::ControlOperator => ParseState::ControlOperator,
-            _ => ParseState::InOperator,
-        },
-    ) {
+    let mut right = match parse_line(parser_utils, right_state.clone()) {
         Ok(inner) => inner.map(|inner| inner.effect),
         Err(_) => None,
     };
     first_element_token.extend_span(parser_utils.index);
 
     if right.is_some() {
-        while parser_utils.tokens.get(parser_utils.index - 1).unwrap().token_type == TokenTypes::ArgumentEnd {
+        while right_state != ParseState::ArgumentOperator
+            && parser_utils.tokens.get(parser_utils.index - 1).unwrap().token_type == TokenTypes::ArgumentEnd
+        {
             (index, tokens) = (parser_utils.index.clone(), parser_utils.tokens.len());
             let mut next_element_token = Span::new(parser_utils.file, parser_utils.index);
             let next = parse_line(parser_utils, ParseState::InOperator)?.map(|inner| inner.effect);
@@ -106,6 +110,12 @@ pub fn parse_operator(
         effects.push(found);
     }
 
+    // Parens closed inside the right side, like a method call's, belong to it and aren't given back
+    let mut depth = parser_utils.tokens[start..parser_utils.index].iter().fold(0, |depth, token| match token.token_type {
+        TokenTypes::ParenOpen => depth + 1,
+        TokenTypes::ParenClose => depth - 1,
+        _ => depth,
+    });
     let mut last = parser_utils.tokens[parser_utils.index - 1].token_type.clone();
     while TokenTypes::BlockStart == last
         || TokenTypes::LineEnd == last
@@ -113,6 +123,12 @@ pub fn parse_operator(
         || TokenTypes::ArgumentEnd == last
         || TokenTypes::ParenClose == last
     {
+        if TokenTypes::ParenClose == last {
+            if depth >= 0 {
+                break;
+            }
+            depth += 1;
+        }
         parser_utils.index -= 1;
         last.clone_from(&parser_utils.tokens[parser_utils.index - 1].token_type);
     }
@@ -6,7 +6,7 @@ use data::tokens::{Span, Token, TokenTypes};
 use syntax::async_util::{NameResolver, UnparsedType};
 use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
 use syntax::program::code::{Field, MemberField};
-use syntax::program::r#struct::{get_internal, StructData, UnfinalizedStruct};
+use syntax::program::r#struct::{get_internal, variant_field, StructData, UnfinalizedStruct};
 use syntax::program::syntax::Syntax;
 use syntax::program::types::Types;
 use syntax::{get_modifier, is_modifier, Attribute, Modifier, ParsingFuture, TraitImplementor};
@@ -69,6 +69,10 @@ pub fn parse_structure(
                 } else {
                     parser_utils.file_name = format!("{}::{}", parser_utils.file_name, name);
                 }
+                // Trait functions are as visible as the trait
+                if is_modifier(modifiers, Modifier::Trait) {
+                    inherit_visibility(&mut member_modifiers, modifiers);
+                }
                 let function = parse_function(
                     parser_utils,
                     is_modifier(modifiers, Modifier::Trait),
@@ -112,6 +116,77 @@ pub fn parse_structure(
     return Ok(UnfinalizedStruct { generics, fields, functions, data });
 }
 
+/// Parses an enum, which is stored as a struct with each variant's fields
+pub fn parse_enum(
+    parser_utils: &mut ParserUtils,
+    attributes: Vec<Attribute>,
+    modifiers: Vec<Modifier>,
+) -> Result<UnfinalizedStruct, ParsingError> {
+    let modifiers = get_modifier(modifiers.as_slice());
+
+    let start = Span::new(parser_utils.file, parser_utils.index);
+    let mut name = String::default();
+    let mut variants: Vec<String> = Vec::default();
+    let mut fields = Vec::default();
+    let mut generics = IndexMap::default();
+    let mut index = 0;
+    while parser_utils.tokens.len() != parser_utils.index {
+        let token: Token = parser_utils.tokens.get(parser_utils.index).unwrap().clone();
+        parser_utils.index += 1;
+        match token.token_type {
+            TokenTypes::Identifier => {
+                name = token.to_string(parser_utils.buffer);
+                parser_utils.imports.parent = Some(UnparsedType::Basic(name.clone()));
+            }
+            TokenTypes::GenericsStart => {
+                parse_generics(parser_utils, &mut generics);
+                parser_utils.imports.parent = Some(UnparsedType::Generic(
+                    Box::new(parser_utils.imports.parent.clone().unwrap()),
+                    generics.keys().map(|key| UnparsedType::Basic(key.clone())).collect(),
+                ));
+            }
+            TokenTypes::EnumVariant => {
+                let variant = token.to_string(parser_utils.buffer);
+                if variants.contains(&variant) {
+                    return Err(Span::new(parser_utils.file, parser_utils.index - 1)
+                        .make_error(ParsingMessage::DuplicateVariant(variant)));
+                }
+                variants.push(variant);
+                index = 0;
+            }
+            TokenTypes::VariantField => {
+                let span = Span::new(parser_utils.file, parser_utils.index - 1);
+                let types = parser_utils.get_struct(&span, token.to_string(parser_utils.buffer));
+                fields.push(Box::pin(to_field(
+                    types,
+                    Vec::default(),
+                    Modifier::Public as u8,
+                    variant_field(variants.last().unwrap(), index),
+                    span,
+                )) as ParsingFuture<MemberField>);
+                index += 1;
+            }
+            TokenTypes::StructTopElement
+            | TokenTypes::VariantFieldsStart
+            | TokenTypes::VariantFieldsEnd
+            | TokenTypes::VariantEnd
+            | TokenTypes::ArgumentEnd
+            | TokenTypes::Comment => {}
+            TokenTypes::StructEnd | TokenTypes::EOF => break,
+            _ => {
+                return Err(
+                    Span::new(parser_utils.file, parser_utils.index - 1).make_error(ParsingMessage::UnexpectedTopElement())
+                )
+            }
+        }
+    }
+
+    let mut data =
+        StructData::new(attributes, Vec::default(), modifiers, start, format!("{}::{}", parser_utils.file_name, name));
+    data.variants = variants;
+    return Ok(UnfinalizedStruct { generics, fields, functions: Vec::default(), data: Arc::new(data) });
+}
+
 /// Parses an implementor
 pub fn parse_implementor(
     parser_utils: &mut ParserUtils,
@@ -137,6 +212,8 @@ pub fn parse_implementor(
                 let name = token.to_string(parser_utils.buffer);
                 let temp = Some(UnparsedType::Basic(name.clone()));
                 if state == 0 {
+                    // Impls without a trait implement the base, so it's the parent until a for is found
+                    parser_utils.imports.parent = temp.clone();
                     base = temp;
                     base_span = Some(Span::new(parser_utils.file, parser_utils.index - 1));
                     state = 1;
@@ -152,6 +229,7 @@ pub fn parse_implementor(
                 } else {
                     if state == 1 {
                         let found = UnparsedType::Generic(Box::new(base.unwrap()), parse_type_generics(parser_utils)?);
+                        parser_utils.imports.parent = Some(found.clone());
                         base = Some(found);
                         base_span.as_mut().unwrap().extend_span(parser_utils.index - 1);
                     } else {
@@ -181,13 +259,17 @@ pub fn parse_implementor(
                 } else {
                     parser_utils.file_name = format!("{}::{}", parser_utils.file_name, base.as_ref().unwrap());
                 }
+                // Trait implementation functions are as visible as the implementation
+                if implementor.is_some() {
+                    inherit_visibility(&mut member_modifiers, get_modifier(&modifiers));
+                }
                 let function = parse_function(parser_utils, false, member_attributes, member_modifiers);
                 functions.push(function?);
                 parser_utils.file_name = file;
                 member_attributes = Vec::default();
                 member_modifiers = Vec::default();
             }
-            TokenTypes::StructTopElement => {}
+            TokenTypes::StructTopElement | TokenTypes::Comment => {}
             TokenTypes::StructEnd | TokenTypes::EOF => break,
             TokenTypes::InvalidCharacters => {
                 return Err(
@@ -242,7 +324,8 @@ pub fn parse_type_generics(parser_utils: &mut ParserUtils) -> Result<Vec<Unparse
                 let name = token.to_string(parser_utils.buffer);
                 current.push(UnparsedType::Basic(name));
             }
-            TokenTypes::GenericsEnd => {
+            // Nested generics end with a bound end
+            TokenTypes::GenericsEnd | TokenTypes::GenericBoundEnd => {
                 break;
             }
             TokenTypes::GenericEnd => {}
@@ -255,6 +338,16 @@ pub fn parse_type_generics(parser_utils: &mut ParserUtils) -> Result<Vec<Unparse
     return Ok(current);
 }
 
+/// Replaces the member's visibility modifiers with its parent's
+fn inherit_visibility(member_modifiers: &mut Vec<Modifier>, parent: u8) {
+    member_modifiers.retain(|modifier| !matches!(modifier, Modifier::Public | Modifier::Protected));
+    for modifier in [Modifier::Public, Modifier::Protected] {
+        if is_modifier(parent, modifier) {
+            member_modifiers.push(modifier);
+        }
+    }
+}
+
 /// Parses the generics and adds them to the generics map
 pub fn parse_generics(parser_utils: &mut ParserUtils, generics: &mut IndexMap<String, Vec<ParsingFuture<Types>>>) {
     let mut name = String::default();
@@ -280,7 +373,7 @@ pub fn parse_generics(parser_utils: &mut ParserUtils, generics: &mut IndexMap<St
             TokenTypes::GenericBound => {
                 let token = parser_utils.tokens.get(parser_utils.index - 1).unwrap();
                 let mut name = token.to_string(parser_utils.buffer);
-                if name.starts_with(':') {
+                if name.starts_with(':') || name.starts_with('+') {
                     name = name[1..].to_string();
                 }
                 let name = name.trim().to_string();
@@ -333,7 +426,7 @@ pub fn parse_bounds(name: String, parser_utils: &mut ParserUtils) -> Option<Unpa
         match token.token_type {
             TokenTypes::Generic | TokenTypes::GenericBound => {
                 let mut name = token.to_string(parser_utils.buffer);
-                if name.starts_with(':') {
+                if name.starts_with(':') || name.starts_with('+') {
                     name = name[1..].to_string();
                 }
                 name = name.trim().to_string();
@@ -380,6 +473,7 @@ pub fn parse_field(
     attributes: Vec<Attribute>,
     modifiers: Vec<Modifier>,
 ) -> ParsingFuture<MemberField> {
+    let span = Span::new(parser_utils.file, parser_utils.index - 1);
     let mut types = None;
     while !parser_utils.tokens.is_empty() {
         let token = &parser_utils.tokens[parser_utils.index];
@@ -395,7 +489,7 @@ pub fn parse_field(
         }
     }
 
-    return Box::pin(to_field(types.unwrap(), attributes, get_modifier(modifiers.as_slice()), name));
+    return Box::pin(to_field(types.unwrap(), attributes, get_modifier(modifiers.as_slice()), name, span));
 }
 
 /// Waits for the type to finish and converts it to a field
@@ -404,6 +498,7 @@ pub async fn to_field(
     attributes: Vec<Attribute>,
     modifier: u8,
     name: String,
+    span: Span,
 ) -> Result<MemberField, ParsingError> {
-    return Ok(MemberField::new(modifier, attributes, Field::new(name, types.await?)));
+    return Ok(MemberField::new(modifier, attributes, Field::new(name, types.await?), span));
 }
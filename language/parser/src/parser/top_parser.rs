@@ -8,7 +8,7 @@ use syntax::program::r#struct::StructData;
 use syntax::{Attribute, Modifier, TopElement, MODIFIERS};
 
 use crate::parser::function_parser::parse_function;
-use crate::parser::struct_parser::{parse_implementor, parse_structure};
+use crate::parser::struct_parser::{parse_enum, parse_implementor, parse_structure};
 use crate::parser::util::ParserUtils;
 
 /// Parses a top element
@@ -30,6 +30,9 @@ pub fn parse_top(parser_utils: &mut ParserUtils) {
             TokenTypes::AttributesStart => parse_attribute(parser_utils, &mut attributes),
             TokenTypes::ModifiersStart => parse_modifier(parser_utils, &mut modifiers),
             TokenTypes::FunctionStart => {
+                // Top level functions aren't in a structure, even if one came before them in the file
+                parser_utils.imports.parent = None;
+                parser_utils.imports.generics.clear();
                 let function = parse_function(parser_utils, false, attributes, modifiers);
                 let function = ParserUtils::add_function(&parser_utils.syntax, parser_utils.file_name.clone(), function);
                 let process_manager = parser_utils.syntax.lock().unwrap().process_manager.cloned();
@@ -60,6 +63,13 @@ pub fn parse_top(parser_utils: &mut ParserUtils) {
                 attributes = Vec::default();
                 modifiers = Vec::default();
             }
+            TokenTypes::EnumStart => {
+                modifiers.push(Modifier::Enum);
+                let structure = parse_enum(parser_utils, attributes, modifiers);
+                parser_utils.add_struct(structure);
+                attributes = Vec::default();
+                modifiers = Vec::default();
+            }
             TokenTypes::ImplStart => {
                 let implementor = parse_implementor(parser_utils, attributes, modifiers);
                 let process_manager = {
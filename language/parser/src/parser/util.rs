@@ -235,7 +235,7 @@ pub fn parse_generics(input: String, parser_utils: &mut ParserUtils) -> (Unparse
                     UnparsedType::Basic(token.to_string(parser_utils.buffer)),
                     Box::pin(Syntax::get_struct(
                         parser_utils.syntax.clone(),
-                        Span::default(),
+                        Span::new(parser_utils.file, parser_utils.index - 1),
                         token.to_string(parser_utils.buffer),
                         Box::new(parser_utils.imports.clone()),
                         vec![],
@@ -302,7 +302,7 @@ fn inner_generic(
                     UnparsedType::Basic(token.to_string(parser_utils.buffer)),
                     Box::pin(Syntax::get_struct(
                         parser_utils.syntax.clone(),
-                        Span::default(),
+                        Span::new(parser_utils.file, parser_utils.index - 1),
                         token.to_string(parser_utils.buffer),
                         Box::new(parser_utils.imports.clone()),
                         vec![],
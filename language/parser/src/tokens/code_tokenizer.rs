@@ -8,6 +8,12 @@ pub fn next_code_token(tokenizer: &mut Tokenizer) -> Token {
         found
     } else if TokenTypes::Period == tokenizer.last.token_type && tokenizer.buffer[tokenizer.index].is_ascii_alphabetic() {
         parse_acceptable(tokenizer, TokenTypes::CallingType)
+    } else if TokenTypes::Period == tokenizer.last.token_type
+        && tokenizer.buffer[tokenizer.index].is_ascii_digit()
+        && tokenizer.buffer[tokenizer.index - 2] != b'.'
+    {
+        // Tuple fields, like the 0 in "pair.0". The second period check keeps ranges like "1..5" as numbers.
+        parse_acceptable(tokenizer, TokenTypes::CallingType)
     } else if tokenizer.matches("{") {
         tokenizer.bracket_depth += 1;
         tokenizer.make_token(TokenTypes::BlockStart)
@@ -27,7 +33,11 @@ pub fn next_code_token(tokenizer: &mut Tokenizer) -> Token {
         }
     } else if tokenizer.matches(".") {
         // This is only a number if the thing before and after is a digit. "1." and ".1" aren't numbers.
-        if tokenizer.buffer[tokenizer.index].is_ascii_digit() && tokenizer.buffer[tokenizer.index - 2].is_ascii_digit() {
+        // Nested tuple fields like "pair.0.1" aren't numbers either.
+        if tokenizer.buffer[tokenizer.index].is_ascii_digit()
+            && tokenizer.buffer[tokenizer.index - 2].is_ascii_digit()
+            && TokenTypes::CallingType != tokenizer.last.token_type
+        {
             tokenizer.index -= 1;
             parse_numbers(tokenizer)
         } else {
@@ -80,6 +90,8 @@ pub fn check_keywords(tokenizer: &mut Tokenizer) -> Option<Token> {
         tokenizer.make_token(TokenTypes::Return)
     } else if tokenizer.matches_word("break") {
         tokenizer.make_token(TokenTypes::Break)
+    } else if tokenizer.matches_word("continue") {
+        tokenizer.make_token(TokenTypes::Continue)
     } else if tokenizer.matches_word("switch") {
         tokenizer.make_token(TokenTypes::Switch)
     } else if tokenizer.matches_word("true") {
@@ -104,6 +116,10 @@ pub fn check_keywords(tokenizer: &mut Tokenizer) -> Option<Token> {
         tokenizer.make_token(TokenTypes::Colon)
     } else if tokenizer.matches_word("let") {
         tokenizer.make_token(TokenTypes::Let)
+    } else if tokenizer.matches_word("mut") {
+        tokenizer.make_token(TokenTypes::Mut)
+    } else if tokenizer.matches("=>") {
+        tokenizer.make_token(TokenTypes::ArmArrow)
     } else if tokenizer.matches("=") {
         tokenizer.make_token(TokenTypes::Equals)
     } else {
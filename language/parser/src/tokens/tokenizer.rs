@@ -1,5 +1,7 @@
 use crate::tokens::code_tokenizer::next_code_token;
-use crate::tokens::top_tokenizer::{next_func_token, next_implementation_token, next_struct_token, next_top_token};
+use crate::tokens::top_tokenizer::{
+    next_enum_token, next_func_token, next_implementation_token, next_struct_token, next_top_token,
+};
 use crate::tokens::util::{next_generic, parse_string};
 use data::tokens::{Token, TokenTypes};
 
@@ -80,12 +82,14 @@ impl<'a> Tokenizer<'a> {
             TokenizerState::TOP_ELEMENT | TokenizerState::TOP_ELEMENT_TO_STRUCT => next_top_token(self),
             TokenizerState::FUNCTION | TokenizerState::FUNCTION_TO_STRUCT_TOP => next_func_token(self),
             TokenizerState::STRUCTURE => next_struct_token(self),
+            TokenizerState::ENUM => next_enum_token(self),
             TokenizerState::IMPLEMENTATION => next_implementation_token(self),
             TokenizerState::STRING | TokenizerState::STRING_TO_CODE_STRUCT_TOP => parse_string(self),
             TokenizerState::CODE | TokenizerState::CODE_TO_STRUCT_TOP => next_code_token(self),
             TokenizerState::GENERIC_TO_IMPL
             | TokenizerState::GENERIC_TO_FUNC
             | TokenizerState::GENERIC_TO_STRUCT
+            | TokenizerState::GENERIC_TO_ENUM
             | TokenizerState::GENERIC_TO_FUNC_TO_STRUCT_TOP => next_generic(self),
             _ => panic!("Unknown state {}!", self.state),
         };
@@ -261,4 +265,8 @@ impl TokenizerState {
     pub const CODE: u64 = 0xC;
     /// A block of code that returns to a program
     pub const CODE_TO_STRUCT_TOP: u64 = 0xD;
+    /// Inside an enum declaration, including its variants
+    pub const ENUM: u64 = 0xE;
+    /// Inside the generic declaration of an enum
+    pub const GENERIC_TO_ENUM: u64 = 0xF;
 }
@@ -1,5 +1,5 @@
 use crate::tokens::tokenizer::{Tokenizer, TokenizerState};
-use crate::tokens::util::{parse_attribute_val, parse_modifier, parse_to_character};
+use crate::tokens::util::{parse_attribute_val, parse_modifier, parse_to_character, parse_type_to_character};
 use data::tokens::{Token, TokenTypes};
 
 /// Handles when the tokenizer isn't in any other state.
@@ -101,6 +101,14 @@ fn get_top_element(tokenizer: &mut Tokenizer) -> Token {
             tokenizer.state = TokenizerState::STRUCTURE;
             tokenizer.make_token(TokenTypes::TraitStart)
         }
+    } else if tokenizer.matches_word("enum") {
+        // Enums can't be inside structures
+        if tokenizer.state == TokenizerState::TOP_ELEMENT_TO_STRUCT {
+            tokenizer.handle_invalid()
+        } else {
+            tokenizer.state = TokenizerState::ENUM;
+            tokenizer.make_token(TokenTypes::EnumStart)
+        }
     } else if tokenizer.matches("impl") {
         // What is being implemented is next, so whitespace is skipped.
         tokenizer.next_included().unwrap_or(0);
@@ -178,7 +186,7 @@ pub fn next_func_token(tokenizer: &mut Tokenizer) -> Token {
                 }
             }
         }
-        TokenTypes::ArgumentTypeSeparator => parse_to_character(tokenizer, TokenTypes::ArgumentType, &[b',', b')']),
+        TokenTypes::ArgumentTypeSeparator => parse_type_to_character(tokenizer, TokenTypes::ArgumentType, &[b',', b')']),
         TokenTypes::ArgumentType => {
             if tokenizer.matches(",") {
                 tokenizer.make_token(TokenTypes::ArgumentSeparator)
@@ -284,3 +292,70 @@ pub fn next_implementation_token(tokenizer: &mut Tokenizer) -> Token {
         token => panic!("How'd you get here? {:?}", token),
     }
 }
+
+/// Gets the next token of an enum, which is its header followed by the variants.
+pub fn next_enum_token(tokenizer: &mut Tokenizer) -> Token {
+    return match tokenizer.last.token_type {
+        TokenTypes::EnumStart => parse_to_character(tokenizer, TokenTypes::Identifier, &[b'{', b'<']),
+        TokenTypes::Identifier | TokenTypes::GenericsEnd => {
+            if tokenizer.matches("<") {
+                tokenizer.state = TokenizerState::GENERIC_TO_ENUM;
+                tokenizer.make_token(TokenTypes::GenericsStart)
+            } else if tokenizer.matches("{") {
+                tokenizer.make_token(TokenTypes::StructTopElement)
+            } else {
+                tokenizer.state = TokenizerState::TOP_ELEMENT;
+                tokenizer.handle_invalid()
+            }
+        }
+        TokenTypes::EnumVariant => {
+            if tokenizer.matches("(") {
+                tokenizer.make_token(TokenTypes::VariantFieldsStart)
+            } else {
+                next_variant(tokenizer)
+            }
+        }
+        TokenTypes::VariantFieldsStart | TokenTypes::VariantField | TokenTypes::ArgumentEnd => {
+            if tokenizer.matches(")") {
+                tokenizer.make_token(TokenTypes::VariantFieldsEnd)
+            } else if tokenizer.last.token_type == TokenTypes::VariantField {
+                if tokenizer.matches(",") {
+                    tokenizer.make_token(TokenTypes::ArgumentEnd)
+                } else {
+                    tokenizer.state = TokenizerState::TOP_ELEMENT;
+                    tokenizer.handle_invalid()
+                }
+            } else {
+                parse_variant_field(tokenizer)
+            }
+        }
+        _ => next_variant(tokenizer),
+    };
+}
+
+/// Gets the start of the next variant, or the end of the enum
+fn next_variant(tokenizer: &mut Tokenizer) -> Token {
+    return if tokenizer.matches("}") {
+        tokenizer.state = TokenizerState::TOP_ELEMENT;
+        tokenizer.make_token(TokenTypes::StructEnd)
+    } else if tokenizer.matches(",") {
+        tokenizer.make_token(TokenTypes::VariantEnd)
+    } else {
+        parse_to_character(tokenizer, TokenTypes::EnumVariant, &[b',', b'(', b'}'])
+    };
+}
+
+/// Parses the type of a variant's field, skipping over any commas in its generics
+fn parse_variant_field(tokenizer: &mut Tokenizer) -> Token {
+    let mut depth = 0;
+    loop {
+        match tokenizer.next_included()? {
+            b'<' => depth += 1,
+            b'>' => depth -= 1,
+            b',' | b')' if depth == 0 => break,
+            _ => {}
+        }
+    }
+    tokenizer.index -= 1;
+    return tokenizer.make_token(TokenTypes::VariantField);
+}
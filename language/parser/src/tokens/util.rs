@@ -9,6 +9,27 @@ pub fn parse_to_character(tokenizer: &mut Tokenizer, token_type: TokenTypes, end
     return tokenizer.make_token(token_type);
 }
 
+/// Parses a type to one of the provided end characters, skipping any inside of brackets so
+/// types like fn(A, B) -> C or Map<A, B> aren't split at their commas.
+pub fn parse_type_to_character(tokenizer: &mut Tokenizer, token_type: TokenTypes, end: &[u8]) -> Token {
+    let mut depth = 0;
+    let mut last = b' ';
+    loop {
+        let found = tokenizer.next_included()?;
+        match found {
+            _ if depth == 0 && end.contains(&found) => break,
+            b'(' | b'<' | b'[' => depth += 1,
+            // The > in -> doesn't close a bracket
+            b'>' if last == b'-' => {}
+            b')' | b'>' | b']' => depth -= 1,
+            _ => {}
+        }
+        last = found;
+    }
+    tokenizer.index -= 1;
+    return tokenizer.make_token(token_type);
+}
+
 /// Parses the value of an attribute
 pub fn parse_attribute_val(tokenizer: &mut Tokenizer, token_type: TokenTypes) -> Token {
     let mut depth = 1;
@@ -165,6 +186,7 @@ pub fn next_generic(tokenizer: &mut Tokenizer) -> Token {
                         TokenizerState::GENERIC_TO_FUNC => TokenizerState::FUNCTION,
                         TokenizerState::GENERIC_TO_FUNC_TO_STRUCT_TOP => TokenizerState::FUNCTION_TO_STRUCT_TOP,
                         TokenizerState::GENERIC_TO_STRUCT => TokenizerState::STRUCTURE,
+                        TokenizerState::GENERIC_TO_ENUM => TokenizerState::ENUM,
                         TokenizerState::GENERIC_TO_IMPL => TokenizerState::IMPLEMENTATION,
                         _ => panic!("Unexpected generic state!"),
                     };
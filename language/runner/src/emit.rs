@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use data::Readable;
+use syntax::program::code::{EffectType, Effects, ExpressionType, FinalizedEffectType, FinalizedEffects};
+use syntax::program::function::{CodeBody, FinalizedCodeBody, FinalizedFunction};
+
+/// Writes the output to the file in the temp folder
+pub fn write(temp_folder: &Path, name: &str, output: String) -> Result<(), String> {
+    fs::create_dir_all(temp_folder).map_err(|error| format!("Failed to create {}: {}", temp_folder.display(), error))?;
+    return fs::write(temp_folder.join(name), output).map_err(|error| format!("Failed to write {}: {}", name, error));
+}
+
+/// Writes every token of the file with the line, column, type and text of each token
+pub fn emit_tokens(name: &str, file: &dyn Readable) -> String {
+    let contents = file.contents();
+    let mut output = format!("file {}\n", name);
+    for token in file.read() {
+        // Some tokens, like the end of the file, are empty
+        let text =
+            if token.end_offset > token.start_offset { token.to_string(contents.as_bytes()) } else { String::default() };
+        writeln!(output, "  {}:{} {:?} {:?}", token.start.0, token.start.1, token.token_type, text).unwrap();
+    }
+    return output;
+}
+
+/// Writes the parsed code of every function, sorted by the function's name
+pub fn emit_ast(parsed: &HashMap<String, CodeBody>) -> String {
+    let mut names = parsed.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut output = String::default();
+    for name in names {
+        writeln!(output, "fn {}", name).unwrap();
+        write_body(&mut output, 1, &parsed[name]);
+    }
+    return output;
+}
+
+/// Writes the checked code of every function, sorted by the function's name
+pub fn emit_checked(mut functions: Vec<Arc<FinalizedFunction>>) -> String {
+    functions.sort_by(|first, second| first.data.name.cmp(&second.data.name));
+    let mut output = String::default();
+    for function in functions {
+        let arguments = function
+            .fields
+            .iter()
+            .map(|field| format!("{}: {}", field.field.name, field.field.field_type))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &function.return_type {
+            Some(returning) => writeln!(output, "fn {}({}) -> {}", function.data.name, arguments, returning).unwrap(),
+            None => writeln!(output, "fn {}({})", function.data.name, arguments).unwrap(),
+        }
+        write_finalized_body(&mut output, 1, &function.code);
+    }
+    return output;
+}
+
+/// Gets the text each line starts with for the expression type
+fn expression_prefix(expression_type: &ExpressionType) -> &'static str {
+    return match expression_type {
+        ExpressionType::Break => "break ",
+        ExpressionType::Return(_) => "return ",
+        ExpressionType::Line => "",
+    };
+}
+
+/// Writes a line indented to the depth
+fn write_line(output: &mut String, depth: usize, line: &str) {
+    writeln!(output, "{}{}", "  ".repeat(depth), line).unwrap();
+}
+
+/// Writes the label and each line of the code body
+fn write_body(output: &mut String, depth: usize, body: &CodeBody) {
+    write_line(output, depth, &format!("{}:", body.label));
+    for expression in &body.expressions {
+        write_effect(output, depth + 1, expression_prefix(&expression.expression_type), &expression.effect);
+    }
+}
+
+/// Writes the effect on one line, then its inner effects on the lines after it one level deeper
+// skipcq: RS-R1000 Match statements have complexity calculated incorrectly
+fn write_effect(output: &mut String, depth: usize, prefix: &str, effect: &Effects) {
+    let mut children: Vec<(String, &Effects)> = Vec::default();
+    let line = match &effect.types {
+        EffectType::NOP => "NOP".to_string(),
+        EffectType::Paren(inner) => {
+            children.push((String::default(), inner));
+            "Paren".to_string()
+        }
+        EffectType::CreateVariable(name, mutable, types, value) => {
+            children.push((String::default(), value));
+            let types = types.as_ref().map_or_else(String::default, |types| format!(": {}", types));
+            format!("CreateVariable {}{}{}", if *mutable { "mut " } else { "" }, name, types)
+        }
+        EffectType::Jump(label) => format!("Jump {}", label),
+        EffectType::CompareJump(comparing, first, second) => {
+            children.push((String::default(), comparing));
+            format!("CompareJump {} {}", first, second)
+        }
+        EffectType::CodeBody(body) => {
+            write_line(output, depth, &format!("{}CodeBody", prefix));
+            write_body(output, depth + 1, body);
+            return;
+        }
+        EffectType::ImplementationCall(calling, traits, function, arguments, returning) => {
+            children.push((String::default(), calling));
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            let returning = returning.as_ref().map_or_else(String::default, |types| format!(" -> {}", types));
+            format!("ImplementationCall {}::{}{}", traits, function, returning)
+        }
+        EffectType::MethodCall(calling, function, arguments, returning) => {
+            children.extend(calling.iter().map(|calling| ("on = ".to_string(), calling.as_ref())));
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            let returning = returning.as_ref().map_or_else(String::default, |(types, _)| format!(" -> {}", types));
+            format!("MethodCall {}{}", function, returning)
+        }
+        EffectType::Set(setting, value) => {
+            children.push((String::default(), setting));
+            children.push((String::default(), value));
+            "Set".to_string()
+        }
+        EffectType::LoadVariable(name) => format!("LoadVariable {}", name),
+        EffectType::Load(from, field) => {
+            children.push((String::default(), from));
+            format!("Load {}", field)
+        }
+        EffectType::Operation(operation, values) => {
+            children.extend(values.iter().map(|value| (String::default(), value)));
+            format!("Operation {}", operation)
+        }
+        EffectType::CreateStruct(types, fields) => {
+            children.extend(fields.iter().map(|(name, value)| (format!("{} = ", name), value)));
+            format!("CreateStruct {}", types)
+        }
+        EffectType::CreateArray(values) => {
+            children.extend(values.iter().map(|value| (String::default(), value)));
+            "CreateArray".to_string()
+        }
+        EffectType::CreateTuple(values) => {
+            children.extend(values.iter().map(|value| (String::default(), value)));
+            "CreateTuple".to_string()
+        }
+        EffectType::Destructure(names, value) => {
+            children.push((String::default(), value));
+            let names = names
+                .iter()
+                .map(|(name, mutable)| if *mutable { format!("mut {}", name) } else { name.clone() })
+                .collect::<Vec<_>>();
+            format!("Destructure ({})", names.join(", "))
+        }
+        EffectType::IsVariant(checking, variant) => {
+            children.push((String::default(), checking));
+            format!("IsVariant {}", variant)
+        }
+        EffectType::Closure(arguments, returning, body) => {
+            let arguments =
+                arguments.iter().map(|(name, types)| format!("{}: {}", name, types)).collect::<Vec<_>>().join(", ");
+            let returning = returning.as_ref().map_or_else(String::default, |types| format!(" -> {}", types));
+            write_line(output, depth, &format!("{}Closure |{}|{}", prefix, arguments, returning));
+            write_body(output, depth + 1, body);
+            return;
+        }
+        EffectType::Float(value) => format!("Float {}", value),
+        EffectType::Int(value) => format!("Int {}", value),
+        EffectType::UInt(value) => format!("UInt {}", value),
+        EffectType::Bool(value) => format!("Bool {}", value),
+        EffectType::Char(value) => format!("Char {:?}", value),
+        EffectType::String(value) => format!("String {:?}", value),
+    };
+
+    write_line(output, depth, &format!("{}{}", prefix, line));
+    for (prefix, child) in children {
+        write_effect(output, depth + 1, &prefix, child);
+    }
+}
+
+/// Writes the label and each line of the finalized code body
+fn write_finalized_body(output: &mut String, depth: usize, body: &FinalizedCodeBody) {
+    write_line(output, depth, &format!("{}:", body.label));
+    for expression in &body.expressions {
+        write_finalized_effect(output, depth + 1, expression_prefix(&expression.expression_type), &expression.effect);
+    }
+}
+
+/// Writes the finalized effect on one line, then its inner effects on the lines after it one level deeper
+// skipcq: RS-R1000 Match statements have complexity calculated incorrectly
+fn write_finalized_effect(output: &mut String, depth: usize, prefix: &str, effect: &FinalizedEffects) {
+    let mut children: Vec<(String, &FinalizedEffects)> = Vec::default();
+    let line = match &effect.types {
+        FinalizedEffectType::NOP => "NOP".to_string(),
+        FinalizedEffectType::CreateVariable(name, value, types) => {
+            children.push((String::default(), value));
+            format!("CreateVariable {}: {}", name, types)
+        }
+        FinalizedEffectType::Jump(label) => format!("Jump {}", label),
+        FinalizedEffectType::CompareJump(comparing, first, second) => {
+            children.push((String::default(), comparing));
+            format!("CompareJump {} {}", first, second)
+        }
+        FinalizedEffectType::CodeBody(body) => {
+            write_line(output, depth, &format!("{}CodeBody", prefix));
+            write_finalized_body(output, depth + 1, body);
+            return;
+        }
+        FinalizedEffectType::MethodCall(calling, function, arguments, returning) => {
+            children.extend(calling.iter().map(|calling| ("on = ".to_string(), calling.as_ref())));
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            let returning = returning.as_ref().map_or_else(String::default, |types| format!(" -> {}", types));
+            format!("MethodCall {}{}", function.data.name, returning)
+        }
+        FinalizedEffectType::GenericMethodCall(function, types, arguments) => {
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            format!("GenericMethodCall {} on {}", function.data.name, types)
+        }
+        FinalizedEffectType::Set(setting, value) => {
+            children.push((String::default(), setting));
+            children.push((String::default(), value));
+            "Set".to_string()
+        }
+        FinalizedEffectType::LoadVariable(name) => format!("LoadVariable {}", name),
+        FinalizedEffectType::Load(from, field, types) => {
+            children.push((String::default(), from));
+            format!("Load {} from {}", field, types)
+        }
+        FinalizedEffectType::CreateStruct(target, types, fields) => {
+            children.extend(target.iter().map(|target| ("at = ".to_string(), target.as_ref())));
+            children.extend(fields.iter().map(|(index, value)| (format!("{} = ", index), value)));
+            format!("CreateStruct {}", types)
+        }
+        FinalizedEffectType::CreateArray(types, values) => {
+            children.extend(values.iter().map(|value| (String::default(), value)));
+            match types {
+                Some(types) => format!("CreateArray {}", types),
+                None => "CreateArray".to_string(),
+            }
+        }
+        FinalizedEffectType::CreateTuple(types, values) => {
+            children.extend(values.iter().map(|value| (String::default(), value)));
+            format!("CreateTuple {}", types)
+        }
+        FinalizedEffectType::CreateVariant(target, types, tag, fields) => {
+            children.push(("at = ".to_string(), target));
+            children.extend(fields.iter().map(|(index, value)| (format!("{} = ", index), value)));
+            format!("CreateVariant {} {}", types, tag)
+        }
+        FinalizedEffectType::IsVariant(checking, tag) => {
+            children.push((String::default(), checking));
+            format!("IsVariant {}", tag)
+        }
+        FinalizedEffectType::Propagate(value, check, residual, unwrapped) => {
+            children.push(("value = ".to_string(), value));
+            children.push(("check = ".to_string(), check));
+            children.push(("residual = ".to_string(), residual));
+            children.push(("unwrapped = ".to_string(), unwrapped));
+            "Propagate".to_string()
+        }
+        FinalizedEffectType::ShortCircuit(first, second, and) => {
+            children.push((String::default(), first));
+            children.push((String::default(), second));
+            format!("ShortCircuit {}", if *and { "&&" } else { "||" })
+        }
+        FinalizedEffectType::CreateClosure(arguments, returning, captures, body) => {
+            let arguments = arguments
+                .iter()
+                .map(|argument| format!("{}: {}", argument.name, argument.field_type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let returning = returning.as_ref().map_or_else(String::default, |types| format!(" -> {}", types));
+            let captures =
+                captures.iter().map(|(name, types)| format!("{}: {}", name, types)).collect::<Vec<_>>().join(", ");
+            write_line(
+                output,
+                depth,
+                &format!("{}CreateClosure |{}|{} capturing [{}]", prefix, arguments, returning, captures),
+            );
+            write_finalized_body(output, depth + 1, body);
+            return;
+        }
+        FinalizedEffectType::FunctionReference(function) => format!("FunctionReference {}", function.data.name),
+        FinalizedEffectType::CallClosure(calling, arguments) => {
+            children.push(("on = ".to_string(), calling));
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            "CallClosure".to_string()
+        }
+        FinalizedEffectType::Float(value, types) => format!("Float {} {}", value, types),
+        FinalizedEffectType::UInt(value, types) => format!("UInt {} {}", value, types),
+        FinalizedEffectType::Bool(value) => format!("Bool {}", value),
+        FinalizedEffectType::String(value) => format!("String {:?}", value),
+        FinalizedEffectType::Char(value) => format!("Char {:?}", value),
+        FinalizedEffectType::VirtualCall(index, function, arguments, returning) => {
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            let returning = returning.as_ref().map_or_else(String::default, |(types, _)| format!(" -> {}", types));
+            format!("VirtualCall {} {}{}", index, function.data.name, returning)
+        }
+        FinalizedEffectType::GenericVirtualCall(index, target, function, arguments, returning) => {
+            children.extend(arguments.iter().map(|argument| (String::default(), argument)));
+            let returning = returning.as_ref().map_or_else(String::default, |(types, _)| format!(" -> {}", types));
+            format!("GenericVirtualCall {} {} from {}{}", index, target.name, function.data.name, returning)
+        }
+        FinalizedEffectType::Downcast(value, types, _) => {
+            children.push((String::default(), value));
+            format!("Downcast {}", types)
+        }
+        FinalizedEffectType::HeapStore(value) => {
+            children.push((String::default(), value));
+            "HeapStore".to_string()
+        }
+        FinalizedEffectType::HeapAllocate(types) => format!("HeapAllocate {}", types),
+        FinalizedEffectType::ReferenceLoad(value) => {
+            children.push((String::default(), value));
+            "ReferenceLoad".to_string()
+        }
+        FinalizedEffectType::StackStore(value) => {
+            children.push((String::default(), value));
+            "StackStore".to_string()
+        }
+        FinalizedEffectType::Free(value) => {
+            children.push((String::default(), value));
+            "Free".to_string()
+        }
+    };
+
+    write_line(output, depth, &format!("{}{}", prefix, line));
+    for (prefix, child) in children {
+        write_finalized_effect(output, depth + 1, &prefix, child);
+    }
+}
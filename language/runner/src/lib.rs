@@ -1,6 +1,7 @@
+use compiler_interpreter::Interpreter;
 use compiler_llvm::LLVMCompiler;
 use dashmap::DashMap;
-use data::CompilerArguments;
+use data::{CompilerArguments, TargetPointer};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -12,19 +13,22 @@ use syntax::program::function::FinalizedFunction;
 use syntax::program::r#struct::FinalizedStruct;
 use syntax::program::syntax::Compiler;
 
+/// Writes the intermediate stages of compilation for debugging
+pub mod emit;
 /// The main Raven runner
 pub mod runner;
 
 /// Gets the compiler given the name and the compiling Arcs (so they can be passed to the compiler)
-pub fn get_compiler<T>(
+pub fn get_compiler<T: TargetPointer>(
     compiling: Arc<DashMap<String, Arc<FinalizedFunction>>>,
     struct_compiling: Arc<DashMap<String, Arc<FinalizedStruct>>>,
     arguments: CompilerArguments,
 ) -> Box<dyn Compiler<T> + Send + Sync> {
-    return Box::new(match arguments.compiler.to_lowercase().as_str() {
-        "llvm" => LLVMCompiler::new(compiling, struct_compiling, arguments),
+    return match arguments.compiler.to_lowercase().as_str() {
+        "llvm" => Box::new(LLVMCompiler::new(compiling, struct_compiling, arguments)),
+        "interpreter" => Box::new(Interpreter::new(compiling, arguments)),
         _ => panic!("Unknown compilers {}", arguments.compiler),
-    });
+    };
 }
 
 /// A future used to wait for the handle to finish
@@ -41,28 +45,21 @@ impl Future for JoinWaiter {
 
         let mut removing = Vec::default();
 
-        let mut i = 0;
-        for mut handle in &mut locked.joining {
-            if handle.is_finished() {
-                removing.push(i);
-                i += 1;
-            } else {
-                match Pin::new(&mut handle).poll(cx) {
-                    Poll::Ready(inner) => match inner {
-                        Ok(result) => {
-                            match result {
-                                Err(error) => return Poll::Ready(Err(error)),
-                                _ => {}
-                            }
-                            removing.push(i);
-                            i += 1;
+        for (i, handle) in locked.joining.iter_mut().enumerate() {
+            // Finished handles are polled too so their errors aren't lost
+            match Pin::new(handle).poll(cx) {
+                Poll::Ready(inner) => match inner {
+                    Ok(result) => {
+                        if let Err(error) = result {
+                            return Poll::Ready(Err(error));
                         }
-                        Err(error) => {
-                            panic!("{}", error);
-                        }
-                    },
-                    Poll::Pending => {}
-                }
+                        removing.push(i);
+                    }
+                    Err(error) => {
+                        panic!("{}", error);
+                    }
+                },
+                Poll::Pending => {}
             }
         }
 
@@ -70,11 +67,23 @@ impl Future for JoinWaiter {
         for found in removing {
             locked.joining.remove(found);
         }
-        return if locked.joining.is_empty() {
-            Poll::Ready(Ok(()))
-        } else {
-            locked.waker = Some(cx.waker().clone());
-            Poll::Pending
-        };
+        if locked.joining.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+
+        // Every task is asleep with nothing left to wake it, so explain what they're stuck on
+        let mut tracker = locked.tracker.lock().unwrap();
+        if tracker.deadlocked() {
+            // Tasks only stuck on a task that failed are finished, the failure's error is returned by the runner
+            return Poll::Ready(match tracker.deadlock_error() {
+                Some(error) => Err(error),
+                None => Ok(()),
+            });
+        }
+        tracker.waker = Some(cx.waker().clone());
+        drop(tracker);
+
+        locked.waker = Some(cx.waker().clone());
+        return Poll::Pending;
     }
 }
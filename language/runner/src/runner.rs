@@ -1,27 +1,35 @@
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::time::Duration;
 
 use anyhow::Error;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::time;
 
 use checker::output::TypesChecker;
-use data::{Arguments, CompilerArguments};
+use data::tokens::Span;
+use data::{Arguments, CompilerArguments, Emit, TargetPointer};
 use parser::parse;
 use syntax::async_util::HandleWrapper;
-use syntax::errors::ParsingError;
-use syntax::program::syntax::Syntax;
+use syntax::errors::{ErrorSource, ParsingError, ParsingMessage};
+use syntax::program::syntax::{SourceFile, Syntax};
 
+use crate::emit::{emit_ast, emit_checked, emit_tokens, write};
 use crate::{get_compiler, JoinWaiter};
 
 /// Runs Raven to completion with the given arguments
-pub async fn run<T: Send + 'static>(settings: &Arguments) -> Result<Option<T>, Vec<ParsingError>> {
+pub async fn run<T: TargetPointer + Send + 'static>(settings: &Arguments) -> Result<Option<T>, Vec<ParsingError>> {
     let handle = Arc::new(Mutex::new(HandleWrapper::new(settings.cpu_runtime.handle().clone())));
     let mut syntax = Syntax::new(Box::new(TypesChecker::new(handle.clone(), settings.runner_settings.include_references())));
     syntax.async_manager.target.clone_from(&settings.runner_settings.compiler_arguments.target);
 
+    let emit = &settings.runner_settings.compiler_arguments.emit;
+    let temp_folder = &settings.runner_settings.compiler_arguments.temp_folder;
+    if emit.contains(&Emit::Ast) {
+        syntax.parsed = Some(HashMap::default());
+    }
+
     let syntax = Arc::new(Mutex::new(syntax));
 
     let (sender, mut receiver) = mpsc::channel(1);
@@ -36,20 +44,35 @@ pub async fn run<T: Send + 'static>(settings: &Arguments) -> Result<Option<T>, V
     ));
 
     let mut handles = Vec::default();
+    let mut tokens = Vec::default();
     // Parses source, getting handles and building into the unresolved syntax.
-    for source_set in &settings.runner_settings.sources {
+    for (project, source_set) in settings.runner_settings.sources.iter().enumerate() {
         for file in source_set.get_files() {
             if !file.path().ends_with("rv") {
                 continue;
             }
 
+            let name = source_set.relative(&*file);
+            let positions = if settings.runner_settings.compiler_arguments.debug_info {
+                file.read().iter().map(|token| token.start).collect()
+            } else {
+                Vec::default()
+            };
+            syntax
+                .lock()
+                .unwrap()
+                .files
+                .insert(file.hash(), SourceFile { name: name.clone(), project, path: file.path(), positions });
+            if emit.contains(&Emit::Tokens) {
+                tokens.push(emit_tokens(&name, &*file));
+            }
             handles.push(
                 settings
                     .io_runtime
                     .as_ref()
                     .map(|inner| inner.handle().clone())
                     .unwrap_or_else(|| settings.cpu_runtime.handle().clone())
-                    .spawn(parse(syntax.clone(), handle.clone(), source_set.relative(&*file).clone(), file)),
+                    .spawn(parse(syntax.clone(), handle.clone(), name, file)),
             );
         }
     }
@@ -57,9 +80,8 @@ pub async fn run<T: Send + 'static>(settings: &Arguments) -> Result<Option<T>, V
     let mut errors = Vec::default();
     //Join any parsing errors
     for handle in handles {
-        match time::timeout(Duration::from_secs(1), handle).await {
-            Err(error) => errors.push(Error::new(error)),
-            Ok(_) => {}
+        if let Err(error) = handle.await {
+            errors.push(Error::new(error));
         }
     }
 
@@ -73,34 +95,54 @@ pub async fn run<T: Send + 'static>(settings: &Arguments) -> Result<Option<T>, V
     syntax.lock().unwrap().finish();
 
     let mut errors = vec![];
-    match time::timeout(Duration::from_secs(1), JoinWaiter { handle: handle.clone() }).await {
-        Ok(error) => match error {
-            Err(error) => {
-                errors.push(error);
-            }
-            _ => {}
-        },
-        Err(_) => {
-            for (name, _) in &handle.lock().unwrap().names {
-                println!("Infinite loop for {}", name);
-            }
-            panic!();
-        }
+    // Finishes once every task is done, or with an error if they're stuck waiting on each other
+    if let Err(error) = (JoinWaiter { handle: handle.clone() }).await {
+        errors.push(error);
+    }
+    syntax.lock().unwrap().finish_checking();
+
+    // Failing to write the emitted files isn't in the code, so it doesn't have a span
+    if let Err(error) = emit_outputs(&syntax, emit, temp_folder, tokens) {
+        errors.push(Span::default().make_error(ParsingMessage::Compiler(error)));
     }
 
     errors.append(&mut syntax.lock().unwrap().errors);
     return if errors.is_empty() {
         go_sender.send(()).await.unwrap();
-        Ok(receiver.recv().await.unwrap())
+        // Compiler errors, like failing to link, aren't in the code so they don't have a span
+        receiver.recv().await.unwrap().map_err(|error| vec![Span::default().make_error(ParsingMessage::Compiler(error))])
     } else {
         Err(errors)
     };
 }
 
+/// Writes the tokens, AST and checked code to the temp folder, if they're being emitted
+fn emit_outputs(
+    syntax: &Arc<Mutex<Syntax>>,
+    emit: &[Emit],
+    temp_folder: &Path,
+    mut tokens: Vec<String>,
+) -> Result<(), String> {
+    // Files and functions are sorted so the output is the same every run
+    if emit.contains(&Emit::Tokens) {
+        tokens.sort();
+        write(temp_folder, "tokens.txt", tokens.concat())?;
+    }
+    if let Some(parsed) = &syntax.lock().unwrap().parsed {
+        write(temp_folder, "ast.txt", emit_ast(parsed))?;
+    }
+    if emit.contains(&Emit::Checked) {
+        let compiling = syntax.lock().unwrap().compiling.clone();
+        let functions = compiling.iter().map(|pair| pair.value().clone()).collect::<Vec<_>>();
+        write(temp_folder, "checked.txt", emit_checked(functions))?;
+    }
+    return Ok(());
+}
+
 /// Runs the compiler, waiting for the receiver before running the main function then sending the result on the sender.
-pub async fn start<T>(
+pub async fn start<T: TargetPointer>(
     compiler_arguments: CompilerArguments,
-    sender: Sender<Option<T>>,
+    sender: Sender<Result<Option<T>, String>>,
     receiver: Receiver<()>,
     syntax: Arc<Mutex<Syntax>>,
 ) {
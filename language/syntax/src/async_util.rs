@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
 use std::hash::Hash;
@@ -6,7 +6,7 @@ use std::ops::DerefMut;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::task::{Context, Poll, Waker};
+use std::task::{Context, Poll, Wake, Waker};
 
 use tokio::runtime::Handle;
 use tokio::task::{AbortHandle, JoinHandle};
@@ -14,7 +14,7 @@ use tokio::task::{AbortHandle, JoinHandle};
 use data::tokens::Span;
 
 use crate::errors::{ErrorSource, ParsingMessage};
-use crate::program::function::display_parenless;
+use crate::program::function::{display_parenless, FinalizedFunction};
 use crate::program::syntax::Syntax;
 use crate::program::types::FinalizedTypes;
 use crate::{FinishedStructImplementor, ParsingError, TopElement};
@@ -82,6 +82,14 @@ impl<T: TopElement> AsyncTypesGetter<T> {
         //Look for a program of that name
         if let Some(found) = getting.types.get(&name).cloned() {
             if !not_trait || !found.is_trait() {
+                // The target is run from outside the program, so it doesn't have to be public
+                if name != locked.async_manager.target {
+                    if let Err(error) =
+                        locked.check_visible(found.name(), found.modifiers(), found.get_span(), &self.error.span)
+                    {
+                        return Some(Err(error));
+                    }
+                }
                 self.finished = Some(found.clone());
                 return Some(Ok(found));
             }
@@ -177,6 +185,7 @@ impl<T: TopElement> Future for AsyncTypesGetter<T> {
         }
 
         // Parsing isn't finished, so this sleeps.
+        wait_on(self.getting.clone(), &self.error.span);
         return Poll::Pending;
     }
 }
@@ -208,6 +217,7 @@ where
 
         // The finalized element doesn't exist, sleep.
         manager.wakers.entry(self.getting.name().clone()).or_insert(vec![]).push(cx.waker().clone());
+        wait_on(self.getting.name().clone(), self.getting.get_span());
 
         // This never panics because as long as the data exists, every element will be finalized.
         return Poll::Pending;
@@ -233,6 +243,31 @@ impl Future for AsyncStructImplGetter {
     }
 }
 
+/// Future for finding the main function, shared by every compiler
+pub struct MainFuture {
+    /// Program syntax
+    pub syntax: Arc<Mutex<Syntax>>,
+}
+
+impl Future for MainFuture {
+    type Output = Option<Arc<FinalizedFunction>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut locked = self.syntax.lock().unwrap();
+        if let Some(found) = locked.compiling.get(&locked.async_manager.target) {
+            return Poll::Ready(Some(found.clone()));
+        }
+
+        // Once everything is checked, the target failed to check and will never be found
+        if locked.async_manager.checked {
+            return Poll::Ready(None);
+        }
+        locked.async_manager.target_waker = Some(cx.waker().clone());
+        wait_on(locked.async_manager.target.clone(), &Span::default());
+        return Poll::Pending;
+    }
+}
+
 /// A type that hasn't been parsed yet, used for types that need to be clonable before they're finalized.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum UnparsedType {
@@ -302,16 +337,26 @@ pub struct HandleWrapper {
     pub names: HashMap<String, AbortHandle>,
     /// A waker to wake when finished with a task
     pub waker: Option<Waker>,
+    /// Tracks which tasks are blocked to find deadlocks
+    pub tracker: Arc<Mutex<TaskTracker>>,
 }
 
 impl HandleWrapper {
     /// Creates a new handle wrapper
     pub fn new(handle: Handle) -> HandleWrapper {
-        return HandleWrapper { handle, joining: vec![], names: HashMap::default(), waker: None };
+        return HandleWrapper {
+            handle,
+            joining: vec![],
+            names: HashMap::default(),
+            waker: None,
+            tracker: Arc::new(Mutex::new(TaskTracker::default())),
+        };
     }
     /// Spawns a task and adds it to the joining vec
     pub fn spawn<F: Future<Output = Result<(), ParsingError>> + Send + 'static>(&mut self, name: String, future: F) {
-        let handle = self.handle.spawn(future);
+        // The task is tracked as soon as it's spawned so it's never missed when checking for deadlocks
+        let id = self.tracker.lock().unwrap().add_task(name.clone());
+        let handle = self.handle.spawn(TrackedTask { id, tracker: self.tracker.clone(), future: Box::pin(future) });
         self.names.insert(name, handle.abort_handle());
 
         self.joining.push(handle);
@@ -325,3 +370,212 @@ impl HandleWrapper {
         }
     }
 }
+
+tokio::task_local! {
+    /// The id and tracker of the task being polled, so getters can record what it's waiting on
+    static CURRENT_TASK: (u64, Arc<Mutex<TaskTracker>>);
+}
+
+/// Records what the current task is waiting on, which is used to explain deadlocks.
+/// Does nothing outside of tasks spawned by the HandleWrapper.
+pub fn wait_on(name: String, span: &Span) {
+    let _ = CURRENT_TASK.try_with(|(id, tracker)| {
+        tracker.lock().unwrap().waiting.insert(*id, WaitingOn { name, span: span.clone() });
+    });
+}
+
+/// Marks the current task as failed, after its error was added to the program's errors.
+/// Anything waiting on it will never finish, which is a result of that error instead of a deadlock.
+pub fn fail_task() {
+    let _ = CURRENT_TASK.try_with(|(id, tracker)| {
+        let mut tracker = tracker.lock().unwrap();
+        if let Some(name) = tracker.tasks.get(id).cloned() {
+            tracker.failed.insert(name);
+        }
+    });
+}
+
+/// What a task is waiting on
+#[derive(Clone, Debug)]
+pub struct WaitingOn {
+    /// The name of the type, function or operation being waited on
+    pub name: String,
+    /// Where it's needed
+    pub span: Span,
+}
+
+/// Tracks every task spawned by the HandleWrapper, finding when all of them are asleep with nothing left to wake them.
+/// Wakers are wrapped, so a task is only blocked if it slept without being woken since.
+#[derive(Default)]
+pub struct TaskTracker {
+    /// The id of the last task added
+    last_id: u64,
+    /// The names of the running tasks
+    pub tasks: HashMap<u64, String>,
+    /// The tasks that are asleep and haven't been woken
+    pub blocked: HashSet<u64>,
+    /// The tasks woken while they were being polled
+    woken: HashSet<u64>,
+    /// What each task last waited on
+    pub waiting: HashMap<u64, WaitingOn>,
+    /// The names of tasks that failed with an error
+    pub failed: HashSet<String>,
+    /// Woken when every task is blocked
+    pub waker: Option<Waker>,
+}
+
+impl TaskTracker {
+    /// Adds a running task, returning its id
+    fn add_task(&mut self, name: String) -> u64 {
+        self.last_id += 1;
+        self.tasks.insert(self.last_id, name);
+        return self.last_id;
+    }
+
+    /// Removes a finished task
+    fn remove_task(&mut self, id: u64) {
+        self.tasks.remove(&id);
+        self.blocked.remove(&id);
+        self.woken.remove(&id);
+        self.waiting.remove(&id);
+        self.wake_if_deadlocked();
+    }
+
+    /// If every running task is blocked, in which case none of them can ever finish
+    pub fn deadlocked(&self) -> bool {
+        return !self.tasks.is_empty() && self.blocked.len() == self.tasks.len();
+    }
+
+    /// Wakes the waker if every task is blocked
+    fn wake_if_deadlocked(&self) {
+        if self.deadlocked() {
+            if let Some(waker) = &self.waker {
+                waker.wake_by_ref();
+            }
+        }
+    }
+
+    /// Explains the deadlock by following what each task waits on, starting from the first task,
+    /// until it finds a cycle or a name that no task will ever finish.
+    /// Tasks stuck because of a failed task are left out, since that error was already reported,
+    /// so this returns None if nothing else is blocked.
+    pub fn deadlock_error(&self) -> Option<ParsingError> {
+        let mut blocked = self.blocked.iter().copied().filter(|id| !self.waits_on_failed(*id)).collect::<Vec<_>>();
+        if blocked.is_empty() {
+            return None;
+        }
+        blocked.sort();
+
+        let mut path: Vec<u64> = Vec::default();
+        let mut current = blocked[0];
+        loop {
+            if let Some(start) = path.iter().position(|found| *found == current) {
+                let mut names = path[start..].iter().map(|id| self.tasks[id].clone()).collect::<Vec<_>>();
+                names.push(names[0].clone());
+                let span = self.waiting.get(&path[start]).map(|waiting| waiting.span.clone()).unwrap_or_default();
+                return Some(span.make_error(ParsingMessage::Deadlock(names)));
+            }
+            path.push(current);
+
+            let waiting = match self.waiting.get(&current) {
+                Some(waiting) => waiting,
+                None => {
+                    return Some(Span::default().make_error(ParsingMessage::Deadlock(vec![self.tasks[&current].clone()])))
+                }
+            };
+            current = match blocked.iter().find(|id| self.tasks[id] == waiting.name) {
+                Some(found) => *found,
+                None => return Some(waiting.span.make_error(ParsingMessage::FailedToFind(waiting.name.clone()))),
+            };
+        }
+    }
+
+    /// Checks if the task is waiting on a failed task, directly or through other blocked tasks
+    fn waits_on_failed(&self, id: u64) -> bool {
+        let mut seen = HashSet::new();
+        let mut current = id;
+        while seen.insert(current) {
+            let waiting = match self.waiting.get(&current) {
+                Some(waiting) => waiting,
+                None => return false,
+            };
+            if self.failed.contains(&waiting.name) {
+                return true;
+            }
+            current = match self.blocked.iter().find(|id| self.tasks[id] == waiting.name) {
+                Some(found) => *found,
+                None => return false,
+            };
+        }
+        return false;
+    }
+}
+
+/// A task spawned by the HandleWrapper, which tells the tracker whenever it's blocked or woken.
+struct TrackedTask<F> {
+    /// The task's id
+    id: u64,
+    /// The tracker
+    tracker: Arc<Mutex<TaskTracker>>,
+    /// The task
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future<Output = Result<(), ParsingError>>> Future for TrackedTask<F> {
+    type Output = Result<(), ParsingError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut tracker = self.tracker.lock().unwrap();
+            tracker.blocked.remove(&self.id);
+            tracker.woken.remove(&self.id);
+            tracker.waiting.remove(&self.id);
+        }
+
+        let waker =
+            Waker::from(Arc::new(TrackedWaker { id: self.id, tracker: self.tracker.clone(), inner: cx.waker().clone() }));
+        let current = (self.id, self.tracker.clone());
+        let output = CURRENT_TASK.sync_scope(current, || self.future.as_mut().poll(&mut Context::from_waker(&waker)));
+
+        let mut tracker = self.tracker.lock().unwrap();
+        match output {
+            Poll::Ready(_) => tracker.remove_task(self.id),
+            Poll::Pending => {
+                // If it was woken while being polled, it's going to be polled again
+                if !tracker.woken.remove(&self.id) {
+                    tracker.blocked.insert(self.id);
+                    tracker.wake_if_deadlocked();
+                }
+            }
+        }
+        return output;
+    }
+}
+
+/// Wraps a task's waker to unblock the task in the tracker
+struct TrackedWaker {
+    /// The task's id
+    id: u64,
+    /// The tracker
+    tracker: Arc<Mutex<TaskTracker>>,
+    /// The task's waker
+    inner: Waker,
+}
+
+impl Wake for TrackedWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        {
+            let mut tracker = self.tracker.lock().unwrap();
+            // Finished tasks can still be woken by old wakers
+            if tracker.tasks.contains_key(&self.id) {
+                tracker.blocked.remove(&self.id);
+                tracker.woken.insert(self.id);
+            }
+        }
+        self.inner.wake_by_ref();
+    }
+}
@@ -14,32 +14,49 @@ pub enum ParsingMessage {
     UnexpectedIf(),
     UnexpectedElse(),
     UnexpectedFor(),
+    UnexpectedBreak(),
+    UnexpectedContinue(),
+    UnexpectedPropagate(),
     UnexpectedToken(),
     UnexpectedSymbol(),
     UnexpectedVoid(),
+    UnexpectedBlock(),
     UnexpectedTopElement(),
     UnexpectedReturnType(FinalizedTypes, FinalizedTypes),
     ExpectedEffect(),
     ExpectedCodeBlock(),
     ExpectedVariableName(),
+    UnknownVariable(String),
+    Immutable(String),
+    MismatchedDestructure(usize, FinalizedTypes),
     ExpectedIn(),
     ExpectedWhile(),
+    ExpectedSwitchArm(),
+    ExpectedClosureArgument(),
     ExtraSymbol(),
     SelfInStatic(),
     FailedToFind(String),
+    Deadlock(Vec<String>),
     UnexpectedCharacters(),
     DuplicateStructure(),
     DuplicateFunction(),
     UnknownField(String),
+    DuplicateVariant(String),
+    UnknownVariant(String, FinalizedTypes),
     IncorrectBoundsLength(),
     MismatchedTypes(FinalizedTypes, FinalizedTypes),
     UnknownOperation(),
     UnknownFunction(),
     MissingArgument(),
+    NotAFunction(FinalizedTypes),
+    MissingReturn(String),
+    UninferredReturn(String),
     AmbiguousMethod(String),
     NoMethod(String, FinalizedTypes),
     NoImpl(FinalizedTypes, String),
     NoTraitImpl(FinalizedTypes, FinalizedTypes),
+    NotVisible(String, String),
+    Compiler(String),
 }
 
 impl Display for ParsingMessage {
@@ -52,9 +69,15 @@ impl Display for ParsingMessage {
             ParsingMessage::UnexpectedIf() => write!(f, "Unexpected if! Did you forget a semicolon?"),
             ParsingMessage::UnexpectedElse() => write!(f, "Unexpected else!"),
             ParsingMessage::UnexpectedFor() => write!(f, "Unexpected for! Did you forget a semicolon?"),
+            ParsingMessage::UnexpectedBreak() => write!(f, "Unexpected break outside of a loop!"),
+            ParsingMessage::UnexpectedContinue() => write!(f, "Unexpected continue outside of a loop!"),
+            ParsingMessage::UnexpectedPropagate() => write!(f, "Can't propagate out of a function that returns nothing!"),
             ParsingMessage::UnexpectedToken() => write!(f, "Unexpected token, expected variable name!"),
             ParsingMessage::UnexpectedSymbol() => write!(f, "Unexpected symbol, expected equals!"),
             ParsingMessage::UnexpectedVoid() => write!(f, "Expected a value, found void!"),
+            ParsingMessage::UnexpectedBlock() => {
+                write!(f, "Unexpected block! Blocks need a control statement, like if or while")
+            }
             ParsingMessage::UnexpectedTopElement() => write!(f, "Unexpected top element!"),
             ParsingMessage::UnexpectedReturnType(expected, gotten) => {
                 write!(f, "Unexpected return type! Expected a {} but found {}", fix_type(expected), fix_type(gotten))
@@ -62,15 +85,39 @@ impl Display for ParsingMessage {
             ParsingMessage::ExpectedEffect() => write!(f, "Expected an effect!"),
             ParsingMessage::ExpectedCodeBlock() => write!(f, "Expected a code block!"),
             ParsingMessage::ExpectedVariableName() => write!(f, "Expected a variable name!"),
+            ParsingMessage::UnknownVariable(name) => {
+                write!(f, "Unknown variable {}! Variables can only be used in the block they're declared in", name)
+            }
+            ParsingMessage::Immutable(name) => {
+                write!(f, "{} is changed but isn't mutable, declare it with mut to change it!", name)
+            }
+            ParsingMessage::MismatchedDestructure(names, found) => {
+                write!(f, "Can't destructure {} into {} variables, expected a tuple with that many values!", found, names)
+            }
             ParsingMessage::ExpectedWhile() => write!(f, "Expected a while!"),
             ParsingMessage::ExpectedIn() => write!(f, "Missing \"in\" in for loop."),
+            ParsingMessage::ExpectedSwitchArm() => write!(f, "Expected a switch arm, like \"pattern => {{ ... }}\"!"),
+            ParsingMessage::ExpectedClosureArgument() => {
+                write!(f, "Expected a closure argument, like \"|value: i64| -> i64 {{ ... }}\"!")
+            }
             ParsingMessage::ExtraSymbol() => write!(f, "Extra symbol!"),
             ParsingMessage::SelfInStatic() => write!(f, "self in static function!"),
             ParsingMessage::FailedToFind(name) => write!(f, "Failed to find type {}, did you import it correctly?", name),
+            ParsingMessage::Deadlock(names) => {
+                if names.len() == 1 {
+                    write!(f, "{} is waiting on something that will never finish!", names[0])
+                } else {
+                    write!(f, "Deadlock found, {} forever!", names.join(" waits on "))
+                }
+            }
             ParsingMessage::UnexpectedCharacters() => write!(f, "Unexpected characters!"),
             ParsingMessage::DuplicateStructure() => write!(f, "Duplicate structure!"),
             ParsingMessage::DuplicateFunction() => write!(f, "Duplicate function!"),
             ParsingMessage::UnknownField(field) => write!(f, "Unknown field {}!", field),
+            ParsingMessage::DuplicateVariant(variant) => write!(f, "Duplicate variant {}!", variant),
+            ParsingMessage::UnknownVariant(variant, types) => {
+                write!(f, "Unknown variant {} of {}!", variant, fix_type(types))
+            }
             ParsingMessage::IncorrectBoundsLength() => write!(f, "Incorrect bounds length!"),
             ParsingMessage::MismatchedTypes(found, bound) => {
                 write!(f, "{} isn't of type {}", fix_type(found), fix_type(bound))
@@ -78,6 +125,15 @@ impl Display for ParsingMessage {
             ParsingMessage::UnknownOperation() => write!(f, "Unknown operation!"),
             ParsingMessage::UnknownFunction() => write!(f, "Unknown function!"),
             ParsingMessage::MissingArgument() => write!(f, "Incorrect arguments length!"),
+            ParsingMessage::NotAFunction(types) => write!(f, "Can't call {}, it isn't a function!", fix_type(types)),
+            ParsingMessage::MissingReturn(name) => write!(f, "Not every code path in {} returns a value!", name),
+            ParsingMessage::UninferredReturn(name) => {
+                write!(
+                    f,
+                    "{} is used before any of its returns, so its return type can't be inferred! Try declaring it",
+                    name
+                )
+            }
             ParsingMessage::AmbiguousMethod(name) => write!(f, "Ambiguous method {}!", name),
             ParsingMessage::NoMethod(name, types) => write!(f, "No method {} for generic {}", name, fix_type(types)),
             ParsingMessage::NoImpl(base, method) => {
@@ -86,6 +142,10 @@ impl Display for ParsingMessage {
             ParsingMessage::NoTraitImpl(base, traits) => {
                 write!(f, "No implementation of {} for {}", fix_type(traits), fix_type(base))
             }
+            ParsingMessage::NotVisible(name, file) => {
+                write!(f, "{} is declared in {} and isn't visible here, is it missing pub?", name, file)
+            }
+            ParsingMessage::Compiler(message) => write!(f, "{}", message),
         };
     }
 }
@@ -125,6 +185,12 @@ impl ParsingError {
 
     /// Prints the error to console
     pub fn print(&self, sources: &Vec<Box<dyn SourceSet>>) {
+        // Compiler errors aren't in any file
+        if let ParsingMessage::Compiler(message) = &self.message {
+            println!("{}", message);
+            return;
+        }
+
         let mut file = None;
         'outer: for source in sources {
             for readable in source.get_files() {
@@ -56,7 +56,8 @@ use data::tokens::Span;
 pub type ParsingFuture<T> = Pin<Box<dyn Future<Output = Result<T, ParsingError>> + Send + Sync>>;
 
 /// All the modifiers, used for modifier parsing and debug output.
-pub static MODIFIERS: [Modifier; 4] = [Modifier::Public, Modifier::Protected, Modifier::Extern, Modifier::Internal];
+/// pub(proj) starts with pub, so it has to be checked first.
+pub static MODIFIERS: [Modifier; 4] = [Modifier::Protected, Modifier::Public, Modifier::Extern, Modifier::Internal];
 
 /// All the modifiers structures/functions/fields can have
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -71,6 +72,10 @@ pub enum Modifier {
     Internal = 0b1000,
     /// Hidden from the user, only used internally
     Trait = 0b1_0000,
+    /// Hidden from the user, marks structures declared as enums
+    Enum = 0b10_0000,
+    /// Marks function arguments that can be changed
+    Mut = 0b100_0000,
 }
 
 impl Display for Modifier {
@@ -81,6 +86,8 @@ impl Display for Modifier {
             Modifier::Extern => write!(f, "extern"),
             Modifier::Internal => write!(f, "internal"),
             Modifier::Trait => panic!("Shouldn't display trait modifier!"),
+            Modifier::Enum => panic!("Shouldn't display enum modifier!"),
+            Modifier::Mut => write!(f, "mut"),
         };
     }
 }
@@ -216,15 +223,20 @@ pub trait ProcessManager: Send + Sync {
 pub struct SimpleVariableManager {
     /// The variables and their type
     pub variables: HashMap<String, FinalizedTypes>,
+    /// The variables that can't be changed and where they were declared
+    pub immutable: HashMap<String, Span>,
 }
 
 impl SimpleVariableManager {
     /// Gets the variable manager for the function, filling in the function parameters
     pub fn for_function(codeless: &CodelessFinalizedFunction) -> Self {
-        let mut variable_manager = SimpleVariableManager { variables: HashMap::default() };
+        let mut variable_manager = SimpleVariableManager { variables: HashMap::default(), immutable: HashMap::default() };
 
         for field in &codeless.arguments {
             variable_manager.variables.insert(field.field.name.clone(), field.field.field_type.clone());
+            if !is_modifier(field.modifiers, Modifier::Mut) {
+                variable_manager.immutable.insert(field.field.name.clone(), field.span.clone());
+            }
         }
 
         return variable_manager;
@@ -232,10 +244,13 @@ impl SimpleVariableManager {
 
     /// Gets the variable manager for the function, filling in the function parameters
     pub fn for_final_function(codeless: &FinalizedFunction) -> Self {
-        let mut variable_manager = SimpleVariableManager { variables: HashMap::default() };
+        let mut variable_manager = SimpleVariableManager { variables: HashMap::default(), immutable: HashMap::default() };
 
         for field in &codeless.fields {
             variable_manager.variables.insert(field.field.name.clone(), field.field.field_type.clone());
+            if !is_modifier(field.modifiers, Modifier::Mut) {
+                variable_manager.immutable.insert(field.field.name.clone(), field.span.clone());
+            }
         }
 
         return variable_manager;
@@ -273,6 +288,9 @@ where
     /// Span
     fn get_span(&self) -> &Span;
 
+    /// The element's modifiers
+    fn modifiers(&self) -> u8;
+
     /// Whether the top element is a function and has the operator modifier
     fn is_operator(&self) -> bool;
 
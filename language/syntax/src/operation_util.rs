@@ -1,3 +1,4 @@
+use crate::async_util::wait_on;
 use crate::program::r#struct::StructData;
 use crate::program::syntax::Syntax;
 use crate::ParsingError;
@@ -43,6 +44,7 @@ impl Future for OperationGetter {
                 locked.operation_wakers.insert(operation.clone(), vec![cx.waker().clone()]);
             }
         }
+        wait_on(self.operation.join(" or "), &self.error.span);
 
         return Poll::Pending;
     }
@@ -4,7 +4,7 @@ use data::tokens::Span;
 
 use crate::async_util::UnparsedType;
 use crate::program::function::{CodeBody, CodelessFinalizedFunction, FinalizedCodeBody, FunctionData};
-use crate::program::r#struct::{BOOL, CHAR, F64, STR, U64};
+use crate::program::r#struct::{BOOL, CHAR, STR};
 use crate::program::types::{FinalizedTypes, Types};
 use crate::{Attribute, VariableManager};
 
@@ -26,6 +26,11 @@ pub struct FinalizedExpression {
     pub effect: FinalizedEffects,
 }
 
+/// The label break statements jump to until the parser links them to their loop's end.
+pub const BREAK_LABEL: &str = "$break";
+/// The label continue statements jump to until the parser links them to their loop's header.
+pub const CONTINUE_LABEL: &str = "$continue";
+
 /// the types of expressions: a normal line, a return, or a break (for inside control statements).
 #[derive(Clone, Debug)]
 pub enum ExpressionType {
@@ -64,6 +69,8 @@ pub struct MemberField {
     pub attributes: Vec<Attribute>,
     /// The field itself
     pub field: Field,
+    /// Where the field was declared
+    pub span: Span,
 }
 
 /// A finalized member field.
@@ -75,6 +82,8 @@ pub struct FinalizedMemberField {
     pub attributes: Vec<Attribute>,
     /// The field itself
     pub field: FinalizedField,
+    /// Where the field was declared
+    pub span: Span,
 }
 
 impl PartialEq for ExpressionType {
@@ -90,8 +99,8 @@ impl PartialEq for ExpressionType {
 
 impl MemberField {
     /// Creates a new field
-    pub fn new(modifiers: u8, attributes: Vec<Attribute>, field: Field) -> Self {
-        return Self { modifiers, attributes, field };
+    pub fn new(modifiers: u8, attributes: Vec<Attribute>, field: Field, span: Span) -> Self {
+        return Self { modifiers, attributes, field, span };
     }
 }
 
@@ -140,8 +149,8 @@ pub enum EffectType {
     NOP,
     /// An effect wrapped in parenthesis, just a wrapper around the effect to prevent issues with operator merging.
     Paren(Box<Effects>),
-    /// Creates a variable with the given name and value.
-    CreateVariable(String, Box<Effects>),
+    /// Creates a variable with the given name, whether it's mutable, type (if given) and value.
+    CreateVariable(String, bool, Option<UnparsedType>, Box<Effects>),
     /// Label of jumping to body
     Jump(String),
     /// Comparison effect, and label to jump to the first if true, second if false
@@ -166,6 +175,14 @@ pub enum EffectType {
     CreateStruct(UnparsedType, Vec<(String, Effects)>),
     /// Creates an array of the given effects.
     CreateArray(Vec<Effects>),
+    /// Creates a tuple of the given effects.
+    CreateTuple(Vec<Effects>),
+    /// Creates a variable for each value of the tuple, with the variables' names and whether they're mutable.
+    Destructure(Vec<(String, bool)>, Box<Effects>),
+    /// Checks if the enum is the variant with the given name, used by switch arms.
+    IsVariant(Box<Effects>, String),
+    /// A closure with its arguments' names and types, its return type (if any) and its code.
+    Closure(Vec<(String, UnparsedType)>, Option<UnparsedType>, CodeBody),
     /// A float
     Float(f64),
     /// An integer
@@ -223,10 +240,28 @@ pub enum FinalizedEffectType {
     CreateStruct(Option<Box<FinalizedEffects>>, FinalizedTypes, Vec<(usize, FinalizedEffects)>),
     /// Create an array with the type and values
     CreateArray(Option<FinalizedTypes>, Vec<FinalizedEffects>),
-    /// Creates a float
-    Float(f64),
-    /// Creates an unsigned int
-    UInt(u64),
+    /// Creates a tuple of the given type on the heap with the values.
+    CreateTuple(FinalizedTypes, Vec<FinalizedEffects>),
+    /// Creates an enum at the given reference, of the given type and variant tag with a tuple of the index of the field and the argument.
+    CreateVariant(Box<FinalizedEffects>, FinalizedTypes, usize, Vec<(usize, FinalizedEffects)>),
+    /// Checks if the enum has the given variant tag
+    IsVariant(Box<FinalizedEffects>, usize),
+    /// Propagates a value out of the function, like the "?" operator. Stores the value in a variable,
+    /// then returns the residual if the check is true, or else evaluates to the unwrapped value.
+    Propagate(Box<FinalizedEffects>, Box<FinalizedEffects>, Box<FinalizedEffects>, Box<FinalizedEffects>),
+    /// A boolean && (if true) or || (if false), which only runs the second effect if the first doesn't decide the result.
+    ShortCircuit(Box<FinalizedEffects>, Box<FinalizedEffects>, bool),
+    /// Creates a closure with the given arguments, return type, captured variables and code.
+    /// Compiles to a function pointer and an environment struct holding the captured variables.
+    CreateClosure(Vec<FinalizedField>, Option<FinalizedTypes>, Vec<(String, FinalizedTypes)>, FinalizedCodeBody),
+    /// Gets a function as a function value, so it can be passed around like a closure.
+    FunctionReference(Arc<CodelessFinalizedFunction>),
+    /// Calls a function value (a closure or function reference) with the given arguments.
+    CallClosure(Box<FinalizedEffects>, Vec<FinalizedEffects>),
+    /// Creates a float of the given type
+    Float(f64, FinalizedTypes),
+    /// Creates an integer of the given type
+    UInt(u64, FinalizedTypes),
     /// Creates a boolean
     Bool(bool),
     /// Creates a string
@@ -255,6 +290,8 @@ pub enum FinalizedEffectType {
     ReferenceLoad(Box<FinalizedEffects>),
     /// Stores an effect on the stack.
     StackStore(Box<FinalizedEffects>),
+    /// Frees the heap allocation the effect points to, inserted by the checker when an owned variable goes out of scope.
+    Free(Box<FinalizedEffects>),
 }
 
 impl FinalizedEffectType {
@@ -262,7 +299,7 @@ impl FinalizedEffectType {
     /// This can only be called on degenericed types and as such can be sync
     pub fn get_nongeneric_return(&self, variables: &dyn VariableManager) -> Option<FinalizedTypes> {
         return match self {
-            Self::NOP | Self::Jump(_) | Self::CompareJump(_, _, _) | Self::CodeBody(_) => None,
+            Self::NOP | Self::Jump(_) | Self::CompareJump(_, _, _) | Self::CodeBody(_) | Self::Free(_) => None,
             // Downcasts simply return the downcasting target.
             Self::CreateVariable(_, _, types) | Self::Downcast(_, types, _) => Some(types.clone()),
             Self::MethodCall(_, function, _, _)
@@ -280,24 +317,43 @@ impl FinalizedEffectType {
                 panic!("Unresolved variable {} from {:?}", name, variables);
             }
             // Gets the type of the field in the program with that name.
-            Self::Load(_, name, loading) => loading
-                .inner_struct()
-                .fields
-                .iter()
-                .find(|field| &field.field.name == name)
-                .map(|field| field.field.field_type.clone()),
+            Self::Load(_, name, loading) => loading.get_field_type(name),
             // Returns the program type.
-            Self::CreateStruct(_, types, _) => Some(FinalizedTypes::Reference(Box::new(types.clone()))),
+            Self::CreateStruct(_, types, _) | Self::CreateVariant(_, types, _, _) | Self::CreateTuple(types, _) => {
+                Some(FinalizedTypes::Reference(Box::new(types.clone())))
+            }
             // Returns the internal constant type.
-            Self::Float(_) => Some(FinalizedTypes::Struct(F64.clone())),
-            Self::UInt(_) => Some(FinalizedTypes::Struct(U64.clone())),
-            Self::Bool(_) => Some(FinalizedTypes::Struct(BOOL.clone())),
+            Self::Float(_, types) | Self::UInt(_, types) => Some(types.clone()),
+            Self::Bool(_) | Self::IsVariant(_, _) | Self::ShortCircuit(_, _, _) => {
+                Some(FinalizedTypes::Struct(BOOL.clone()))
+            }
             Self::String(_) => Some(FinalizedTypes::Struct(STR.clone())),
             Self::Char(_) => Some(FinalizedTypes::Struct(CHAR.clone())),
             // Stores just return their inner type.
             Self::HeapStore(inner) | Self::StackStore(inner) | Self::Set(_, inner) => {
                 inner.types.get_nongeneric_return(variables)
             }
+            // Propagating returns the unwrapped value.
+            Self::Propagate(_, _, _, unwrapped) => unwrapped.types.get_nongeneric_return(variables),
+            // Closures and function references return a function type.
+            Self::CreateClosure(arguments, returning, _, _) => {
+                Some(FinalizedTypes::Reference(Box::new(FinalizedTypes::Function(
+                    arguments.iter().map(|argument| argument.field_type.clone()).collect(),
+                    returning.clone().map(Box::new),
+                ))))
+            }
+            Self::FunctionReference(function) => Some(FinalizedTypes::Reference(Box::new(FinalizedTypes::Function(
+                function.arguments.iter().map(|argument| argument.field.field_type.clone()).collect(),
+                function.return_type.clone().map(Box::new),
+            )))),
+            // Calling a function value returns its return type.
+            Self::CallClosure(calling, _) => calling
+                .types
+                .get_nongeneric_return(variables)?
+                .inner_function()?
+                .1
+                .as_ref()
+                .map(|inner| FinalizedTypes::Reference(inner.clone())),
             // References return their inner type as well.
             Self::ReferenceLoad(inner) => match inner.types.get_nongeneric_return(variables).unwrap() {
                 FinalizedTypes::Reference(inner) => Some(*inner),
@@ -372,6 +428,12 @@ impl FinalizedEffectType {
                     effect.types.degeneric(process_manager, variables, syntax, span).await?;
                 }
             }
+            Self::CreateTuple(types, effects) => {
+                types.degeneric(process_manager.generics(), syntax).await;
+                for effect in effects {
+                    effect.types.degeneric(process_manager, variables, syntax, span).await?;
+                }
+            }
             Self::HeapAllocate(target) | Self::Downcast(_, target, _) => {
                 target.degeneric(process_manager.generics(), syntax).await
             }
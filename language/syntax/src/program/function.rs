@@ -54,6 +54,10 @@ impl TopElement for FunctionData {
         return &self.span;
     }
 
+    fn modifiers(&self) -> u8 {
+        return self.modifiers;
+    }
+
     fn is_operator(&self) -> bool {
         return false;
     }
@@ -94,6 +98,9 @@ impl TopElement for FunctionData {
 
         // Get the codeless finalized function and the code from the function.
         let (codeless_function, code) = process_manager.verify_func(current, &syntax).await;
+        if let Some(parsed) = &mut syntax.lock().unwrap().parsed {
+            parsed.insert(name.clone(), code.clone());
+        }
 
         // Finalize the code and combine it with the codeless finalized function.
         let finalized_function = process_manager.verify_code(codeless_function.clone(), code, resolver, &syntax).await;
@@ -116,6 +116,8 @@ pub struct StructData {
     pub attributes: Vec<Attribute>,
     /// The program's functions, if it's a trait
     pub functions: Vec<Arc<FunctionData>>,
+    /// The names of the enum's variants, in the order of their tags. Empty if it's not an enum.
+    pub variants: Vec<String>,
     /// The program's errors
     pub poisoned: Vec<ParsingError>,
 }
@@ -151,7 +153,7 @@ pub struct FinalizedStruct {
 
 impl Hash for FinalizedStruct {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.data.name.hash(state);
+        self.base_name().hash(state);
     }
 }
 
@@ -169,27 +171,26 @@ impl PartialEq for StructData {
 
 impl PartialEq for FinalizedStruct {
     fn eq(&self, other: &Self) -> bool {
-        if self.data.name.starts_with("collections::array_list") && other.data.name.starts_with("collections::array_list") {
-            println!("{}", self.data.name.split('$').next().unwrap());
-        }
-        return self.data.name.splitn(2, '$').next().unwrap() == other.data.name.splitn(2, '$').next().unwrap();
+        return self.base_name() == other.base_name();
     }
 }
 
 static mut ID: AtomicU64 = AtomicU64::new(0);
 
 impl StructData {
-    /// creates an empty struct data, usually for internal structs
+    /// creates an empty struct data, usually for internal structs.
+    /// They aren't declared in any file, so they're public to be visible everywhere.
     pub fn empty(name: String) -> Self {
         let id = unsafe { ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst) };
         return Self {
             attributes: Vec::default(),
             chalk_data: Self::get_chalk_data(id, 0),
             id,
-            modifiers: Modifier::Internal as u8,
+            modifiers: Modifier::Internal as u8 | Modifier::Public as u8,
             name,
             span: Span::default(),
             functions: Vec::default(),
+            variants: Vec::default(),
             poisoned: Vec::default(),
         };
     }
@@ -212,6 +213,7 @@ impl StructData {
             name,
             span,
             functions,
+            variants: Vec::default(),
             poisoned: Vec::default(),
         };
     }
@@ -265,6 +267,34 @@ impl FinalizedStruct {
     pub fn empty_of(data: StructData) -> Self {
         return Self { generics: IndexMap::default(), fields: Vec::default(), data: Arc::new(data) };
     }
+
+    /// The name of the struct without its generics, so degenericed structs like "HashMap$u64_u64"
+    /// or "Iter<u64>" hash the same as and are equal to their base struct
+    pub fn base_name(&self) -> &str {
+        return self.data.name.split(['$', '<']).next().unwrap();
+    }
+
+    /// Gets the indexes of the enum variant's fields, in order
+    pub fn variant_fields(&self, variant: &str) -> Vec<usize> {
+        let prefix = format!("{}.", variant);
+        return self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.field.name.starts_with(&prefix))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// The most fields any of the enum's variants has, which is the size of the enum's payload
+    pub fn payload_size(&self) -> usize {
+        return self.data.variants.iter().map(|variant| self.variant_fields(variant).len()).max().unwrap_or(0);
+    }
+}
+
+/// The name of an enum variant's field, which can't be accessed like a normal field
+pub fn variant_field(variant: &str, index: usize) -> String {
+    return format!("{}.{}", variant, index);
 }
 
 #[async_trait]
@@ -276,6 +306,10 @@ impl TopElement for StructData {
         return &self.span;
     }
 
+    fn modifiers(&self) -> u8 {
+        return self.modifiers;
+    }
+
     fn is_operator(&self) -> bool {
         return self.is_trait() && Attribute::find_attribute("operation", &self.attributes).is_some();
     }
@@ -24,7 +24,7 @@ pub use data::Main;
 use crate::async_util::{AsyncStructImplGetter, AsyncTypesGetter, NameResolver, UnparsedType};
 use crate::chalk_interner::ChalkIr;
 use crate::errors::{ErrorSource, ParsingMessage};
-use crate::program::function::{FinalizedFunction, FunctionData};
+use crate::program::function::{CodeBody, FinalizedFunction, FunctionData};
 use crate::program::r#struct::{FinalizedStruct, StructData, BOOL, F32, F64, I16, I32, I64, I8, STR, U16, U32, U64, U8};
 use crate::program::types::FinalizedTypes;
 use crate::top_element_manager::{GetterManager, TopElementManager};
@@ -65,6 +65,22 @@ pub struct Syntax {
     pub operation_wakers: HashMap<String, Vec<Waker>>,
     /// Manages the next steps of compilation after parsing
     pub process_manager: Box<dyn ProcessManager>,
+    /// Every source file, by the hash of the file, used to check visibility
+    pub files: HashMap<u64, SourceFile>,
+    /// The code of every function before it's checked, only kept if it's being emitted
+    pub parsed: Option<HashMap<String, CodeBody>>,
+}
+
+/// A source file of the program
+pub struct SourceFile {
+    /// The name of the file, like "collections::array_list"
+    pub name: String,
+    /// The index of the source set the file is in, each source set is its own project
+    pub project: usize,
+    /// The path to the file
+    pub path: String,
+    /// The line and column each token starts at, only kept if debug info is being emitted
+    pub positions: Vec<(u32, u32)>,
 }
 
 impl Syntax {
@@ -97,9 +113,44 @@ impl Syntax {
             operations: HashMap::default(),
             operation_wakers: HashMap::default(),
             process_manager,
+            files: HashMap::default(),
+            parsed: None,
         };
     }
 
+    /// Checks if an element with the modifiers declared at the span can be accessed from the other span.
+    /// Public elements are visible everywhere, pub(proj) ones in their project, and the rest in their file.
+    /// Spans outside of a known file can't be checked, so only public elements are visible from them.
+    pub fn is_visible(&self, modifiers: u8, declared: &Span, accessing: &Span) -> bool {
+        if is_modifier(modifiers, Modifier::Public) {
+            return true;
+        }
+
+        let (declared_file, accessing_file) = match (self.files.get(&declared.file), self.files.get(&accessing.file)) {
+            (Some(declared_file), Some(accessing_file)) => (declared_file, accessing_file),
+            _ => return false,
+        };
+
+        if is_modifier(modifiers, Modifier::Protected) {
+            return declared_file.project == accessing_file.project;
+        }
+        return declared.file == accessing.file;
+    }
+
+    /// Errors if the element named name, with the modifiers declared at the span, can't be accessed from the other span
+    pub fn check_visible(&self, name: &str, modifiers: u8, declared: &Span, accessing: &Span) -> Result<(), ParsingError> {
+        if !self.is_visible(modifiers, declared, accessing) {
+            return Err(self.not_visible(name, declared, accessing));
+        }
+        return Ok(());
+    }
+
+    /// Makes the error for accessing an element that isn't visible
+    fn not_visible(&self, name: &str, declared: &Span, accessing: &Span) -> ParsingError {
+        let file = self.files.get(&declared.file).map_or_else(String::default, |file| file.name.clone());
+        return accessing.make_error(ParsingMessage::NotVisible(name.to_string(), file));
+    }
+
     /// Adds a function to the compiling list
     pub async fn add_compiling(
         process_manager: Box<dyn ProcessManager>,
@@ -151,6 +202,14 @@ impl Syntax {
         }
     }
 
+    /// Sets every function to be checked, waking the target's waker so a missing target can be detected.
+    pub fn finish_checking(&mut self) {
+        self.async_manager.checked = true;
+        if let Some(waker) = self.async_manager.target_waker.take() {
+            waker.wake();
+        }
+    }
+
     /// Checks if the implementations are finished parsing.
     pub fn finished_impls(&self) -> bool {
         return self.async_manager.finished && self.async_manager.parsing_impls == 0;
@@ -273,6 +332,8 @@ impl Syntax {
                 // References are unwrapped and the inner is checked.
                 self.solve_nonstruct_types(inner, checking)
             }
+            // Functions and tuples can't implement traits, so they're only ever equal to the same type.
+            FinalizedTypes::Function(_, _) | FinalizedTypes::Tuple(_) => Some(target_type.of_type_sync(checking, None).0),
             _ => None,
         };
     }
@@ -425,6 +486,49 @@ impl Syntax {
             )));
         }
 
+        // Handles function types by getting each argument type and the return type
+        if getting.starts_with("fn(") {
+            let (arguments, returning) = Self::split_function_type(&getting);
+            let mut argument_types = Vec::default();
+            for argument in arguments {
+                argument_types.push(
+                    Self::get_struct(
+                        syntax.clone(),
+                        error.clone(),
+                        argument,
+                        name_resolver.boxed_clone(),
+                        resolved_generics.clone(),
+                    )
+                    .await?,
+                );
+            }
+            let returning = match returning {
+                Some(returning) => {
+                    Some(Box::new(Self::get_struct(syntax, error, returning, name_resolver, resolved_generics).await?))
+                }
+                None => None,
+            };
+            return Ok(Types::Function(argument_types, returning));
+        }
+
+        // Handles tuple types by getting each value's type
+        if getting.starts_with('(') {
+            let mut types = Vec::default();
+            for found in Self::split_type_list(&getting, 1).0 {
+                types.push(
+                    Self::get_struct(
+                        syntax.clone(),
+                        error.clone(),
+                        found,
+                        name_resolver.boxed_clone(),
+                        resolved_generics.clone(),
+                    )
+                    .await?,
+                );
+            }
+            return Ok(Types::Tuple(types));
+        }
+
         // Checks if the type is a generic type
         if let Some(found) = name_resolver.generic(&getting) {
             let mut bounds = Vec::default();
@@ -454,6 +558,47 @@ impl Syntax {
         return Ok(Types::Struct(AsyncTypesGetter::new(syntax, error, getting, name_resolver, false).await?));
     }
 
+    /// Splits a function type like "fn(A, B) -> C" into its argument type names and return type name.
+    fn split_function_type(getting: &str) -> (Vec<String>, Option<String>) {
+        let (arguments, i) = Self::split_type_list(getting, 3);
+
+        // Everything after the arrow is the return type.
+        let returning = getting[i + 1..].trim();
+        let returning = returning.strip_prefix("->").map(|inner| inner.trim().to_string());
+        return (arguments, returning);
+    }
+
+    /// Splits the comma separated type names starting at the given index up to the closing parenthesis,
+    /// returning them and the index of the parenthesis.
+    fn split_type_list(getting: &str, start: usize) -> (Vec<String>, usize) {
+        let input = getting.as_bytes();
+        let mut arguments = Vec::default();
+        let mut depth = 0;
+        let mut last = start;
+        let mut i = start;
+        while i < input.len() {
+            match input[i] {
+                b'(' | b'<' | b'[' => depth += 1,
+                // The > in -> isn't a closing bracket
+                b'>' if input[i - 1] == b'-' => {}
+                b')' | b'>' | b']' if depth > 0 => depth -= 1,
+                b',' if depth == 0 => {
+                    arguments.push(getting[last..i].trim().to_string());
+                    last = i + 1;
+                }
+                b')' => {
+                    if !getting[last..i].trim().is_empty() {
+                        arguments.push(getting[last..i].trim().to_string());
+                    }
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        return (arguments, i);
+    }
+
     /// Parses generic bounds on a type, returning the length parsed and the types found.
     /// TODO should probably be mostly moved to the tokenizer
     #[async_recursion]
@@ -562,6 +707,6 @@ impl Syntax {
 #[async_trait]
 pub trait Compiler<T> {
     /// Compiles the target function and returns the main runner.
-    /// Waits for the receiver before calling any of the code
-    async fn compile(&self, receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Option<T>;
+    /// Waits for the receiver before calling any of the code, and errors if the output can't be written
+    async fn compile(&self, receiver: Receiver<()>, syntax: &Arc<Mutex<Syntax>>) -> Result<Option<T>, String>;
 }
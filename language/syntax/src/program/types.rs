@@ -2,6 +2,7 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::future::Future;
+use std::ops::Deref;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -37,6 +38,10 @@ pub enum Types {
     Generic(String, Vec<Types>),
     /// An array
     Array(Box<Types>),
+    /// A function value, with its argument types and optional return type. For example, fn(i64) -> bool.
+    Function(Vec<Types>, Option<Box<Types>>),
+    /// A tuple of values, like (i64, bool)
+    Tuple(Vec<Types>),
 }
 
 ///A type with a reference to the finalized program instead of the data.
@@ -52,6 +57,10 @@ pub enum FinalizedTypes {
     Generic(String, Vec<FinalizedTypes>),
     /// An array
     Array(Box<FinalizedTypes>),
+    /// A function value, with its argument types and optional return type
+    Function(Vec<FinalizedTypes>, Option<Box<FinalizedTypes>>),
+    /// A tuple of values
+    Tuple(Vec<FinalizedTypes>),
 }
 
 impl Types {
@@ -61,6 +70,7 @@ impl Types {
             Types::Struct(structs) => structs.name.clone(),
             Types::Reference(structs) => structs.name(),
             Types::Array(types) => format!("[{}]", types.name()),
+            Types::Function(_, _) | Types::Tuple(_) => self.to_string(),
             Types::Generic(_, _) => panic!("Generics should never be named"),
             Types::GenericType(_, _) => panic!("Generics should never be named"),
         };
@@ -73,6 +83,14 @@ impl Types {
             Types::Struct(structs) => FinalizedTypes::Struct(AsyncDataGetter::new(syntax, structs.clone()).await),
             Types::Reference(structs) => FinalizedTypes::Reference(Box::new(structs.finalize(syntax).await)),
             Types::Array(inner) => FinalizedTypes::Array(Box::new(inner.finalize(syntax).await)),
+            Types::Function(arguments, returning) => FinalizedTypes::Function(
+                Self::finalize_all(syntax.clone(), arguments).await,
+                match returning {
+                    Some(returning) => Some(Box::new(returning.finalize(syntax).await)),
+                    None => None,
+                },
+            ),
+            Types::Tuple(types) => FinalizedTypes::Tuple(Self::finalize_all(syntax, types).await),
             Types::Generic(name, bounds) => FinalizedTypes::Generic(name.clone(), Self::finalize_all(syntax, bounds).await),
             Types::GenericType(base, bounds) => FinalizedTypes::GenericType(
                 Box::new(base.finalize(syntax.clone()).await),
@@ -111,6 +129,24 @@ impl FinalizedTypes {
         };
     }
 
+    /// Gets the type of the field with the given name, or of the value at that index for tuples
+    pub fn get_field_type(&self, name: &str) -> Option<FinalizedTypes> {
+        if let Some(types) = self.inner_tuple() {
+            // Tuple values are stored like struct fields, as references
+            return name
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| types.get(index))
+                .map(|found| FinalizedTypes::Reference(Box::new(found.clone())));
+        }
+        return self
+            .inner_struct()
+            .fields
+            .iter()
+            .find(|field| field.field.name == name)
+            .map(|field| field.field.field_type.clone());
+    }
+
     /// Finds all methods with the name from the type
     pub fn find_method(&self, name: &String) -> Option<Vec<(FinalizedTypes, Arc<FunctionData>)>> {
         return match self {
@@ -137,7 +173,7 @@ impl FinalizedTypes {
                     Some(output)
                 }
             }
-            FinalizedTypes::Array(_) => None,
+            FinalizedTypes::Array(_) | FinalizedTypes::Function(_, _) | FinalizedTypes::Tuple(_) => None,
         };
     }
 
@@ -170,6 +206,21 @@ impl FinalizedTypes {
             }
             FinalizedTypes::Reference(inner) => inner.to_chalk_type(binders),
             FinalizedTypes::Array(inner) => TyKind::Slice(inner.to_chalk_type(binders)).intern(ChalkIr),
+            // Functions are represented as a tuple of their arguments followed by their return type.
+            FinalizedTypes::Function(arguments, returning) => {
+                let mut types = Vec::default();
+                for argument in arguments.iter().chain(returning.iter().map(|inner| inner.deref())) {
+                    types.push(GenericArgData::Ty(argument.to_chalk_type(binders)).intern(ChalkIr));
+                }
+                TyKind::Tuple(types.len(), Substitution::from_iter(ChalkIr, types)).intern(ChalkIr)
+            }
+            FinalizedTypes::Tuple(types) => {
+                let types = types
+                    .iter()
+                    .map(|inner| GenericArgData::Ty(inner.to_chalk_type(binders)).intern(ChalkIr))
+                    .collect::<Vec<_>>();
+                TyKind::Tuple(types.len(), Substitution::from_iter(ChalkIr, types)).intern(ChalkIr)
+            }
             FinalizedTypes::Generic(name, _bounds) => {
                 let index = binders.iter().position(|found| *found == name).unwrap();
                 TyKind::BoundVar(BoundVar { debruijn: DebruijnIndex::INNERMOST, index }).intern(ChalkIr)
@@ -209,6 +260,24 @@ impl FinalizedTypes {
         };
     }
 
+    /// Gets the argument and return types of a function type, ignoring references
+    pub fn inner_function(&self) -> Option<(&Vec<FinalizedTypes>, &Option<Box<FinalizedTypes>>)> {
+        return match self {
+            FinalizedTypes::Function(arguments, returning) => Some((arguments, returning)),
+            FinalizedTypes::Reference(inner) => inner.inner_function(),
+            _ => None,
+        };
+    }
+
+    /// Gets the types of a tuple's values, ignoring references
+    pub fn inner_tuple(&self) -> Option<&Vec<FinalizedTypes>> {
+        return match self {
+            FinalizedTypes::Tuple(types) => Some(types),
+            FinalizedTypes::Reference(inner) => inner.inner_tuple(),
+            _ => None,
+        };
+    }
+
     /// Gets the inner generic type from a type
     pub fn inner_generic_type(&self) -> Option<(&Box<FinalizedTypes>, &Vec<FinalizedTypes>)> {
         return match self {
@@ -283,7 +352,7 @@ impl FinalizedTypes {
                 FinalizedTypes::GenericType(base, _) => self.of_type_sync(base, syntax),
                 // References are ignored for type checking.
                 FinalizedTypes::Reference(inner) => self.of_type_sync(inner, syntax),
-                FinalizedTypes::Array(_) => (false, None),
+                FinalizedTypes::Array(_) | FinalizedTypes::Function(_, _) | FinalizedTypes::Tuple(_) => (false, None),
             },
             FinalizedTypes::Array(inner) => match other {
                 // Check the inner type.
@@ -341,18 +410,100 @@ impl FinalizedTypes {
                 FinalizedTypes::Struct(_) => base.of_type_sync(other, syntax),
                 // References are ignored for type checking.
                 FinalizedTypes::Reference(inner) => self.of_type_sync(inner, syntax),
-                FinalizedTypes::Array(_) => (false, None),
+                FinalizedTypes::Array(_) | FinalizedTypes::Function(_, _) | FinalizedTypes::Tuple(_) => (false, None),
+            },
+            FinalizedTypes::Function(arguments, returning) => match other {
+                FinalizedTypes::Function(other_arguments, other_returning) => {
+                    if arguments.len() != other_arguments.len() || returning.is_some() != other_returning.is_some() {
+                        return (false, None);
+                    }
+
+                    // Every argument and the return type must match exactly.
+                    let mut fails = Vec::default();
+                    for (found, other_found) in arguments
+                        .iter()
+                        .chain(returning.iter().map(|inner| inner.deref()))
+                        .zip(other_arguments.iter().chain(other_returning.iter().map(|inner| inner.deref())))
+                    {
+                        let (result, future) = found.of_type_sync(other_found, syntax.clone());
+                        if !result {
+                            if let Some(found) = future {
+                                fails.push(found);
+                            } else {
+                                return (false, None);
+                            }
+                        }
+                    }
+                    if !fails.is_empty() {
+                        return (false, Some(Box::pin(Self::join(fails))));
+                    }
+                    (true, None)
+                }
+                // References are ignored for type checking.
+                FinalizedTypes::Reference(other) => self.of_type_sync(other, syntax),
+                // Only functions can equal functions
+                _ => (false, None),
+            },
+            FinalizedTypes::Tuple(types) => match other {
+                FinalizedTypes::Tuple(other_types) => {
+                    if types.len() != other_types.len() {
+                        return (false, None);
+                    }
+
+                    // Every value must match.
+                    let mut fails = Vec::default();
+                    for (found, other_found) in types.iter().zip(other_types) {
+                        let (result, future) = found.of_type_sync(other_found, syntax.clone());
+                        if !result {
+                            if let Some(found) = future {
+                                fails.push(found);
+                            } else {
+                                return (false, None);
+                            }
+                        }
+                    }
+                    if !fails.is_empty() {
+                        return (false, Some(Box::pin(Self::join(fails))));
+                    }
+                    (true, None)
+                }
+                FinalizedTypes::Generic(_, bounds) => {
+                    // If any bounds fail, the type isn't of the generic.
+                    let mut fails = Vec::default();
+                    for bound in bounds {
+                        let (result, future) = self.of_type_sync(bound, syntax.clone());
+                        if !result {
+                            if let Some(found) = future {
+                                fails.push(found);
+                            } else {
+                                return (false, None);
+                            }
+                        }
+                    }
+                    if !fails.is_empty() {
+                        return (false, Some(Box::pin(Self::join(fails))));
+                    }
+                    (true, None)
+                }
+                // References are ignored for type checking.
+                FinalizedTypes::Reference(other) => self.of_type_sync(other, syntax),
+                // Only tuples can equal tuples
+                _ => (false, None),
             },
             // References are ignored for type checking.
             FinalizedTypes::Reference(referencing) => referencing.of_type_sync(other, syntax),
             FinalizedTypes::Generic(_, bounds) => match other {
                 FinalizedTypes::Generic(_, other_bounds) => {
+                    // Generics without bounds, like a struct's field types, are checked once they're degenericed
+                    if bounds.is_empty() {
+                        return (true, None);
+                    }
                     let mut outer_fails: Vec<Pin<Box<dyn Future<Output = bool> + Send + Sync>>> = Vec::default();
-                    // For two generics to be the same, each bound must match at least one other bound.
-                    'outer: for bound in bounds {
+                    // A generic fits another generic if each of the other's bounds is matched by one of its bounds.
+                    'outer: for other_bound in other_bounds {
                         let mut fails = Vec::default();
-                        for other_bound in other_bounds {
-                            let (result, failure) = other_bound.of_type_sync(bound, syntax.clone());
+                        for bound in bounds {
+                            let (result, failure) = bound.of_type_sync(other_bound, syntax.clone());
                             if result {
                                 continue 'outer;
                             } else if let Some(found) = failure {
@@ -372,7 +523,11 @@ impl FinalizedTypes {
                     (true, None)
                 }
                 FinalizedTypes::Reference(inner) => self.of_type_sync(inner, syntax),
-                FinalizedTypes::Struct(_) | FinalizedTypes::GenericType(_, _) | FinalizedTypes::Array(_) => {
+                FinalizedTypes::Struct(_)
+                | FinalizedTypes::GenericType(_, _)
+                | FinalizedTypes::Array(_)
+                | FinalizedTypes::Function(_, _)
+                | FinalizedTypes::Tuple(_) => {
                     let mut fails = Vec::default();
                     for bound in bounds {
                         let (result, failure) = bound.of_type_sync(other, syntax.clone());
@@ -424,14 +579,22 @@ impl FinalizedTypes {
         }
         match self {
             FinalizedTypes::Generic(name, bounds) => {
+                // Generics, like a field's type inside a generic impl, are checked once they're degenericed
+                let mut resolved = other;
+                while let FinalizedTypes::Reference(inner) = resolved {
+                    resolved = inner;
+                }
+                let unresolved = matches!(resolved, FinalizedTypes::Generic(_, _));
+
                 // Check for bound errors.
-                for bound in bounds {
+                for bound in bounds.iter().filter(|_| !unresolved) {
                     if !other.of_type(bound, syntax.clone()).await {
                         return Err(bounds_error.make_error(ParsingMessage::MismatchedTypes(other.clone(), bound.clone())));
                     }
                 }
 
-                generics.insert(name.clone(), other.clone());
+                // References are how values are passed around, not part of the type, so "[T]" is the same for every value
+                generics.insert(name.clone(), resolved.clone());
             }
             FinalizedTypes::GenericType(base, bounds) => {
                 let mut other = other;
@@ -449,6 +612,25 @@ impl FinalizedTypes {
                     for i in 0..bounds.len() {
                         bounds[i].resolve_generic(&other_bounds[i], syntax, generics, bounds_error.clone()).await?;
                     }
+                } else if let FinalizedTypes::Struct(other) = other {
+                    // Flattened types, like "HashMap<u64, u64>", only hold their generics in their fields
+                    let base = base.inner_struct();
+                    if other.data.name.split('<').next().unwrap() != base.data.name {
+                        return Ok(());
+                    }
+                    let mut found = HashMap::default();
+                    for (field, other_field) in base.fields.iter().zip(&other.fields) {
+                        field
+                            .field
+                            .field_type
+                            .resolve_generic(&other_field.field.field_type, syntax, &mut found, bounds_error.clone())
+                            .await?;
+                    }
+                    for (bound, name) in bounds.iter().zip(base.generics.keys()) {
+                        if let Some(other_bound) = found.get(name) {
+                            bound.resolve_generic(other_bound, syntax, generics, bounds_error.clone()).await?;
+                        }
+                    }
                 }
             }
             // Ignore references.
@@ -468,6 +650,47 @@ impl FinalizedTypes {
 
                 return Err(bounds_error.make_error(ParsingMessage::MismatchedTypes(other.clone(), *inner.clone())));
             }
+            FinalizedTypes::Function(arguments, returning) => {
+                let mut other = other;
+                // Ignore references.
+                while let FinalizedTypes::Reference(inner) = other {
+                    other = inner;
+                }
+
+                if let FinalizedTypes::Function(other_arguments, other_returning) = other {
+                    if arguments.len() == other_arguments.len() {
+                        for i in 0..arguments.len() {
+                            arguments[i]
+                                .resolve_generic(&other_arguments[i], syntax, generics, bounds_error.clone())
+                                .await?;
+                        }
+                        if let (Some(returning), Some(other_returning)) = (returning, other_returning) {
+                            returning.resolve_generic(other_returning, syntax, generics, bounds_error).await?;
+                        }
+                        return Ok(());
+                    }
+                }
+
+                return Err(bounds_error.make_error(ParsingMessage::MismatchedTypes(other.clone(), self.clone())));
+            }
+            FinalizedTypes::Tuple(types) => {
+                let mut other = other;
+                // Ignore references.
+                while let FinalizedTypes::Reference(inner) = other {
+                    other = inner;
+                }
+
+                if let FinalizedTypes::Tuple(other_types) = other {
+                    if types.len() == other_types.len() {
+                        for (found, other_found) in types.iter().zip(other_types) {
+                            found.resolve_generic(other_found, syntax, generics, bounds_error.clone()).await?;
+                        }
+                        return Ok(());
+                    }
+                }
+
+                return Err(bounds_error.make_error(ParsingMessage::MismatchedTypes(other.clone(), self.clone())));
+            }
             _ => {}
         }
         return Ok(());
@@ -479,6 +702,7 @@ impl FinalizedTypes {
             FinalizedTypes::Struct(structs) => structs.data.name.clone(),
             FinalizedTypes::Reference(structs) => structs.name(),
             FinalizedTypes::Array(inner) => format!("[{}]", inner.name()),
+            FinalizedTypes::Function(_, _) | FinalizedTypes::Tuple(_) => self.to_string(),
             FinalizedTypes::Generic(name, _) => {
                 panic!("Generics should never be named, tried to get {}", name)
             }
@@ -486,6 +710,22 @@ impl FinalizedTypes {
         };
     }
 
+    /// Finds the tag of the enum variant with the given name, or None if the type isn't an enum with that variant.
+    pub fn get_variant(&self, name: &str) -> Option<usize> {
+        return self.inner_struct_safe()?.data.variants.iter().position(|variant| variant == name);
+    }
+
+    /// Checks if the type (or the type it references) is a primitive number.
+    /// Primitives are kept on the stack and returned by value instead of being allocated on the heap.
+    pub fn is_primitive(&self) -> bool {
+        return match self.name_safe() {
+            Some(name) => {
+                matches!(name.as_str(), "i64" | "i32" | "i16" | "i8" | "u64" | "u32" | "u16" | "u8" | "f64" | "f32" | "bool")
+            }
+            None => false,
+        };
+    }
+
     /// The name of the function, not erroring if the name can't be gotten
     /// Can be used to check if a type is generic or not
     pub fn name_safe(&self) -> Option<String> {
@@ -493,6 +733,24 @@ impl FinalizedTypes {
             FinalizedTypes::Struct(structs) => Some(structs.data.name.clone()),
             FinalizedTypes::Reference(structs) => structs.name_safe(),
             FinalizedTypes::Array(inner) => inner.name_safe().map(|inner| format!("[{}]", inner)),
+            FinalizedTypes::Function(arguments, returning) => {
+                let mut names = Vec::default();
+                for argument in arguments {
+                    names.push(argument.name_safe()?);
+                }
+                let returning = match returning {
+                    Some(returning) => format!(" -> {}", returning.name_safe()?),
+                    None => String::default(),
+                };
+                Some(format!("fn({}){}", names.join(", "), returning))
+            }
+            FinalizedTypes::Tuple(types) => {
+                let mut names = Vec::default();
+                for found in types {
+                    names.push(found.name_safe()?);
+                }
+                Some(format!("({})", names.join(", ")))
+            }
             FinalizedTypes::Generic(_, _) => None,
             FinalizedTypes::GenericType(_, _) => None,
         };
@@ -505,6 +763,14 @@ impl Display for Types {
             Types::Struct(structure) => write!(f, "{}", structure.name),
             Types::Reference(structure) => write!(f, "{}", structure),
             Types::Array(inner) => write!(f, "[{}]", inner),
+            Types::Function(arguments, returning) => {
+                write!(f, "fn({})", display_function_arguments(arguments))?;
+                if let Some(returning) = returning {
+                    write!(f, " -> {}", returning)?;
+                }
+                Ok(())
+            }
+            Types::Tuple(types) => write!(f, "({})", display_function_arguments(types)),
             Types::Generic(name, bounds) => write!(f, "{}: {}", name, display(bounds, " + ")),
             Types::GenericType(types, generics) => {
                 write!(f, "{}<{}>", types, display_parenless(generics, ", "))
@@ -519,6 +785,14 @@ impl Display for FinalizedTypes {
             FinalizedTypes::Struct(structure) => write!(f, "{}", structure.data.name),
             FinalizedTypes::Reference(structure) => write!(f, "{}", structure),
             FinalizedTypes::Array(inner) => write!(f, "[{}]", inner),
+            FinalizedTypes::Function(arguments, returning) => {
+                write!(f, "fn({})", display_function_arguments(arguments))?;
+                if let Some(returning) = returning {
+                    write!(f, " -> {}", returning)?;
+                }
+                Ok(())
+            }
+            FinalizedTypes::Tuple(types) => write!(f, "({})", display_function_arguments(types)),
             FinalizedTypes::Generic(name, bounds) => {
                 write!(f, "{}: {}", name, display(bounds, " + "))
             }
@@ -538,3 +812,8 @@ impl PartialEq for FinalizedTypes {
         });
     }
 }
+
+/// Displays the arguments of a function type or the values of a tuple, separated by commas
+fn display_function_arguments<T: Display>(arguments: &[T]) -> String {
+    return arguments.iter().map(|argument| argument.to_string()).collect::<Vec<_>>().join(", ");
+}
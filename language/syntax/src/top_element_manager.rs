@@ -8,12 +8,12 @@ use std::task::{Context, Poll, Waker};
 
 use data::tokens::Span;
 
-use crate::async_util::NameResolver;
+use crate::async_util::{wait_on, NameResolver};
 use crate::errors::ParsingError;
 use crate::program::code::FinalizedEffects;
 use crate::program::function::FunctionData;
 use crate::program::syntax::Syntax;
-use crate::program::types::FinalizedTypes;
+use crate::program::types::{FinalizedTypes, Types};
 use crate::{FinishedTraitImplementor, TopElement};
 
 /// The async manager, just stores basic information about the current parsing state.
@@ -29,6 +29,8 @@ pub struct GetterManager {
     pub target: String,
     /// Waker to wake when the target method is found
     pub target_waker: Option<Waker>,
+    /// If every function has finished being checked
+    pub checked: bool,
 }
 
 /// Waits for an implementation of the type
@@ -65,6 +67,7 @@ impl Future for ImplWaiter {
                         cx.waker().wake_by_ref();
                     }
                     locked.async_manager.impl_waiters.push(cx.waker().clone());
+                    wait_on(format!("impl {} for {}", self.trait_type, self.base_type), &self.error.span);
                     Poll::Pending
                 }
             }
@@ -95,8 +98,15 @@ impl<F: AsyncFnMut(Arc<FinishedTraitImplementor>, Arc<FunctionData>) -> Result<F
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let finished = self.syntax.lock().unwrap().finished_impls();
-        let value =
-            pin!(find_trait_implementation(&self.syntax, &*self.resolver, &self.method, &self.return_type)).poll(cx).clone();
+        let value = pin!(find_trait_implementation(
+            &self.syntax,
+            &*self.resolver,
+            &self.method,
+            &self.return_type,
+            &self.error.span
+        ))
+        .poll(cx)
+        .clone();
         return match value {
             Poll::Ready(inner) => match inner {
                 Ok(inner) => {
@@ -142,30 +152,45 @@ pub async fn find_trait_implementation(
     resolver: &dyn NameResolver,
     method: &String,
     return_type: &FinalizedTypes,
+    span: &Span,
 ) -> Result<Option<Vec<(Arc<FinishedTraitImplementor>, Vec<Arc<FunctionData>>)>>, ParsingError> {
     let mut output = Vec::default();
 
+    let mut traits = Vec::default();
     for import in resolver.imports() {
         if let Ok(value) = Syntax::get_struct(
             syntax.clone(),
-            Span::default(),
+            span.clone(),
             import.split("::").last().unwrap().to_string(),
             resolver.boxed_clone(),
             vec![],
         )
         .await
         {
-            let value = value.finalize(syntax.clone()).await;
-            if let Some(value) = Syntax::get_implementation_methods(&syntax, &return_type, &value).await {
-                for (types, functions) in &value {
-                    for temp in functions {
-                        let mut current = vec![];
-                        if &temp.name.split("::").last().unwrap() == method {
-                            current.push(temp.clone());
-                        }
-                        if !current.is_empty() {
-                            output.push((types.clone(), current))
-                        }
+            traits.push(value);
+        } else {
+            // Module imports, like "import list;", bring in every trait declared in the module
+            let prefix = import.clone() + "::";
+            let locked = syntax.lock().unwrap();
+            for (name, structure) in &locked.structures.types {
+                if structure.is_trait() && name.strip_prefix(&prefix).is_some_and(|name| !name.contains("::")) {
+                    traits.push(Types::Struct(structure.clone()));
+                }
+            }
+        }
+    }
+
+    for value in traits {
+        let value = value.finalize(syntax.clone()).await;
+        if let Some(value) = Syntax::get_implementation_methods(&syntax, &return_type, &value).await {
+            for (types, functions) in &value {
+                for temp in functions {
+                    let mut current = vec![];
+                    if &temp.name.split("::").last().unwrap() == method {
+                        current.push(temp.clone());
+                    }
+                    if !current.is_empty() {
+                        output.push((types.clone(), current))
                     }
                 }
             }
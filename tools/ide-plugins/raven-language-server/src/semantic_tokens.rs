@@ -84,6 +84,10 @@ fn get_token(last: &TokenTypes, token_type: &TokenTypes) -> u32 {
         | TokenTypes::Else
         | TokenTypes::Do
         | TokenTypes::While
+        | TokenTypes::Switch
+        | TokenTypes::Break
+        | TokenTypes::Continue
+        | TokenTypes::Mut
         | TokenTypes::In => SemanticTokenTypes::Keyword,
         TokenTypes::Comment => SemanticTokenTypes::Comment,
         TokenTypes::ImportStart | TokenTypes::Return | TokenTypes::New | TokenTypes::FunctionStart | TokenTypes::Let => {
@@ -1,16 +1,55 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::AtomicPtr;
 
-fn fibonacci(n: u64) -> u64 {
-    match n {
-        0 => 1,
-        1 => 1,
-        n => fibonacci(n - 1) + fibonacci(n - 2),
-    }
-}
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use data::{Arguments, CompilerArguments, Optimization, RunnerSettings};
+use parser::FileSourceSet;
+
+/// Benchmarks a recursive Raven fibonacci, which is dominated by primitive math.
+/// Compiling and running are benchmarked separately, so changes to the generated code aren't hidden by compile times.
+fn fibonacci_benchmark(c: &mut Criterion) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let output = root.join("target").join(format!("recursion{}", env::consts::EXE_SUFFIX));
+    let arguments = Arguments::build_args(
+        false,
+        RunnerSettings {
+            sources: vec![
+                Box::new(FileSourceSet { root: root.join("benches/raven/recursion.rv") }),
+                Box::new(FileSourceSet { root: root.join("../../lib/core/src") }),
+            ],
+            compiler_arguments: CompilerArguments {
+                compiler: "llvm".to_string(),
+                target: "recursion::bench".to_string(),
+                temp_folder: root.join("target"),
+                output: Some(output.clone()),
+                // Benchmarks should match a release build
+                optimization: Optimization::Aggressive,
+                emit: Vec::default(),
+                debug_info: false,
+            },
+        },
+    );
 
-fn criterion_benchmark(c: &mut Criterion) {
-    c.bench_function("fib 20", |b| b.iter(|| fibonacci(black_box(20))));
+    let mut group = c.benchmark_group("raven");
+    group.sample_size(10);
+    // Also builds the executable the run benchmark uses
+    group.bench_function("fibonacci 30 compile", |bencher| {
+        bencher.iter(|| {
+            let result = arguments.cpu_runtime.block_on(runner::runner::run::<AtomicPtr<bool>>(&arguments));
+            assert!(result.is_ok(), "Failed to build the fibonacci benchmark!");
+        })
+    });
+    group.bench_function("fibonacci 30 run", |bencher| {
+        bencher.iter(|| {
+            let status = Command::new(&output).status().expect("Failed to start the fibonacci benchmark!");
+            assert!(status.success(), "Failed to run the fibonacci benchmark!");
+        })
+    });
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, fibonacci_benchmark);
 criterion_main!(benches);
@@ -1,13 +1,14 @@
 use core::fmt::Debug;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicPtr, Ordering};
 use std::{env, path};
 
 use include_dir::{include_dir, Dir, DirEntry, File};
 
 use data::tokens::{Token, TokenTypes};
-use data::{Arguments, CompilerArguments, RavenExtern, Readable, RunnerSettings, SourceSet};
+use data::{Arguments, CompilerArguments, Emit, Optimization, RavenExtern, Readable, RunnerSettings, SourceSet};
 use parser::tokens::tokenizer::Tokenizer;
 use parser::FileSourceSet;
 use syntax::errors::ParsingError;
@@ -31,34 +32,48 @@ static STD_MACOS: Dir = include_dir!("lib/std/macos/src");
 /// The Magpie classes
 static MAGPIE: Dir = include_dir!("tools/magpie/lib/src");
 
-/// Finds the Raven project/file and runs it
+/// Finds the Raven project/file and runs it, or builds it into an executable with the build command
 fn main() {
-    let args = env::args().collect::<Vec<_>>();
+    let mut args = env::args().collect::<Vec<_>>();
+    let optimization = get_optimization(&mut args);
+    let emit = get_emit(&mut args);
+    let debug_info = get_debug_info(&mut args);
+    let compiler = get_compiler(&mut args);
+
+    // The build command outputs an executable instead of running the program
+    let building = args.len() > 1 && args[1] == "build";
+    if building {
+        args.remove(1);
+    }
 
     if args.len() == 2 {
         let target = env::current_dir().unwrap().join(args[1].clone());
+        let name = args[1].clone().split(path::MAIN_SEPARATOR).last().unwrap().replace(".rv", "");
         let mut arguments = Arguments::build_args(
             false,
             RunnerSettings {
                 sources: vec![],
                 compiler_arguments: CompilerArguments {
-                    target: format!(
-                        "{}::main",
-                        args[1].clone().split(path::MAIN_SEPARATOR).last().unwrap().replace(".rv", "")
-                    ),
-                    compiler: "llvm".to_string(),
+                    target: format!("{}::main", name),
+                    compiler,
                     temp_folder: env::current_dir().unwrap().join("target"),
+                    output: if building { Some(get_output(&name)) } else { None },
+                    optimization,
+                    emit,
+                    debug_info,
                 },
             },
         );
 
-        println!(
-            "Building and running {}...",
-            args[1].clone().split(path::MAIN_SEPARATOR).last().unwrap().replace(".rv", "")
-        );
-        match build::<()>(&mut arguments, vec![Box::new(FileSourceSet { root: target })]) {
-            _ => return,
+        if building {
+            println!("Building {}...", name);
+        } else {
+            println!("Building and running {}...", name);
+        }
+        if build::<()>(&mut arguments, vec![Box::new(FileSourceSet { root: target })]).is_ok() && building {
+            println!("Built {}", arguments.runner_settings.compiler_arguments.output.as_ref().unwrap().display());
         }
+        return;
     } else if args.len() > 2 {
         panic!("Unknown extra arguments! {:?}", args);
     }
@@ -78,6 +93,10 @@ fn main() {
                 target: "build::project".to_string(),
                 compiler: "llvm".to_string(),
                 temp_folder: env::current_dir().unwrap().join("target"),
+                output: None,
+                optimization: Optimization::None,
+                emit: Vec::default(),
+                debug_info: false,
             },
         },
     );
@@ -98,6 +117,13 @@ fn main() {
     };
 
     arguments.runner_settings.compiler_arguments.target = "main::main".to_string();
+    arguments.runner_settings.compiler_arguments.optimization = optimization;
+    arguments.runner_settings.compiler_arguments.emit = emit;
+    arguments.runner_settings.compiler_arguments.debug_info = debug_info;
+    arguments.runner_settings.compiler_arguments.compiler = compiler;
+    if building {
+        arguments.runner_settings.compiler_arguments.output = Some(get_output(&project.name));
+    }
 
     let source = env::current_dir().unwrap().join("src");
 
@@ -105,29 +131,82 @@ fn main() {
         panic!("Source folder (src) not found!");
     }
 
-    println!("Building and running {}...", project.name);
-    match build::<()>(&mut arguments, vec![Box::new(FileSourceSet { root: source })]) {
-        _ => {}
+    if building {
+        println!("Building {}...", project.name);
+    } else {
+        println!("Building and running {}...", project.name);
+    }
+    if build::<()>(&mut arguments, vec![Box::new(FileSourceSet { root: source })]).is_ok() && building {
+        println!("Built {}", arguments.runner_settings.compiler_arguments.output.as_ref().unwrap().display());
     }
 }
 
+/// Removes the optimization flag, like -O2, from the arguments and returns its level
+fn get_optimization(args: &mut Vec<String>) -> Optimization {
+    let mut optimization = Optimization::default();
+    args.retain(|arg| {
+        if !arg.starts_with("-O") {
+            return true;
+        }
+        optimization = match Optimization::from_flag(arg) {
+            Some(found) => found,
+            None => panic!("Unknown optimization level {}, expected -O0, -O1, -O2, -O3 or -Os", arg),
+        };
+        return false;
+    });
+    return optimization;
+}
+
+/// Removes the emit flag, like --emit=tokens,llvm-ir, from the arguments and returns the stages to emit
+fn get_emit(args: &mut Vec<String>) -> Vec<Emit> {
+    let mut emit = Vec::default();
+    args.retain(|arg| {
+        let stages = match arg.strip_prefix("--emit=") {
+            Some(stages) => stages,
+            None => return true,
+        };
+        for stage in stages.split(',') {
+            match Emit::from_name(stage) {
+                Some(found) => emit.push(found),
+                None => panic!("Unknown emit stage {}, expected tokens, ast, checked, llvm-ir, llvm-bc or asm", stage),
+            }
+        }
+        return false;
+    });
+    return emit;
+}
+
+/// Removes the debug info flag, -g, from the arguments and returns if it was there
+fn get_debug_info(args: &mut Vec<String>) -> bool {
+    let length = args.len();
+    args.retain(|arg| arg != "-g");
+    return args.len() != length;
+}
+
+/// Removes the compiler flag, like --compiler=interpreter, from the arguments and returns the compiler's name
+fn get_compiler(args: &mut Vec<String>) -> String {
+    let mut compiler = "llvm".to_string();
+    args.retain(|arg| match arg.strip_prefix("--compiler=") {
+        Some(found) => {
+            compiler = found.to_string();
+            false
+        }
+        None => true,
+    });
+    return compiler;
+}
+
+/// Gets the path of the executable to build for the given name
+fn get_output(name: &str) -> PathBuf {
+    return env::current_dir().unwrap().join("target").join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+}
+
 /// Builds a Raven project, adding the needed dependencies
 pub fn build<T: RavenExtern + 'static>(
     arguments: &mut Arguments,
     mut source: Vec<Box<dyn SourceSet>>,
 ) -> Result<Option<T>, ()> {
-    let platform_std = match env::consts::OS {
-        "windows" => &STD_WINDOWS,
-        "linux" => &STD_LINUX,
-        "macos" => &STD_MACOS,
-        _ => panic!("Unsupported platform {}!", env::consts::OS),
-    };
-
-    source.push(Box::new(InnerSourceSet { set: &STD_UNIVERSAL }));
-    source.push(Box::new(InnerSourceSet { set: platform_std }));
-    source.push(Box::new(InnerSourceSet { set: &CORE }));
-
-    arguments.runner_settings.sources = source.iter().map(|inner| inner.cloned()).collect::<Vec<_>>();
+    add_dependencies(arguments, &mut source);
 
     let value = run::<T>(&arguments);
     return match value {
@@ -142,6 +221,22 @@ pub fn build<T: RavenExtern + 'static>(
     };
 }
 
+/// Adds the standard library and core to the sources
+fn add_dependencies(arguments: &mut Arguments, source: &mut Vec<Box<dyn SourceSet>>) {
+    let platform_std = match env::consts::OS {
+        "windows" => &STD_WINDOWS,
+        "linux" => &STD_LINUX,
+        "macos" => &STD_MACOS,
+        _ => panic!("Unsupported platform {}!", env::consts::OS),
+    };
+
+    source.push(Box::new(InnerSourceSet { set: &STD_UNIVERSAL }));
+    source.push(Box::new(InnerSourceSet { set: platform_std }));
+    source.push(Box::new(InnerSourceSet { set: &CORE }));
+
+    arguments.runner_settings.sources = source.iter().map(|inner| inner.cloned()).collect::<Vec<_>>();
+}
+
 /// Runs Raven and blocks until a result is gotten
 fn run<T: RavenExtern + 'static>(arguments: &Arguments) -> Result<Option<T>, Vec<ParsingError>> {
     let result = arguments.cpu_runtime.block_on(runner::runner::run::<AtomicPtr<T::Input>>(&arguments))?;
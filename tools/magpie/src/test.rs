@@ -5,23 +5,89 @@ use std::path;
 
 #[cfg(test)]
 mod test {
-    use crate::build;
     use crate::test::InnerFileSourceSet;
-    use data::{Arguments, CompilerArguments, RunnerSettings};
+    use crate::{add_dependencies, build, run, FileWrapper, InnerSourceSet};
+    use data::{Arguments, CompilerArguments, Optimization, Readable, RunnerSettings, SourceSet};
     use include_dir::{include_dir, Dir, DirEntry};
     use std::{env, path};
 
     /// Tests directory
     static TESTS: Dir = include_dir!("lib/test/test");
+    /// Tests that fail to compile, starting with a comment holding the expected error
+    static ERRORS: Dir = include_dir!("lib/test/error");
 
-    /// Main test
+    /// Runs the tests on the LLVM compiler
     #[test]
     pub fn test_magpie() {
-        test_recursive(&TESTS);
+        test_recursive(&TESTS, "llvm");
     }
 
-    /// Recursively searches for files in the test folder to run as a test
-    fn test_recursive(dir: &'static Dir<'_>) {
+    /// Runs the tests on the interpreter, which should give the same results as the LLVM compiler
+    #[test]
+    pub fn test_interpreter() {
+        test_recursive(&TESTS, "interpreter");
+    }
+
+    /// Makes sure invalid code fails with the expected error.
+    /// A second comment, like "// at 300", is the code the error should point at.
+    /// Folders are tests split between files, with the comments and the test function in test.rv.
+    #[test]
+    pub fn test_errors() {
+        for entry in ERRORS.entries() {
+            let (file, source): (_, Box<dyn SourceSet>) = match entry {
+                DirEntry::File(file) => (file, Box::new(InnerFileSourceSet { set: file })),
+                DirEntry::Dir(dir) => {
+                    (dir.get_file(dir.path().join("test.rv")).unwrap(), Box::new(InnerSourceSet { set: dir }))
+                }
+            };
+            let path = file.path().to_str().unwrap().replace(path::MAIN_SEPARATOR, "::");
+            println!("Running {}", path);
+            let path = format!("{}::test", &path[0..path.len() - 3]);
+            let contents = file.contents_utf8().unwrap();
+            let mut lines = contents.lines();
+            let expected = lines.next().unwrap().trim_start_matches("//").trim();
+            let pointing = lines.next().and_then(|line| line.strip_prefix("// at "));
+
+            let mut arguments = get_arguments(&path, "llvm");
+            add_dependencies(&mut arguments, &mut vec![source]);
+            match run::<bool>(&arguments) {
+                Ok(_) => panic!("Test {} compiled!", path),
+                Err(errors) => {
+                    let found = errors.iter().map(|error| error.message.to_string()).collect::<Vec<_>>();
+                    // Anything waiting on the code with the error shouldn't report another error
+                    assert!(found.len() == 1 && found[0] == expected, "Test {} failed with {:?}!", path, found);
+
+                    if let Some(pointing) = pointing {
+                        let token = &FileWrapper { file }.read()[errors[0].span.start];
+                        let code = contents[token.start_offset..token.end_offset].trim();
+                        assert_eq!(code, pointing.trim(), "Test {} points at the wrong code!", path);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gets the arguments to compile a test with the compiler
+    fn get_arguments(target: &str, compiler: &str) -> Arguments {
+        return Arguments::build_args(
+            false,
+            RunnerSettings {
+                sources: vec![],
+                compiler_arguments: CompilerArguments {
+                    compiler: compiler.to_string(),
+                    target: target.to_string(),
+                    temp_folder: env::current_dir().unwrap().join("target"),
+                    output: None,
+                    optimization: Optimization::None,
+                    emit: Vec::default(),
+                    debug_info: false,
+                },
+            },
+        );
+    }
+
+    /// Recursively searches for files in the test folder to run as a test with the compiler
+    fn test_recursive(dir: &'static Dir<'_>, compiler: &str) {
         for entry in dir.entries() {
             match entry {
                 DirEntry::File(file) => {
@@ -30,19 +96,9 @@ mod test {
                         println!("File {} doesn't have the right file extension!", path);
                         continue;
                     }
-                    println!("Running {}", path);
+                    println!("Running {} with {}", path, compiler);
                     let path = format!("{}::test", &path[0..path.len() - 3]);
-                    let mut arguments = Arguments::build_args(
-                        false,
-                        RunnerSettings {
-                            sources: vec![],
-                            compiler_arguments: CompilerArguments {
-                                compiler: "llvm".to_string(),
-                                target: path.clone(),
-                                temp_folder: env::current_dir().unwrap().join("target"),
-                            },
-                        },
-                    );
+                    let mut arguments = get_arguments(&path, compiler);
 
                     match build::<bool>(&mut arguments, vec![Box::new(InnerFileSourceSet { set: file })]) {
                         Ok(inner) => match inner {
@@ -57,7 +113,7 @@ mod test {
                     }
                 }
                 DirEntry::Dir(dir) => {
-                    test_recursive(dir);
+                    test_recursive(dir, compiler);
                 }
             }
         }